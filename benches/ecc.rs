@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use qrgen::{ErrorCorrection, Options};
+
+// Read the module matrix back out of a rendered image
+fn get_matrix(input: &str) -> (usize, Vec<u8>) {
+    let image = qrgen::render_qr_code(input, ErrorCorrection::High, &Options::default()).unwrap();
+    let size = (image.width() as usize - 80) / 10;
+    let matrix = (0..size * size)
+        .map(|i| image.get_pixel(40 + (i % size) as u32 * 10, 40 + (i / size) as u32 * 10)[0])
+        .collect();
+    (size, matrix)
+}
+
+fn error_correction(c: &mut Criterion) {
+    // Large enough to need version 40 with 81 blocks of 30 error correction codewords
+    let input = "A".repeat(1800);
+    c.bench_function("encode version 40", |b| {
+        b.iter(|| {
+            qrgen::render_qr_code(&input, ErrorCorrection::High, &Options::default()).unwrap()
+        })
+    });
+
+    let (size, mut matrix) = get_matrix(&input);
+    c.bench_function("decode version 40", |b| {
+        b.iter(|| qrgen::decode_matrix(size, &matrix).unwrap())
+    });
+
+    // Flip a run of modules in the bottom right corner so that the decoder
+    // has to locate and correct errors
+    for y in size - 20..size - 9 {
+        for x in size - 20..size - 9 {
+            matrix[y * size + x] ^= 0xFF;
+        }
+    }
+    c.bench_function("decode damaged version 40", |b| {
+        b.iter(|| qrgen::decode_matrix(size, &matrix).unwrap())
+    });
+}
+
+criterion_group!(benches, error_correction);
+criterion_main!(benches);
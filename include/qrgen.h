@@ -0,0 +1,180 @@
+#ifndef QRGEN_H
+#define QRGEN_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit it by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define QR_EC_LOW 0
+
+#define QR_EC_MEDIUM 1
+
+#define QR_EC_QUARTILE 2
+
+#define QR_EC_HIGH 3
+
+#define QR_FORMAT_PNG 0
+
+#define QR_FORMAT_SVG 1
+
+#define QR_FORMAT_PDF 2
+
+#define QR_FORMAT_TEXT 3
+
+/**
+ * The result of every call that can fail
+ */
+typedef enum QrStatus {
+  QR_OK = 0,
+  /**
+   * A pointer that has to be set was null
+   */
+  QR_NULL_POINTER = 1,
+  /**
+   * The input isn't valid UTF-8
+   */
+  QR_INVALID_UTF8 = 2,
+  /**
+   * The input doesn't fit in a qr code with these options
+   */
+  QR_DATA_TOO_LONG = 3,
+  QR_INVALID_VERSION = 4,
+  QR_INVALID_MASK = 5,
+  /**
+   * The error correction level, scale or format isn't one of the allowed values
+   */
+  QR_INVALID_OPTION = 6,
+  /**
+   * The output doesn't fit in the buffer, and the length it needs has been written
+   */
+  QR_BUFFER_TOO_SMALL = 7,
+  /**
+   * This build of the library can't write the format
+   */
+  QR_UNSUPPORTED_FORMAT = 8,
+  /**
+   * Something went wrong inside the library
+   */
+  QR_INTERNAL_ERROR = 9,
+} QrStatus;
+
+/**
+ * An encoded qr code that can be drawn in different formats
+ */
+typedef struct QrCode QrCode;
+
+/**
+ * Options for encoding and drawing, see `qr_default_options`
+ */
+typedef struct QrOptions {
+  /**
+   * One of the QR_EC_* levels
+   */
+  uint8_t ec;
+  /**
+   * Version from 1 to 40, or 0 for the smallest one that fits
+   */
+  uint8_t version;
+  /**
+   * Mask pattern from 0 to 7, or -1 for the one with the lowest penalty
+   */
+  int8_t mask;
+  /**
+   * Draw light modules on a dark background
+   */
+  bool inverted;
+  /**
+   * Flip the qr code horizontally
+   */
+  bool mirrored;
+  /**
+   * Width of each module in pixels, or in points for pdfs, from 1 to 100
+   */
+  uint32_t scale;
+  /**
+   * Width of the border around the symbol in modules
+   */
+  uint32_t quiet_zone;
+  uint8_t foreground[3];
+  uint8_t background[3];
+} QrOptions;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Get the default options: medium error correction, the smallest version,
+ * the best mask, a scale of 10 and a quiet zone of 4 modules, in black on white
+ */
+QrOptions qr_default_options(void);
+
+/**
+ * Encode `length` bytes of UTF-8 text and store the qr code in `code`,
+ * which has to be released with `qr_free`. The default options are used
+ * when `options` is null, and `code` is set to null when encoding fails.
+ *
+ * # Safety
+ * `data` has to point to `length` readable bytes, `options` has to be null
+ * or point to a `QrOptions`, and `code` has to point to writable memory.
+ */
+QrStatus qr_create(const uint8_t *data, size_t length, const QrOptions *options, QrCode **code);
+
+/**
+ * Get the width of the symbol in modules, not counting the quiet zone,
+ * or 0 when `code` is null
+ *
+ * # Safety
+ * `code` has to be null or come from `qr_create`.
+ */
+size_t qr_size(const QrCode *code);
+
+/**
+ * Write the modules row by row to `buffer`, with 1 for a dark module and 0
+ * for a light one. The buffer needs room for `qr_size(code)` squared bytes.
+ *
+ * # Safety
+ * `code` has to come from `qr_create` and `buffer` has to point to
+ * `capacity` writable bytes.
+ */
+QrStatus qr_get_modules(const QrCode *code, uint8_t *buffer, size_t capacity);
+
+/**
+ * Draw the qr code in one of the QR_FORMAT_* formats into `buffer`. The
+ * length of the output is stored in `written` even when the buffer is too
+ * small, so passing a null buffer finds out how much room is needed. Text
+ * and svg output is UTF-8 without a terminating nul.
+ *
+ * # Safety
+ * `code` has to come from `qr_create`, `buffer` has to be null or point to
+ * `capacity` writable bytes, and `written` has to be null or point to
+ * writable memory.
+ */
+QrStatus qr_render(const QrCode *code,
+                   uint32_t format,
+                   uint8_t *buffer,
+                   size_t capacity,
+                   size_t *written);
+
+/**
+ * Release a qr code from `qr_create`. Passing null does nothing.
+ *
+ * # Safety
+ * `code` has to be null or come from `qr_create`, and can't be used afterwards.
+ */
+void qr_free(QrCode *code);
+
+/**
+ * Describe a status as a nul terminated string that lives for the whole program
+ */
+const char *qr_status_message(int32_t status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* QRGEN_H */
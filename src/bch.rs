@@ -0,0 +1,248 @@
+// Format and version information are protected by BCH codes. The data bits
+// are followed by the remainder of dividing them by a generator polynomial,
+// so the codewords are all far apart and a few bit errors can be corrected
+// by picking the nearest valid codeword.
+
+use crate::encoder::ErrorCorrection;
+
+// x^10 + x^8 + x^5 + x^4 + x^2 + x + 1
+const FORMAT_GENERATOR: u32 = 0x537;
+// x^12 + x^11 + x^10 + x^9 + x^8 + x^5 + x^2 + 1
+const VERSION_GENERATOR: u32 = 0x1F25;
+// Masked into the format information so it's never all zeros
+const FORMAT_MASK: u16 = 0x5412;
+
+// Append the remainder of dividing the data by the generator polynomial
+const fn bch_encode(data: u32, generator: u32, degree: u32) -> u32 {
+    let mut remainder = data << degree;
+    let mut bit = 31;
+    while bit >= degree {
+        if remainder & (1 << bit) != 0 {
+            remainder ^= generator << (bit - degree);
+        }
+        bit -= 1;
+    }
+    data << degree | remainder
+}
+
+// Build the BCH(15, 5) format information codes. Index by the 2 error
+// correction level bits then the 3 mask bits.
+const fn build_format_info() -> [u16; 32] {
+    let mut codes = [0; 32];
+    let mut data = 0;
+    while data < 32 {
+        codes[data] = bch_encode(data as u32, FORMAT_GENERATOR, 10) as u16 ^ FORMAT_MASK;
+        data += 1;
+    }
+    codes
+}
+
+// Build the BCH(18, 6) version information codes for versions 7 to 40
+const fn build_version_info() -> [u32; 34] {
+    let mut codes = [0; 34];
+    let mut i = 0;
+    while i < 34 {
+        codes[i] = bch_encode(i as u32 + 7, VERSION_GENERATOR, 12);
+        i += 1;
+    }
+    codes
+}
+
+const FORMAT_INFO: [u16; 32] = build_format_info();
+const VERSION_INFO: [u32; 34] = build_version_info();
+
+// The error correction level bits aren't in the same order as the levels
+const LEVEL_BITS: [usize; 4] = [0b01, 0b00, 0b11, 0b10];
+const LEVELS: [ErrorCorrection; 4] = [
+    ErrorCorrection::Medium,
+    ErrorCorrection::Low,
+    ErrorCorrection::High,
+    ErrorCorrection::Quartile,
+];
+
+/// Get the 15 bit format information for an error correction level and mask pattern
+pub fn get_format_info(level: ErrorCorrection, mask: usize) -> u16 {
+    FORMAT_INFO[LEVEL_BITS[level as usize] << 3 | mask]
+}
+
+/// Get the 18 bit version information, which only versions 7 to 40 have
+pub fn get_version_info(version: usize) -> Option<u32> {
+    VERSION_INFO.get(version.checked_sub(7)?).copied()
+}
+
+/// Find the error correction level and mask pattern of the nearest
+/// valid format information, along with the number of bits that differ
+pub fn decode_format_info(bits: u16) -> (ErrorCorrection, usize, u32) {
+    let (data, distance) = FORMAT_INFO
+        .iter()
+        .enumerate()
+        .map(|(data, &code)| (data, (code ^ bits).count_ones()))
+        .min_by_key(|&(_, distance)| distance)
+        .unwrap();
+    (LEVELS[data >> 3], data & 7, distance)
+}
+
+/// Find the version of the nearest valid version information,
+/// along with the number of bits that differ
+pub fn decode_version_info(bits: u32) -> (usize, u32) {
+    let (i, distance) = VERSION_INFO
+        .iter()
+        .enumerate()
+        .map(|(i, &code)| (i, (code ^ bits).count_ones()))
+        .min_by_key(|&(_, distance)| distance)
+        .unwrap();
+    (i + 7, distance)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::bch::*;
+    use crate::encoder::ErrorCorrection;
+
+    // The hand typed tables these codes used to be looked up from.
+    // Index by error correction level, then by mask pattern index.
+    const FORMAT_INFO_BITS: [[[u8; 15]; 8]; 4] = [
+        [
+            [1, 1, 1, 0, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0],
+            [1, 1, 1, 0, 0, 1, 0, 1, 1, 1, 1, 0, 0, 1, 1],
+            [1, 1, 1, 1, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0],
+            [1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1],
+            [1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 1, 1, 1, 1],
+            [1, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 0, 0, 0],
+            [1, 1, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1],
+            [1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 0],
+        ],
+        [
+            [1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0],
+            [1, 0, 1, 0, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 1],
+            [1, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1, 0, 0],
+            [1, 0, 1, 1, 0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1],
+            [1, 0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 0, 1],
+            [1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0],
+            [1, 0, 0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 1, 1, 1],
+            [1, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0],
+        ],
+        [
+            [0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 1, 1, 1, 1, 1],
+            [0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0, 0, 0],
+            [0, 1, 1, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1],
+            [0, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0],
+            [0, 1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0, 0],
+            [0, 1, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1],
+            [0, 1, 0, 1, 1, 1, 0, 1, 1, 0, 1, 1, 0, 1, 0],
+            [0, 1, 0, 1, 0, 1, 1, 1, 1, 1, 0, 1, 1, 0, 1],
+        ],
+        [
+            [0, 0, 1, 0, 1, 1, 0, 1, 0, 0, 0, 1, 0, 0, 1],
+            [0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 0],
+            [0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1],
+            [0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 1, 0, 0, 0, 0],
+            [0, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0],
+            [0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 1, 0, 1, 0, 1],
+            [0, 0, 0, 1, 1, 0, 1, 0, 0, 0, 0, 1, 1, 0, 0],
+            [0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 1, 1, 0, 1, 1],
+        ],
+    ];
+
+    // Version information bit strings for versions 7 to 40
+    const VERSION_BITSTRINGS: [[u8; 18]; 34] = [
+        [0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0, 0],
+        [0, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0],
+        [0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1],
+        [0, 0, 1, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1],
+        [0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 1, 0],
+        [0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0],
+        [0, 0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1],
+        [0, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 1],
+        [0, 0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0],
+        [0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0, 0],
+        [0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1],
+        [0, 1, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 1],
+        [0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0],
+        [0, 1, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1, 0],
+        [0, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 0, 0, 0, 0, 1, 1],
+        [0, 1, 0, 1, 1, 0, 1, 0, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1],
+        [0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 1, 0, 0],
+        [0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0, 0],
+        [0, 1, 1, 0, 0, 1, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1],
+        [0, 1, 1, 0, 1, 0, 1, 1, 1, 1, 1, 0, 1, 0, 1, 0, 1, 1],
+        [0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0],
+        [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0],
+        [0, 1, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1],
+        [0, 1, 1, 1, 1, 0, 1, 1, 0, 1, 0, 1, 1, 1, 0, 1, 0, 1],
+        [0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0, 0],
+        [1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 0, 1, 0, 1],
+        [1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0, 0, 0],
+        [1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1, 0],
+        [1, 0, 0, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1],
+        [1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1],
+        [1, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 1, 0],
+        [1, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0, 0],
+        [1, 0, 0, 1, 1, 1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1],
+        [1, 0, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 0, 1, 0, 0, 1],
+    ];
+
+    fn to_bits<const N: usize>(value: u32) -> [u8; N] {
+        std::array::from_fn(|i| (value >> (N - 1 - i) & 1) as u8)
+    }
+
+    const LEVELS: [ErrorCorrection; 4] = [
+        ErrorCorrection::Low,
+        ErrorCorrection::Medium,
+        ErrorCorrection::Quartile,
+        ErrorCorrection::High,
+    ];
+
+    #[test]
+    fn test_against_tables() {
+        for level in LEVELS {
+            for (mask, expected) in FORMAT_INFO_BITS[level as usize].iter().enumerate() {
+                let info = get_format_info(level, mask);
+                assert_eq!(&to_bits(info as u32), expected);
+            }
+        }
+
+        for version in 7..=40 {
+            let info = get_version_info(version).unwrap();
+            assert_eq!(to_bits(info), VERSION_BITSTRINGS[version - 7]);
+        }
+        assert_eq!(get_version_info(6), None);
+        assert_eq!(get_version_info(41), None);
+    }
+
+    #[test]
+    fn test_format_info_decoding() {
+        for level in LEVELS {
+            for mask in 0..8 {
+                let info = get_format_info(level, mask);
+                assert_eq!(decode_format_info(info), (level, mask, 0));
+
+                // Any 3 bit errors can be corrected
+                for i in 0..15 {
+                    for j in 0..i {
+                        for k in 0..j {
+                            let damaged = info ^ (1 << i) ^ (1 << j) ^ (1 << k);
+                            assert_eq!(decode_format_info(damaged), (level, mask, 3));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_version_info_decoding() {
+        for version in 7..=40 {
+            let info = get_version_info(version).unwrap();
+            assert_eq!(decode_version_info(info), (version, 0));
+            for i in 0..18 {
+                for j in 0..i {
+                    for k in 0..j {
+                        let damaged = info ^ (1 << i) ^ (1 << j) ^ (1 << k);
+                        assert_eq!(decode_version_info(damaged), (version, 3));
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+pub mod batch;
+pub mod decode;
+pub mod encode;
+pub mod inspect;
+pub mod serve;
+pub mod sheet;
+
+use qrgen::{EncodeError, EncodingMode, ErrorCorrection};
+use std::process::ExitCode;
+
+// Exit codes, where 2 matches the one clap uses for invalid arguments
+const EXIT_IO: u8 = 1;
+const EXIT_USAGE: u8 = 2;
+const EXIT_ENCODING: u8 = 3;
+const EXIT_NOT_FOUND: u8 = 4;
+const EXIT_ROWS_FAILED: u8 = 5;
+
+pub enum Failure {
+    Io(String),
+    Usage(String),
+    Encoding(EncodeError),
+    /// An image didn't have any qr codes that could be decoded
+    NotFound(String),
+    /// Some rows of a batch couldn't be generated
+    Rows(String),
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Failure::Io(message)
+            | Failure::Usage(message)
+            | Failure::NotFound(message)
+            | Failure::Rows(message) => write!(f, "{}", message),
+            Failure::Encoding(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Failure::Io(_) => EXIT_IO,
+            Failure::Usage(_) => EXIT_USAGE,
+            Failure::Encoding(_) => EXIT_ENCODING,
+            Failure::NotFound(_) => EXIT_NOT_FOUND,
+            Failure::Rows(_) => EXIT_ROWS_FAILED,
+        })
+    }
+}
+
+pub fn parse_level(level: &str) -> Result<ErrorCorrection, String> {
+    qrgen::parse_level(level).ok_or_else(|| "expected one of L, M, Q or H".to_string())
+}
+
+pub fn level_letter(level: ErrorCorrection) -> char {
+    match level {
+        ErrorCorrection::Low => 'L',
+        ErrorCorrection::Medium => 'M',
+        ErrorCorrection::Quartile => 'Q',
+        ErrorCorrection::High => 'H',
+    }
+}
+
+pub fn mode_name(mode: EncodingMode) -> &'static str {
+    match mode {
+        EncodingMode::Numeric => "numeric",
+        EncodingMode::Alphanumeric => "alphanumeric",
+        EncodingMode::Byte => "byte",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let message = || "message".to_string();
+        assert_eq!(Failure::Io(message()).exit_code(), ExitCode::from(1));
+        assert_eq!(Failure::Usage(message()).exit_code(), ExitCode::from(2));
+        assert_eq!(
+            Failure::Encoding(EncodeError::DataTooLong).exit_code(),
+            ExitCode::from(3)
+        );
+        assert_eq!(Failure::NotFound(message()).exit_code(), ExitCode::from(4));
+        assert_eq!(Failure::Rows(message()).exit_code(), ExitCode::from(5));
+    }
+
+    #[test]
+    fn test_level_parsing() {
+        assert_eq!(parse_level("q"), Ok(ErrorCorrection::Quartile));
+        assert_eq!(parse_level("H"), Ok(ErrorCorrection::High));
+        assert!(parse_level("X").is_err());
+        assert!(parse_level("").is_err());
+    }
+}
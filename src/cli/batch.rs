@@ -0,0 +1,479 @@
+use super::encode::{write_output, CaptionArgs, StyleArgs};
+use super::Failure;
+use clap::Args;
+use qrgen::QrCode;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// Generate a qr code for each row of a csv or json lines file
+#[derive(Args)]
+pub struct BatchArgs {
+    #[command(flatten)]
+    source: RowArgs,
+
+    /// Where to write each code, like "codes/{sku}.png", where {row} is the row number
+    #[arg(short, long)]
+    output: String,
+
+    /// Write the rows that failed to a csv file
+    #[arg(long)]
+    report: Option<String>,
+
+    /// Text to print below each code, like "{sku}"
+    #[arg(long)]
+    caption: Option<String>,
+
+    #[command(flatten)]
+    caption_style: CaptionArgs,
+
+    #[command(flatten)]
+    style: StyleArgs,
+}
+
+/// Where the rows come from and how to get the text to encode from each one
+#[derive(Args)]
+pub struct RowArgs {
+    /// A .csv file with a header row, or a .jsonl file with an object on each line
+    pub input: String,
+
+    /// Read the input as csv or jsonl instead of going by its extension
+    #[arg(long, value_parser = ["csv", "jsonl"])]
+    format: Option<String>,
+
+    /// Column holding the text to encode
+    #[arg(
+        long,
+        required_unless_present = "template",
+        conflicts_with = "template"
+    )]
+    column: Option<String>,
+
+    /// Build the text to encode from columns, like "https://example.com/{sku}"
+    #[arg(long)]
+    template: Option<String>,
+}
+
+pub type Row = HashMap<String, String>;
+
+/// A row, or why it couldn't be read
+pub type ParsedRow = Result<Row, String>;
+
+#[derive(Debug, PartialEq)]
+pub enum Part {
+    Text(String),
+    Column(String),
+}
+
+/// Split a template into text and {column} placeholders, where {{ and }} are literal braces
+pub fn parse_template(template: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed || name.is_empty() || name.contains('{') {
+                    return Err(format!("invalid placeholder in template {}", template));
+                }
+                parts.push(Part::Text(std::mem::take(&mut text)));
+                parts.push(Part::Column(name));
+            }
+            '}' => return Err(format!("unmatched }} in template {}", template)),
+            c => text.push(c),
+        }
+    }
+    parts.push(Part::Text(text));
+    Ok(parts)
+}
+
+/// Fill in the placeholders, passing each value through the escape function.
+/// {row} is the row number unless there's a column with that name.
+pub fn fill_template(
+    parts: &[Part],
+    row: &Row,
+    number: usize,
+    escape: fn(&str) -> String,
+) -> Result<String, String> {
+    let mut text = String::new();
+    for part in parts {
+        match part {
+            Part::Text(part) => text += part,
+            Part::Column(name) => match row.get(name) {
+                Some(value) => text += &escape(value),
+                None if name == "row" => text += &number.to_string(),
+                None => return Err(format!("there's no {} column", name)),
+            },
+        }
+    }
+    Ok(text)
+}
+
+// Stop values from creating files outside of the output directory
+fn escape_path(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if value == ".." {
+        return "__".to_string();
+    }
+    value
+}
+
+/// Split csv text into records, with quoted fields that can hold commas,
+/// line breaks and doubled quotes. Blank lines are skipped.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut was_quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                c => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() && !was_quoted => {
+                quoted = true;
+                was_quoted = true;
+            }
+            ',' => {
+                record.push(std::mem::take(&mut field));
+                was_quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                if !record.is_empty() || !field.is_empty() || was_quoted {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                was_quoted = false;
+            }
+            c => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err("a quoted field is never closed".to_string());
+    }
+    if !record.is_empty() || !field.is_empty() || was_quoted {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn read_csv_rows(text: &str) -> Result<(Vec<String>, Vec<ParsedRow>), String> {
+    let mut records = parse_csv(text)?.into_iter();
+    let headers: Vec<String> = records.next().ok_or("the csv file is empty")?;
+    let rows = records
+        .map(|record| {
+            if record.len() != headers.len() {
+                return Err(format!(
+                    "expected {} fields but found {}",
+                    headers.len(),
+                    record.len()
+                ));
+            }
+            Ok(headers.iter().cloned().zip(record).collect())
+        })
+        .collect();
+    Ok((headers, rows))
+}
+
+fn read_json_rows(text: &str) -> Vec<ParsedRow> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let value: serde_json::Value =
+                serde_json::from_str(line).map_err(|error| format!("invalid json: {}", error))?;
+            let object = value.as_object().ok_or("expected a json object")?;
+            let row = object.iter().map(|(key, value)| {
+                let value = match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    serde_json::Value::Null => String::new(),
+                    value => value.to_string(),
+                };
+                (key.clone(), value)
+            });
+            Ok(row.collect())
+        })
+        .collect()
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+struct Job {
+    number: usize,
+    payload: String,
+    path: String,
+    caption: Option<String>,
+}
+
+struct RowFailure {
+    number: usize,
+    path: String,
+    message: String,
+}
+
+fn write_report(path: &str, failures: &[RowFailure]) -> Result<(), Failure> {
+    let mut report = String::from("row,file,error\n");
+    for failure in failures {
+        report += &format!(
+            "{},{},{}\n",
+            failure.number,
+            csv_field(&failure.path),
+            csv_field(&failure.message)
+        );
+    }
+    std::fs::write(path, report)
+        .map_err(|error| Failure::Io(format!("unable to write {}: {}", path, error)))
+}
+
+// Encode and write a single code, making its directory if needed
+fn generate(job: &Job, args: &BatchArgs) -> Result<(), String> {
+    let mut options = args.style.to_options();
+    options.caption = job
+        .caption
+        .as_ref()
+        .map(|text| args.caption_style.to_caption(text));
+    let qr = QrCode::new(&job.payload, args.style.ec, &options).map_err(|e| e.to_string())?;
+    if let Some(parent) = std::path::Path::new(&job.path).parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|error| format!("unable to create {}: {}", parent.display(), error))?;
+    }
+    write_output(&qr, &job.path).map_err(|failure| failure.to_string())
+}
+
+impl RowArgs {
+    pub fn get_payload_template(&self) -> Result<Vec<Part>, Failure> {
+        match (&self.column, &self.template) {
+            (Some(column), _) => Ok(vec![Part::Column(column.clone())]),
+            (None, Some(template)) => parse_template(template).map_err(Failure::Usage),
+            (None, None) => unreachable!("clap requires a column or template"),
+        }
+    }
+
+    /// Read the rows, checking that a csv file has the columns the templates use
+    pub fn read_rows(&self, templates: &[&[Part]]) -> Result<Vec<ParsedRow>, Failure> {
+        let text = std::fs::read_to_string(&self.input)
+            .map_err(|error| Failure::Io(format!("unable to read {}: {}", self.input, error)))?;
+        let format = self.format.clone().unwrap_or_else(|| {
+            let path = std::path::Path::new(&self.input);
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            match extension {
+                Some("jsonl" | "ndjson") => "jsonl".to_string(),
+                _ => "csv".to_string(),
+            }
+        });
+        if format == "jsonl" {
+            return Ok(read_json_rows(&text));
+        }
+
+        let (headers, rows) = read_csv_rows(&text)
+            .map_err(|error| Failure::Usage(format!("unable to read {}: {}", self.input, error)))?;
+
+        // Every row would fail the same way, so catch it up front
+        for part in templates.iter().flat_map(|template| template.iter()) {
+            if let Part::Column(name) = part {
+                if name != "row" && !headers.contains(name) {
+                    let message = format!("{} has no {} column", self.input, name);
+                    return Err(Failure::Usage(message));
+                }
+            }
+        }
+        Ok(rows)
+    }
+}
+
+pub fn run(args: &BatchArgs) -> Result<(), Failure> {
+    let payload_template = args.source.get_payload_template()?;
+    let path_template = parse_template(&args.output).map_err(Failure::Usage)?;
+    let caption_template = match &args.caption {
+        Some(caption) => Some(parse_template(caption).map_err(Failure::Usage)?),
+        None => None,
+    };
+    let rows = args.source.read_rows(&[
+        &payload_template,
+        &path_template,
+        caption_template.as_deref().unwrap_or_default(),
+    ])?;
+
+    // Build every payload and path first, so that rows
+    // writing to the same file are caught before any work is done
+    let total = rows.len();
+    let mut jobs = Vec::new();
+    let mut failures = Vec::new();
+    let mut paths = HashSet::new();
+    for (i, row) in rows.into_iter().enumerate() {
+        let number = i + 1;
+        let job = row.and_then(|row| {
+            let payload = fill_template(&payload_template, &row, number, str::to_string)?;
+            let path = fill_template(&path_template, &row, number, escape_path)?;
+            let caption = caption_template
+                .as_ref()
+                .map(|template| fill_template(template, &row, number, str::to_string))
+                .transpose()?;
+            if !paths.insert(path.clone()) {
+                return Err(format!("another row already writes to {}", path));
+            }
+            Ok(Job {
+                number,
+                payload,
+                path,
+                caption,
+            })
+        });
+        match job {
+            Ok(job) => jobs.push(job),
+            Err(message) => failures.push(RowFailure {
+                number,
+                path: String::new(),
+                message,
+            }),
+        }
+    }
+
+    failures.extend(
+        jobs.par_iter()
+            .filter_map(|job| {
+                generate(job, args).err().map(|message| RowFailure {
+                    number: job.number,
+                    path: job.path.clone(),
+                    message,
+                })
+            })
+            .collect::<Vec<_>>(),
+    );
+    failures.sort_by_key(|failure| failure.number);
+
+    for failure in &failures {
+        eprintln!("row {}: {}", failure.number, failure.message);
+    }
+    eprintln!("wrote {} of {} codes", total - failures.len(), total);
+    if let Some(report) = &args.report {
+        write_report(report, &failures)?;
+    }
+
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(Failure::Rows(format!(
+        "{} of {} rows failed",
+        failures.len(),
+        total
+    )))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::batch::*;
+
+    #[test]
+    fn test_csv_parsing() {
+        let text = "\u{feff}sku,name\r\nA1,\"Bolt, 10mm\"\n\nB2,\"Say \"\"hi\"\"\nthere\"\nC3,\n";
+        let records = parse_csv(text).unwrap();
+        assert_eq!(
+            records,
+            [
+                vec!["sku", "name"],
+                vec!["A1", "Bolt, 10mm"],
+                vec!["B2", "Say \"hi\"\nthere"],
+                vec!["C3", ""],
+            ]
+        );
+        assert_eq!(
+            parse_csv("a,b\n\"1,2").unwrap_err(),
+            "a quoted field is never closed"
+        );
+        assert_eq!(parse_csv("a\n\"\"").unwrap(), [vec!["a"], vec![""]]);
+
+        let (headers, rows) = read_csv_rows("a,b\n1,2\n3\n").unwrap();
+        assert_eq!(headers, ["a", "b"]);
+        assert_eq!(rows[0].as_ref().unwrap()["b"], "2");
+        assert_eq!(rows[1], Err("expected 2 fields but found 1".to_string()));
+    }
+
+    #[test]
+    fn test_json_rows() {
+        let rows = read_json_rows("{\"sku\": \"A1\", \"count\": 3, \"note\": null}\n\n[1]\n{");
+        assert_eq!(rows.len(), 3);
+        let row = rows[0].as_ref().unwrap();
+        assert_eq!(
+            (&row["sku"][..], &row["count"][..], &row["note"][..]),
+            ("A1", "3", "")
+        );
+        assert_eq!(rows[1], Err("expected a json object".to_string()));
+        assert!(rows[2].as_ref().unwrap_err().starts_with("invalid json"));
+    }
+
+    #[test]
+    fn test_templates() {
+        let parts = parse_template("codes/{sku}-{{x}}.png").unwrap();
+        assert_eq!(
+            parts,
+            [
+                Part::Text("codes/".to_string()),
+                Part::Column("sku".to_string()),
+                Part::Text("-{x}.png".to_string()),
+            ]
+        );
+        assert!(parse_template("{sku").is_err());
+        assert!(parse_template("{}").is_err());
+        assert!(parse_template("a}b").is_err());
+
+        let row: Row = [("sku".to_string(), "../A/1".to_string())].into();
+        let fill = |template: &str, escape| {
+            fill_template(&parse_template(template).unwrap(), &row, 7, escape)
+        };
+        assert_eq!(
+            fill("out/{sku}.png", escape_path).unwrap(),
+            "out/.._A_1.png"
+        );
+        assert_eq!(fill("{row}:{sku}", str::to_string).unwrap(), "7:../A/1");
+        assert_eq!(
+            fill("{name}", str::to_string),
+            Err("there's no name column".to_string())
+        );
+        assert_eq!(escape_path(".."), "__");
+    }
+}
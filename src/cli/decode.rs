@@ -0,0 +1,100 @@
+use super::{level_letter, mode_name, Failure};
+use clap::Args;
+use qrgen::Decoded;
+use serde_json::json;
+
+/// Find and decode the qr codes in png or jpeg images
+#[derive(Args)]
+pub struct DecodeArgs {
+    #[arg(required = true)]
+    images: Vec<String>,
+
+    /// Print a json object for each image with the details of each qr code
+    #[arg(long)]
+    json: bool,
+}
+
+fn to_json(decoded: &Decoded) -> serde_json::Value {
+    let modes: Vec<&str> = decoded.modes.iter().map(|&mode| mode_name(mode)).collect();
+    json!({
+        "text": decoded.text,
+        "version": decoded.version,
+        "level": level_letter(decoded.level).to_string(),
+        "mask": decoded.mask,
+        "modes": modes,
+        "corrected_errors": decoded.corrected_errors,
+        "inverted": decoded.inverted,
+        "mirrored": decoded.mirrored,
+    })
+}
+
+/// Decode every image, carrying on past the ones that fail
+pub fn run(args: &DecodeArgs) -> Result<(), Failure> {
+    let mut unreadable = 0;
+    let mut not_found = 0;
+    for path in &args.images {
+        let codes = match qrgen::decode_image_file(path) {
+            Ok(codes) => codes,
+            Err(error) => {
+                eprintln!("error: unable to read {}: {}", path, error);
+                unreadable += 1;
+                continue;
+            }
+        };
+
+        if args.json {
+            let codes: Vec<serde_json::Value> = codes.iter().map(to_json).collect();
+            println!("{}", json!({"file": path, "codes": codes}));
+        } else {
+            for decoded in &codes {
+                println!("{}", decoded.text);
+            }
+        }
+
+        if codes.is_empty() {
+            eprintln!("error: no qr codes found in {}", path);
+            not_found += 1;
+        }
+    }
+
+    let total = args.images.len();
+    if unreadable > 0 {
+        let message = format!("{} of {} images could not be read", unreadable, total);
+        return Err(Failure::Io(message));
+    }
+    if not_found > 0 {
+        let message = format!("{} of {} images had no qr codes", not_found, total);
+        return Err(Failure::NotFound(message));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::decode::*;
+    use qrgen::{ErrorCorrection, Options, QrCode};
+
+    #[test]
+    fn test_json_output() {
+        let text = "say \"hi\"\n";
+        let qr = QrCode::new(text, ErrorCorrection::Quartile, &Options::default()).unwrap();
+        let size = qr.size();
+        let matrix: Vec<u8> = (0..size * size)
+            .map(|i| {
+                if qr.is_dark(i % size, i / size) {
+                    0
+                } else {
+                    255
+                }
+            })
+            .collect();
+        let decoded = qrgen::decode_matrix(size, &matrix).unwrap();
+
+        let json = to_json(&decoded);
+        assert_eq!(json["text"], text);
+        assert_eq!(json["level"], "Q");
+        assert_eq!(json["modes"], json!(["byte"]));
+        assert_eq!(json["inverted"], false);
+        assert!(json.to_string().contains("\"text\":\"say \\\"hi\\\"\\n\""));
+    }
+}
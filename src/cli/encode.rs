@@ -0,0 +1,248 @@
+use super::{parse_level, Failure};
+use clap::Args;
+use qrgen::{Alignment, Caption, ErrorCorrection, Options, QrCode};
+use std::io::{Read, Write};
+
+/// Options for generating a qr code from text
+#[derive(Args)]
+pub struct EncodeArgs {
+    /// Text to encode, read from stdin when no input is given
+    #[arg(conflicts_with_all = ["text", "file"])]
+    input: Option<String>,
+
+    /// Text to encode
+    #[arg(long, conflicts_with = "file")]
+    text: Option<String>,
+
+    /// Encode the contents of a file
+    #[arg(long)]
+    file: Option<String>,
+
+    /// Output file ending in .png, .svg, .pdf or .txt
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Text to print below the code
+    #[arg(long)]
+    caption: Option<String>,
+
+    #[command(flatten)]
+    caption_style: CaptionArgs,
+
+    #[command(flatten)]
+    style: StyleArgs,
+}
+
+/// Options for how the qr code is encoded and drawn
+#[derive(Args)]
+pub struct StyleArgs {
+    /// Error correction level: (L)ow, (M)edium, (Q)uartile or (H)igh
+    #[arg(long, default_value = "M", value_parser = parse_level)]
+    pub ec: ErrorCorrection,
+
+    /// Symbol version from 1 to 40, instead of the smallest that fits
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=40))]
+    version: Option<u8>,
+
+    /// Mask pattern from 0 to 7, instead of the one with the lowest penalty
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=7))]
+    mask: Option<u8>,
+
+    /// Width of each module in pixels, or points for pdfs
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..=100))]
+    scale: u32,
+
+    /// Width of the border in modules
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(0..=100))]
+    quiet_zone: u32,
+
+    /// Color of the dark modules as hex, like #000000
+    #[arg(long, default_value = "#000000", value_parser = parse_color)]
+    fg: [u8; 3],
+
+    /// Color of the light modules as hex, like #ffffff
+    #[arg(long, default_value = "#ffffff", value_parser = parse_color)]
+    bg: [u8; 3],
+
+    /// Draw light modules on a dark background
+    #[arg(long)]
+    invert: bool,
+
+    /// Flip the qr code horizontally
+    #[arg(long)]
+    mirror: bool,
+}
+
+impl StyleArgs {
+    pub fn to_options(&self) -> Options {
+        Options {
+            inverted: self.invert,
+            mirrored: self.mirror,
+            version: self.version.map(usize::from),
+            mask: self.mask.map(usize::from),
+            scale: self.scale,
+            quiet_zone: self.quiet_zone,
+            foreground: self.fg,
+            background: self.bg,
+            caption: None,
+        }
+    }
+}
+
+/// Options for how captions are drawn
+#[derive(Args)]
+pub struct CaptionArgs {
+    /// Where the caption sits under the code: left, center or right
+    #[arg(long, default_value = "center", value_parser = parse_alignment)]
+    caption_align: Alignment,
+
+    /// Height of the caption letters in modules
+    #[arg(long, default_value_t = 2.0, value_parser = parse_caption_size)]
+    caption_size: f64,
+}
+
+impl CaptionArgs {
+    pub fn to_caption(&self, text: &str) -> Caption {
+        Caption {
+            text: text.to_string(),
+            alignment: self.caption_align,
+            size: self.caption_size,
+        }
+    }
+}
+
+pub fn parse_alignment(alignment: &str) -> Result<Alignment, String> {
+    match alignment.to_ascii_lowercase().as_str() {
+        "left" => Ok(Alignment::Left),
+        "center" => Ok(Alignment::Center),
+        "right" => Ok(Alignment::Right),
+        _ => Err("expected left, center or right".to_string()),
+    }
+}
+
+pub fn parse_caption_size(size: &str) -> Result<f64, String> {
+    match size.parse::<f64>() {
+        Ok(size) if size > 0.0 && size <= 100.0 => Ok(size),
+        _ => Err("expected a size in modules between 0 and 100".to_string()),
+    }
+}
+
+pub fn parse_color(color: &str) -> Result<[u8; 3], String> {
+    qrgen::parse_color(color).ok_or_else(|| "expected a hex color like #1a2b3c".to_string())
+}
+
+fn read_input(cli: &EncodeArgs) -> Result<String, Failure> {
+    if let Some(text) = cli.input.as_ref().or(cli.text.as_ref()) {
+        return Ok(text.clone());
+    }
+    if let Some(path) = &cli.file {
+        return std::fs::read_to_string(path)
+            .map_err(|error| Failure::Io(format!("unable to read {}: {}", path, error)));
+    }
+
+    // Drop the line break that echo and most editors add
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .map_err(|error| Failure::Io(format!("unable to read stdin: {}", error)))?;
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    Ok(text)
+}
+
+/// Write the qr code in the format given by the file's extension
+pub fn write_output(qr: &QrCode, output: &str) -> Result<(), Failure> {
+    let extension = std::path::Path::new(output)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let bytes = match extension.as_deref() {
+        Some("png") => {
+            return qr
+                .to_image()
+                .save_with_format(output, image::ImageFormat::Png)
+                .map_err(|error| Failure::Io(format!("unable to write {}: {}", output, error)));
+        }
+        Some("svg") => qr.to_svg().into_bytes(),
+        Some("pdf") => qr.to_pdf(),
+        Some("txt") => qr.to_text().into_bytes(),
+        _ => {
+            return Err(Failure::Usage(format!(
+                "can't tell the format of {}, use a .png, .svg, .pdf or .txt extension",
+                output
+            )))
+        }
+    };
+    std::fs::write(output, bytes)
+        .map_err(|error| Failure::Io(format!("unable to write {}: {}", output, error)))
+}
+
+pub fn run(cli: &EncodeArgs) -> Result<(), Failure> {
+    let input = read_input(cli)?;
+    let mut options = cli.style.to_options();
+    options.caption = cli
+        .caption
+        .as_ref()
+        .map(|text| cli.caption_style.to_caption(text));
+    let qr = QrCode::new(&input, cli.style.ec, &options).map_err(Failure::Encoding)?;
+
+    match &cli.output {
+        Some(output) => write_output(&qr, output),
+        None => std::io::stdout()
+            .write_all(qr.to_text().as_bytes())
+            .map_err(|error| Failure::Io(format!("unable to write to stdout: {}", error))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::encode::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Command {
+        #[command(flatten)]
+        args: EncodeArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<EncodeArgs, clap::Error> {
+        let args = std::iter::once("qr").chain(args.iter().copied());
+        Command::try_parse_from(args).map(|command| command.args)
+    }
+
+    #[test]
+    fn test_input_selection() {
+        let args = parse(&["hello"]).unwrap();
+        assert_eq!(read_input(&args).ok(), Some("hello".to_string()));
+        let args = parse(&["--text", "hello"]).unwrap();
+        assert_eq!(read_input(&args).ok(), Some("hello".to_string()));
+
+        // Files are encoded as they are, keeping their last line break
+        let path = std::env::temp_dir().join(format!("qr-input-{}.txt", std::process::id()));
+        std::fs::write(&path, "from a file\n").unwrap();
+        let args = parse(&["--file", path.to_str().unwrap()]).unwrap();
+        assert_eq!(read_input(&args).ok(), Some("from a file\n".to_string()));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(read_input(&args), Err(Failure::Io(_))));
+
+        assert!(parse(&["hello", "--text", "hello"]).is_err());
+        assert!(parse(&["--text", "hello", "--file", "input.txt"]).is_err());
+    }
+
+    #[test]
+    fn test_style_arguments() {
+        let options = parse(&["hello", "--quiet-zone", "100", "--fg", "1a2b3c"])
+            .unwrap()
+            .style
+            .to_options();
+        assert_eq!(options.quiet_zone, 100);
+        assert_eq!(options.foreground, [0x1a, 0x2b, 0x3c]);
+        assert!(parse(&["hello", "--quiet-zone", "101"]).is_err());
+        assert!(parse(&["hello", "--scale", "0"]).is_err());
+        assert!(parse(&["hello", "--caption-size", "0"]).is_err());
+    }
+}
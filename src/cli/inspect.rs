@@ -0,0 +1,186 @@
+use super::{level_letter, mode_name, parse_level, Failure};
+use clap::Args;
+use qrgen::{ErrorCorrection, Options, Section, SectionKind};
+
+/// Explain how text is encoded: the mode, version, blocks,
+/// bitstream and the penalty of each mask
+#[derive(Args)]
+pub struct InspectArgs {
+    /// Text to encode
+    input: String,
+
+    /// Error correction level: (L)ow, (M)edium, (Q)uartile or (H)igh
+    #[arg(long, default_value = "M", value_parser = parse_level)]
+    ec: ErrorCorrection,
+
+    /// Symbol version from 1 to 40, instead of the smallest that fits
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=40))]
+    version: Option<u8>,
+
+    /// Mask pattern from 0 to 7, instead of the one with the lowest penalty
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=7))]
+    mask: Option<u8>,
+}
+
+fn section_name(kind: SectionKind) -> &'static str {
+    match kind {
+        SectionKind::ModeIndicator => "mode indicator",
+        SectionKind::CharacterCount => "character count",
+        SectionKind::Data => "data",
+        SectionKind::Terminator => "terminator",
+        SectionKind::BitPadding => "bit padding",
+        SectionKind::PadBytes => "pad bytes",
+    }
+}
+
+// Pack bits into bytes, filling the last byte with zeroes
+fn to_bytes(bits: &[bool]) -> Vec<u8> {
+    bits.chunks(8)
+        .map(|chunk| {
+            let byte = chunk.iter().fold(0, |byte, &bit| byte << 1 | u8::from(bit));
+            byte << (8 - chunk.len())
+        })
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    hex.join(" ")
+}
+
+// Short fields are shown in binary along with their value, and the rest in hex
+fn describe_section(section: &Section) -> String {
+    let bits = &section.bits;
+    let value = if bits.is_empty() {
+        String::new()
+    } else if bits.len() < 16 {
+        let binary: String = bits
+            .iter()
+            .map(|&bit| if bit { '1' } else { '0' })
+            .collect();
+        let value = bits
+            .iter()
+            .fold(0, |value, &bit| value << 1 | u32::from(bit));
+        format!("{} ({})", binary, value)
+    } else {
+        to_hex(&to_bytes(bits))
+    };
+    format!(
+        "  {:<16}{:>6} bits  {}",
+        section_name(section.kind),
+        bits.len(),
+        value
+    )
+    .trim_end()
+    .to_string()
+}
+
+// Describe the blocks like "2 blocks of 15 and 2 blocks of 16"
+fn describe_blocks(data_codewords: &[usize]) -> String {
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &length in data_codewords {
+        match groups.last_mut() {
+            Some((count, last)) if *last == length => *count += 1,
+            _ => groups.push((1, length)),
+        }
+    }
+    let groups: Vec<String> = groups
+        .iter()
+        .map(|&(count, length)| {
+            let plural = if count == 1 { "block" } else { "blocks" };
+            format!("{} {} of {}", count, plural, length)
+        })
+        .collect();
+    groups.join(" and ")
+}
+
+pub fn run(args: &InspectArgs) -> Result<(), Failure> {
+    let options = Options {
+        version: args.version.map(usize::from),
+        mask: args.mask.map(usize::from),
+        ..Default::default()
+    };
+    let inspection =
+        qrgen::inspect_qr_code(&args.input, args.ec, &options).map_err(Failure::Encoding)?;
+
+    let size = 21 + (inspection.version - 1) * 4;
+    println!("Mode:              {}", mode_name(inspection.mode));
+    println!(
+        "Version:           {} ({}x{} modules)",
+        inspection.version, size, size
+    );
+    println!("Error correction:  {}", level_letter(inspection.level));
+    println!(
+        "Capacity:          {} of {} characters used, {} remaining",
+        inspection.length,
+        inspection.capacity,
+        inspection.capacity - inspection.length
+    );
+    println!(
+        "Blocks:            {} data codewords, with {} error correction codewords each",
+        describe_blocks(&inspection.data_codewords),
+        inspection.ec_codewords
+    );
+
+    println!("\nBitstream:");
+    for section in &inspection.sections {
+        println!("{}", describe_section(section));
+    }
+    let bits: Vec<bool> = inspection
+        .sections
+        .iter()
+        .flat_map(|section| section.bits.iter().copied())
+        .collect();
+    println!("\nData codewords:\n  {}", to_hex(&to_bytes(&bits)));
+
+    println!("\nMask penalties:     run  block  finder  ratio  total");
+    for (mask, scores) in inspection.penalties.iter().enumerate() {
+        let chosen = if mask == inspection.mask {
+            "  <- chosen"
+        } else {
+            ""
+        };
+        println!(
+            "  {}              {:>6} {:>6} {:>7} {:>6} {:>6}{}",
+            mask,
+            scores[0],
+            scores[1],
+            scores[2],
+            scores[3],
+            scores.iter().sum::<u32>(),
+            chosen
+        );
+    }
+    println!("\nChosen mask:       {}", inspection.mask);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::inspect::*;
+
+    #[test]
+    fn test_describing() {
+        assert_eq!(to_bytes(&[true, false, true]), [0xA0]);
+        assert_eq!(to_bytes(&[true; 10]), [0xFF, 0xC0]);
+        assert_eq!(describe_blocks(&[16]), "1 block of 16");
+        assert_eq!(
+            describe_blocks(&[15, 15, 16, 16]),
+            "2 blocks of 15 and 2 blocks of 16"
+        );
+
+        let section = Section {
+            kind: SectionKind::CharacterCount,
+            bits: vec![false, false, false, false, false, true, false, true],
+        };
+        assert_eq!(
+            describe_section(&section),
+            "  character count      8 bits  00000101 (5)"
+        );
+        let section = Section {
+            kind: SectionKind::BitPadding,
+            bits: Vec::new(),
+        };
+        assert_eq!(describe_section(&section), "  bit padding          0 bits");
+    }
+}
@@ -0,0 +1,408 @@
+use super::encode::{parse_alignment, parse_caption_size, parse_color};
+use super::{parse_level, Failure};
+use clap::Args;
+use qrgen::{Caption, ErrorCorrection, Options, QrCode};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::time::Duration;
+
+// Longest request line and headers that are read before giving up
+const MAX_HEAD_LENGTH: usize = 8192;
+
+// Widest png that will be drawn, in pixels
+const MAX_IMAGE_SIZE: usize = 4096;
+
+/// Serve qr codes over http on localhost, like /qr.svg?text=hello&ec=M&scale=8
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// Longest text that can be encoded, in bytes
+    #[arg(long, default_value_t = 1024)]
+    max_length: usize,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            headers: vec![("Cache-Control", "no-store".to_string())],
+            body: format!("{}\n", message).into_bytes(),
+        }
+    }
+
+    fn to_bytes(&self, include_body: bool) -> Vec<u8> {
+        let reason = match self.status {
+            200 => "OK",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        };
+        let mut head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        );
+        for (name, value) in &self.headers {
+            head += &format!("{}: {}\r\n", name, value);
+        }
+        head += "\r\n";
+
+        let mut bytes = head.into_bytes();
+        if include_body {
+            bytes.extend(&self.body);
+        }
+        bytes
+    }
+}
+
+// Decode a query string value, where a plus is a space
+fn decode_component(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let high = (iter.next()? as char).to_digit(16)?;
+                let low = (iter.next()? as char).to_digit(16)?;
+                bytes.push((high * 16 + low) as u8);
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn parse_query(query: &str) -> Result<Vec<(String, String)>, String> {
+    let mut params: Vec<(String, String)> = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let decode = |part| decode_component(part).ok_or("the query isn't encoded properly");
+        let (name, value) = (decode(name)?, decode(value)?);
+        if params.iter().any(|(other, _)| *other == name) {
+            return Err(format!("{} is given more than once", name));
+        }
+        params.push((name, value));
+    }
+    Ok(params)
+}
+
+fn parse_flag(value: &str) -> Result<bool, String> {
+    match value {
+        "" | "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err("expected true or false".to_string()),
+    }
+}
+
+fn parse_number(value: &str, range: std::ops::RangeInclusive<usize>) -> Result<usize, String> {
+    match value.parse() {
+        Ok(number) if range.contains(&number) => Ok(number),
+        _ => Err(format!(
+            "expected a number from {} to {}",
+            range.start(),
+            range.end()
+        )),
+    }
+}
+
+// Get the text, error correction level and options from the query parameters
+fn get_options(
+    params: &[(String, String)],
+    max_length: usize,
+) -> Result<(String, ErrorCorrection, Options), String> {
+    let mut text = None;
+    let mut level = ErrorCorrection::Medium;
+    let mut options = Options::default();
+    let mut caption = Caption::new("");
+    for (name, value) in params {
+        let invalid = |message: String| format!("invalid {}: {}", name, message);
+        match name.as_str() {
+            "text" => text = Some(value.clone()),
+            "ec" => level = parse_level(value).map_err(invalid)?,
+            "version" => options.version = Some(parse_number(value, 1..=40).map_err(invalid)?),
+            "mask" => options.mask = Some(parse_number(value, 0..=7).map_err(invalid)?),
+            "scale" => options.scale = parse_number(value, 1..=100).map_err(invalid)? as u32,
+            "quiet_zone" => {
+                options.quiet_zone = parse_number(value, 0..=100).map_err(invalid)? as u32
+            }
+            "fg" => options.foreground = parse_color(value).map_err(invalid)?,
+            "bg" => options.background = parse_color(value).map_err(invalid)?,
+            "invert" => options.inverted = parse_flag(value).map_err(invalid)?,
+            "mirror" => options.mirrored = parse_flag(value).map_err(invalid)?,
+            "caption" => caption.text = value.clone(),
+            "caption_align" => caption.alignment = parse_alignment(value).map_err(invalid)?,
+            "caption_size" => caption.size = parse_caption_size(value).map_err(invalid)?,
+            _ => return Err(format!("unknown parameter {}", name)),
+        }
+    }
+
+    let text = text.ok_or("the text parameter is required")?;
+    if text.len() > max_length {
+        return Err(format!("text is longer than {} bytes", max_length));
+    }
+    if !caption.text.is_empty() {
+        options.caption = Some(caption);
+    }
+    Ok((text, level, options))
+}
+
+// Draw the code requested by the path and query, as a content type and body
+fn get_code(
+    path: &str,
+    query: &str,
+    max_length: usize,
+) -> Result<(&'static str, Vec<u8>), Response> {
+    let bad_request = |message: String| Response::error(400, &message);
+    let params = parse_query(query).map_err(bad_request)?;
+    let (text, level, options) = get_options(&params, max_length).map_err(bad_request)?;
+    let qr = QrCode::new(&text, level, &options).map_err(|error| bad_request(error.to_string()))?;
+
+    match path {
+        "/qr.png" => {
+            // The caption strip adds a little over its letter height to the bottom
+            let scale = options.scale as f64;
+            let width = (qr.size() + options.quiet_zone as usize * 2) as f64 * scale;
+            let caption = options.caption.as_ref();
+            let height = width + caption.map_or(0.0, |caption| caption.size * scale * 9.0 / 7.0);
+            if height.max(width) > MAX_IMAGE_SIZE as f64 {
+                return Err(bad_request(format!(
+                    "the image would be larger than {} pixels on a side",
+                    MAX_IMAGE_SIZE
+                )));
+            }
+            let mut png = std::io::Cursor::new(Vec::new());
+            qr.to_image()
+                .write_to(&mut png, image::ImageFormat::Png)
+                .map_err(|error| Response::error(500, &error.to_string()))?;
+            Ok(("image/png", png.into_inner()))
+        }
+        "/qr.svg" => Ok(("image/svg+xml", qr.to_svg().into_bytes())),
+        _ => Ok(("text/plain; charset=utf-8", qr.to_text().into_bytes())),
+    }
+}
+
+// Hash the body with 64 bit FNV-1a to get an entity tag that stays the same between runs
+fn get_etag(body: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &byte in body {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("\"{:016x}\"", hash)
+}
+
+// Answer a request given its head, returning the response and whether to send its body
+fn handle(head: &str, max_length: usize) -> (Response, bool) {
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or("");
+    let request_headers: Vec<(&str, &str)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect();
+
+    let mut parts = request_line.split(' ');
+    let (method, target) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version)) if version.starts_with("HTTP/") => {
+            (method, target)
+        }
+        _ => return (Response::error(400, "malformed request"), true),
+    };
+    let include_body = method != "HEAD";
+    if method != "GET" && method != "HEAD" {
+        let mut response = Response::error(405, "only GET and HEAD are supported");
+        response.headers.push(("Allow", "GET, HEAD".to_string()));
+        return (response, include_body);
+    }
+
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if !matches!(path, "/qr.png" | "/qr.svg" | "/qr.txt") {
+        let response = Response::error(404, "try /qr.png, /qr.svg or /qr.txt");
+        return (response, include_body);
+    }
+    let (content_type, body) = match get_code(path, query, max_length) {
+        Ok(code) => code,
+        Err(response) => return (response, include_body),
+    };
+
+    // The same url always gives the same code, so clients can keep it for a while
+    // and check back with the entity tag
+    let etag = get_etag(&body);
+    let headers = vec![
+        ("Cache-Control", "public, max-age=86400".to_string()),
+        ("ETag", etag.clone()),
+    ];
+    let if_none_match = request_headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("If-None-Match"))
+        .map(|(_, tags)| tags.split(',').map(str::trim));
+    if if_none_match.is_some_and(|mut tags| tags.any(|tag| tag == etag || tag == "*")) {
+        let response = Response {
+            status: 304,
+            content_type,
+            headers,
+            body: Vec::new(),
+        };
+        return (response, false);
+    }
+    let response = Response {
+        status: 200,
+        content_type,
+        headers,
+        body,
+    };
+    (response, include_body)
+}
+
+// Read the request line and headers, ignoring any body since only GET and HEAD are served
+fn read_head(stream: &mut TcpStream) -> Option<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_HEAD_LENGTH {
+            return None;
+        }
+        let read = stream.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        head.extend(&buffer[..read]);
+    }
+    Some(String::from_utf8_lossy(&head).into_owned())
+}
+
+fn respond(mut stream: TcpStream, max_length: usize) {
+    // Don't let a client that stops sending hold on to the thread
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+    let (response, include_body) = match read_head(&mut stream) {
+        Some(head) => {
+            let (response, include_body) = handle(&head, max_length);
+            let request_line = head.lines().next().unwrap_or("");
+            eprintln!("{} {}", request_line, response.status);
+            (response, include_body)
+        }
+        None => (Response::error(431, "the request is too long"), true),
+    };
+    let _ = stream.write_all(&response.to_bytes(include_body));
+}
+
+// Answer each connection on its own thread
+fn serve(listener: TcpListener, max_length: usize) {
+    for stream in listener.incoming().flatten() {
+        std::thread::spawn(move || respond(stream, max_length));
+    }
+}
+
+pub fn run(args: &ServeArgs) -> Result<(), Failure> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, args.port)).map_err(|error| {
+        Failure::Io(format!("unable to listen on port {}: {}", args.port, error))
+    })?;
+    eprintln!(
+        "serving qr codes on http://localhost:{}/qr.svg?text=hello",
+        args.port
+    );
+    serve(listener, args.max_length);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::serve::*;
+
+    fn get(target: &str, headers: &str) -> (Response, bool) {
+        handle(&format!("GET {} HTTP/1.1\r\n{}\r\n", target, headers), 64)
+    }
+
+    #[test]
+    fn test_query_parsing() {
+        let params = parse_query("text=a+b%26c&ec=h&invert").unwrap();
+        assert_eq!(params[0], ("text".to_string(), "a b&c".to_string()));
+        assert_eq!(params[2], ("invert".to_string(), String::new()));
+        assert!(parse_query("text=%zz").is_err());
+        assert!(parse_query("text=a&text=b").is_err());
+
+        let (text, level, options) = get_options(&params, 64).unwrap();
+        assert_eq!((&text[..], level), ("a b&c", ErrorCorrection::High));
+        assert!(options.inverted);
+
+        let error = |query| get_options(&parse_query(query).unwrap(), 4).err().unwrap();
+        assert_eq!(error("ec=M"), "the text parameter is required");
+        assert_eq!(error("text=hello"), "text is longer than 4 bytes");
+        assert_eq!(error("text=a&size=3"), "unknown parameter size");
+        assert_eq!(
+            error("text=a&scale=0"),
+            "invalid scale: expected a number from 1 to 100"
+        );
+    }
+
+    #[test]
+    fn test_responses() {
+        let (response, _) = get("/qr.svg?text=hello&scale=8", "");
+        assert_eq!(
+            (response.status, response.content_type),
+            (200, "image/svg+xml")
+        );
+        assert!(response.body.starts_with(b"<svg"));
+        let etag = response.headers[1].1.clone();
+
+        // Asking again with the entity tag gives an empty response
+        let (response, include_body) = get(
+            "/qr.svg?text=hello&scale=8",
+            &format!("if-none-match: {}\r\n", etag),
+        );
+        assert_eq!((response.status, include_body), (304, false));
+
+        let (response, _) = get("/qr.png?text=hello", "");
+        assert!(response.body.starts_with(b"\x89PNG"));
+        assert_eq!(
+            get("/qr.png?text=hello&scale=100&quiet_zone=10", "")
+                .0
+                .status,
+            400
+        );
+        assert_eq!(get("/qr.txt?text=hello&version=1&ec=H", "").0.status, 200);
+        assert_eq!(
+            get("/qr.txt?text=hello%20world%20again&version=1&ec=H", "")
+                .0
+                .status,
+            400
+        );
+        assert_eq!(get("/", "").0.status, 404);
+        assert_eq!(handle("POST /qr.svg HTTP/1.1\r\n\r\n", 64).0.status, 405);
+        assert_eq!(handle("nonsense\r\n\r\n", 64).0.status, 400);
+    }
+
+    #[test]
+    fn test_server() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || serve(listener, 64));
+
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+        stream
+            .write_all(b"HEAD /qr.txt?text=hi HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: text/plain; charset=utf-8\r\n"));
+        assert!(response.contains("Cache-Control: public, max-age=86400\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+    }
+}
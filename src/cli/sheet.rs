@@ -0,0 +1,217 @@
+use super::batch::{fill_template, parse_template, RowArgs};
+use super::encode::StyleArgs;
+use super::Failure;
+use clap::Args;
+use qrgen::{Label, LabelSheet, PageSize, QrCode};
+use rayon::prelude::*;
+
+// Points in a millimeter
+const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// Lay out a qr code for each row of a csv or json lines file on printable label sheets
+#[derive(Args)]
+pub struct SheetArgs {
+    #[command(flatten)]
+    source: RowArgs,
+
+    /// Text to print below each code, like "{sku}", where {row} is the row number
+    #[arg(long)]
+    caption: Option<String>,
+
+    /// Output file ending in .pdf or .svg, where svg pages after the first are numbered like sheet-2.svg
+    #[arg(short, long)]
+    output: String,
+
+    /// Paper size: a4 or letter
+    #[arg(long, default_value = "a4", value_parser = parse_page)]
+    page: PageSize,
+
+    /// Number of labels down each page
+    #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(u16).range(1..))]
+    rows: u16,
+
+    /// Number of labels across each page
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u16).range(1..))]
+    columns: u16,
+
+    /// Page margins in millimeters as top,right,bottom,left, or one value for every side
+    #[arg(long, default_value = "10", value_parser = parse_margins)]
+    margins: [f64; 4],
+
+    /// Space between labels in millimeters as column,row, or one value for both
+    #[arg(long, default_value = "2.5", value_parser = parse_gutters)]
+    gutters: [f64; 2],
+
+    /// Height of the caption letters in points
+    #[arg(long, default_value_t = 8.0)]
+    caption_size: f64,
+
+    #[command(flatten)]
+    style: StyleArgs,
+}
+
+fn parse_page(page: &str) -> Result<PageSize, String> {
+    match page.to_ascii_lowercase().as_str() {
+        "a4" => Ok(PageSize::A4),
+        "letter" => Ok(PageSize::Letter),
+        _ => Err("expected a4 or letter".to_string()),
+    }
+}
+
+// Parse comma separated lengths that can't be negative
+fn parse_lengths(lengths: &str) -> Result<Vec<f64>, String> {
+    lengths
+        .split(',')
+        .map(|length| match length.trim().parse::<f64>() {
+            Ok(length) if length >= 0.0 && length.is_finite() => Ok(length),
+            _ => Err(format!("{} isn't a length in millimeters", length.trim())),
+        })
+        .collect()
+}
+
+fn parse_margins(margins: &str) -> Result<[f64; 4], String> {
+    match parse_lengths(margins)?[..] {
+        [all] => Ok([all; 4]),
+        [top, right, bottom, left] => Ok([top, right, bottom, left]),
+        _ => Err("expected one margin or four".to_string()),
+    }
+}
+
+fn parse_gutters(gutters: &str) -> Result<[f64; 2], String> {
+    match parse_lengths(gutters)?[..] {
+        [both] => Ok([both; 2]),
+        [column, row] => Ok([column, row]),
+        _ => Err("expected one gutter or two".to_string()),
+    }
+}
+
+// Get the file to write each svg page to, where the first page keeps the name it was given
+fn get_page_path(output: &str, page: usize) -> String {
+    if page == 1 {
+        return output.to_string();
+    }
+    let path = std::path::Path::new(output);
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("");
+    let name = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{}-{}.{}", stem, page, extension),
+        None => format!("{}-{}", stem, page),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+fn write_file(path: &str, bytes: &[u8]) -> Result<(), Failure> {
+    std::fs::write(path, bytes)
+        .map_err(|error| Failure::Io(format!("unable to write {}: {}", path, error)))
+}
+
+pub fn run(args: &SheetArgs) -> Result<(), Failure> {
+    let extension = std::path::Path::new(&args.output)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase());
+    let is_pdf = match extension.as_deref() {
+        Some("pdf") => true,
+        Some("svg") => false,
+        _ => {
+            return Err(Failure::Usage(format!(
+                "can't tell the format of {}, use a .pdf or .svg extension",
+                args.output
+            )))
+        }
+    };
+
+    let payload_template = args.source.get_payload_template()?;
+    let caption_template = match &args.caption {
+        Some(caption) => parse_template(caption).map_err(Failure::Usage)?,
+        None => Vec::new(),
+    };
+    let rows = args
+        .source
+        .read_rows(&[&payload_template, &caption_template])?;
+    if rows.is_empty() {
+        return Err(Failure::Usage(format!("{} has no rows", args.source.input)));
+    }
+
+    let [top, right, bottom, left] = args.margins.map(|margin| margin * POINTS_PER_MM);
+    let [column_gutter, row_gutter] = args.gutters.map(|gutter| gutter * POINTS_PER_MM);
+    let sheet = LabelSheet::new(args.page, args.rows.into(), args.columns.into())
+        .margins(top, right, bottom, left)
+        .gutters(column_gutter, row_gutter)
+        .caption_size(args.caption_size);
+
+    let options = args.style.to_options();
+    let labels: Vec<Result<Label, String>> = rows
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let row = row?;
+            let payload = fill_template(&payload_template, &row, i + 1, str::to_string)?;
+            let caption = match args.caption {
+                Some(_) => Some(fill_template(
+                    &caption_template,
+                    &row,
+                    i + 1,
+                    str::to_string,
+                )?),
+                None => None,
+            };
+            let code = QrCode::new(&payload, args.style.ec, &options)
+                .map_err(|error| error.to_string())?;
+            Ok(Label { code, caption })
+        })
+        .collect();
+
+    // A sheet with gaps in it would be printed on the wrong labels,
+    // so nothing gets written unless every row works
+    let total = labels.len();
+    let mut failed = 0;
+    for (i, label) in labels.iter().enumerate() {
+        if let Err(message) = label {
+            eprintln!("row {}: {}", i + 1, message);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(Failure::Rows(format!(
+            "{} of {} rows failed, so no sheet was written",
+            failed, total
+        )));
+    }
+    let labels: Vec<Label> = labels.into_iter().flatten().collect();
+
+    let layout_failure = |error: qrgen::SheetError| Failure::Usage(error.to_string());
+    if is_pdf {
+        let pdf = sheet.to_pdf(&labels).map_err(layout_failure)?;
+        write_file(&args.output, &pdf)?;
+    } else {
+        let pages = sheet.to_svg(&labels).map_err(layout_failure)?;
+        for (i, page) in pages.iter().enumerate() {
+            write_file(&get_page_path(&args.output, i + 1), page.as_bytes())?;
+        }
+    }
+    let pages = total.div_ceil(sheet.labels_per_page());
+    let plural = if pages == 1 { "" } else { "s" };
+    eprintln!("wrote {} labels on {} page{}", total, pages, plural);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::sheet::*;
+
+    #[test]
+    fn test_sheet_arguments() {
+        assert_eq!(parse_margins("10").unwrap(), [10.0; 4]);
+        assert_eq!(parse_margins("1,2,3,4").unwrap(), [1.0, 2.0, 3.0, 4.0]);
+        assert!(parse_margins("1,2").is_err());
+        assert!(parse_margins("-1").is_err());
+        assert_eq!(parse_gutters("2, 3").unwrap(), [2.0, 3.0]);
+        assert_eq!(parse_page("Letter").unwrap(), PageSize::Letter);
+
+        assert_eq!(get_page_path("sheet.svg", 1), "sheet.svg");
+        assert_eq!(get_page_path("out/sheet.svg", 3), "out/sheet-3.svg");
+    }
+}
@@ -0,0 +1,441 @@
+use crate::bch;
+use crate::drawer;
+use crate::encoder::{self, EncodingMode, ErrorCorrection};
+use crate::reed_solomon;
+use crate::tables;
+
+const ALPHANUMERIC_CHARS: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+// The format and version information codes can correct up to 3 bit errors
+const MAX_BIT_ERRORS: u32 = 3;
+
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    InvalidSize,
+    InvalidFormatInfo,
+    InvalidVersionInfo,
+    CorruptedData,
+    UnsupportedMode(u8),
+    InvalidData,
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DecodeError::InvalidSize => write!(f, "matrix is not a valid qr code size"),
+            DecodeError::InvalidFormatInfo => write!(f, "unable to read the format information"),
+            DecodeError::InvalidVersionInfo => write!(f, "unable to read the version information"),
+            DecodeError::CorruptedData => write!(f, "the data codewords are corrupted"),
+            DecodeError::UnsupportedMode(mode) => write!(f, "unsupported encoding mode {}", mode),
+            DecodeError::InvalidData => write!(f, "the data bitstream is malformed"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// A qr code read back from its module matrix
+#[derive(Debug)]
+pub struct Decoded {
+    pub text: String,
+    pub version: usize,
+    pub level: ErrorCorrection,
+    pub mask: usize,
+    pub modes: Vec<EncodingMode>,
+    /// Number of corrected codewords in each block
+    pub corrected_errors: Vec<usize>,
+    pub inverted: bool,
+    pub mirrored: bool,
+}
+
+fn is_dark(matrix: &[u8], size: usize, x: usize, y: usize) -> bool {
+    matrix[y * size + x] < 128
+}
+
+// Count how many modules of a finder pattern match when its top left corner is at (startx, starty)
+fn get_finder_matches(matrix: &[u8], size: usize, startx: usize, starty: usize) -> usize {
+    let mut matches = 0;
+    for y in 0..7 {
+        for x in 0..7 {
+            let is_border = x == 0 || y == 0 || x == 6 || y == 6;
+            let is_inner = (2..=4).contains(&x) && (2..=4).contains(&y);
+            let expected = is_border || is_inner;
+            matches += usize::from(is_dark(matrix, size, startx + x, starty + y) == expected);
+        }
+    }
+    matches
+}
+
+// Bring an inverted or mirrored matrix back to its normal orientation and reflectance
+fn normalize(matrix: &[u8], size: usize) -> (Vec<u8>, bool, bool) {
+    let mut normalized = matrix.to_vec();
+
+    // Inverted symbols have light finder pattern borders
+    let matches = get_finder_matches(matrix, size, 0, 0);
+    let inverted = matches < 49 - matches;
+    if inverted {
+        for module in normalized.iter_mut() {
+            *module = 255 - *module;
+        }
+    }
+
+    // Mirrored symbols have their third finder pattern in the bottom right
+    let bottom_left = get_finder_matches(&normalized, size, 0, size - 7);
+    let bottom_right = get_finder_matches(&normalized, size, size - 7, size - 7);
+    let mirrored = bottom_right > bottom_left;
+    if mirrored {
+        for row in normalized.chunks_mut(size) {
+            row.reverse();
+        }
+    }
+
+    (normalized, inverted, mirrored)
+}
+
+// Read bits from the matrix into an integer, most significant bit first
+fn read_bits(matrix: &[u8], size: usize, positions: &[(usize, usize)]) -> u32 {
+    positions.iter().fold(0, |bits, &(x, y)| {
+        bits << 1 | u32::from(is_dark(matrix, size, x, y))
+    })
+}
+
+// Find the error correction level and mask closest to either copy of the format information
+fn read_format_info(matrix: &[u8], size: usize) -> Result<(ErrorCorrection, usize), DecodeError> {
+    let (level, mask, distance) = drawer::QR::get_format_positions(size)
+        .iter()
+        .map(|copy| bch::decode_format_info(read_bits(matrix, size, copy) as u16))
+        .min_by_key(|&(_, _, distance)| distance)
+        .unwrap();
+
+    if distance > MAX_BIT_ERRORS {
+        return Err(DecodeError::InvalidFormatInfo);
+    }
+    Ok((level, mask))
+}
+
+// Find the version closest to either copy of the version information
+fn read_version_info(matrix: &[u8], size: usize) -> Result<usize, DecodeError> {
+    let (version, distance) = drawer::QR::get_version_positions(size)
+        .iter()
+        .map(|copy| bch::decode_version_info(read_bits(matrix, size, copy)))
+        .min_by_key(|&(_, distance)| distance)
+        .unwrap();
+
+    if distance > MAX_BIT_ERRORS {
+        return Err(DecodeError::InvalidVersionInfo);
+    }
+    Ok(version)
+}
+
+// Split the interleaved codewords back into blocks of data codewords
+// followed by their error correction codewords
+fn deinterleave(codewords: &[u8], version: usize, level: ErrorCorrection) -> Vec<Vec<u8>> {
+    let info = tables::ECC_DATA[version - 1][level as usize];
+    let ecc_count = info[0] as usize;
+    let mut lengths = vec![info[2] as usize; info[1] as usize];
+    lengths.extend(vec![info[4] as usize; info[3] as usize]);
+
+    let mut blocks: Vec<Vec<u8>> = lengths.iter().map(|&n| Vec::with_capacity(n)).collect();
+    let mut codewords = codewords.iter();
+    let size = lengths.iter().max().copied().unwrap_or(0);
+    for i in 0..size + ecc_count {
+        for (block, &length) in blocks.iter_mut().zip(lengths.iter()) {
+            // Error correction codewords only start after the longest block
+            if i < length || i >= size {
+                block.push(*codewords.next().unwrap());
+            }
+        }
+    }
+    blocks
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.position
+    }
+
+    fn read(&mut self, bits: usize) -> Result<u32, DecodeError> {
+        if bits > self.remaining() {
+            return Err(DecodeError::InvalidData);
+        }
+
+        let mut value = 0;
+        for _ in 0..bits {
+            let byte = self.data[self.position / 8];
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+// Parse the segments in the data codewords back into the original payload
+fn parse_segments(
+    data: &[u8],
+    version: usize,
+) -> Result<(Vec<u8>, Vec<EncodingMode>), DecodeError> {
+    let mut reader = BitReader { data, position: 0 };
+    let mut payload = Vec::new();
+    let mut modes = Vec::new();
+
+    // The terminator can be shortened or left out if there's no space left
+    while reader.remaining() >= 4 {
+        let mode = match reader.read(4)? {
+            0 => break,
+            1 => EncodingMode::Numeric,
+            2 => EncodingMode::Alphanumeric,
+            4 => EncodingMode::Byte,
+            7 => {
+                // Skip the ECI designator, which can be 1, 2 or 3 bytes long
+                let first = reader.read(8)?;
+                if first & 0x80 != 0 {
+                    reader.read(if first & 0x40 == 0 { 8 } else { 16 })?;
+                }
+                continue;
+            }
+            other => return Err(DecodeError::UnsupportedMode(other as u8)),
+        };
+
+        let count = reader.read(encoder::get_count_bits_size(version, mode) as usize)? as usize;
+        match mode {
+            EncodingMode::Numeric => {
+                let mut remaining = count;
+                while remaining > 0 {
+                    let digits = std::cmp::min(remaining, 3);
+                    let num_bits = [0, 4, 7, 10][digits];
+                    let value = reader.read(num_bits)?;
+                    if value >= 10u32.pow(digits as u32) {
+                        return Err(DecodeError::InvalidData);
+                    }
+                    let group = format!("{:0width$}", value, width = digits);
+                    payload.extend(group.bytes());
+                    remaining -= digits;
+                }
+            }
+            EncodingMode::Alphanumeric => {
+                let mut remaining = count;
+                while remaining > 0 {
+                    if remaining >= 2 {
+                        let value = reader.read(11)? as usize;
+                        if value >= 45 * 45 {
+                            return Err(DecodeError::InvalidData);
+                        }
+                        payload.push(ALPHANUMERIC_CHARS[value / 45]);
+                        payload.push(ALPHANUMERIC_CHARS[value % 45]);
+                        remaining -= 2;
+                    } else {
+                        let value = reader.read(6)? as usize;
+                        if value >= 45 {
+                            return Err(DecodeError::InvalidData);
+                        }
+                        payload.push(ALPHANUMERIC_CHARS[value]);
+                        remaining -= 1;
+                    }
+                }
+            }
+            EncodingMode::Byte => {
+                for _ in 0..count {
+                    payload.push(reader.read(8)? as u8);
+                }
+            }
+        }
+        modes.push(mode);
+    }
+
+    Ok((payload, modes))
+}
+
+// Byte mode data is read as UTF-8, falling back to ISO-8859-1, which is the standard's default
+fn bytes_to_text(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(error) => error.into_bytes().into_iter().map(char::from).collect(),
+    }
+}
+
+/// Decode a qr code from its module matrix. Modules darker than 128 are dark.
+/// Inverted and mirrored symbols are detected and read as well.
+pub fn decode(size: usize, matrix: &[u8]) -> Result<Decoded, DecodeError> {
+    if !(21..=177).contains(&size) || !(size - 17).is_multiple_of(4) || matrix.len() != size * size
+    {
+        return Err(DecodeError::InvalidSize);
+    }
+
+    let (matrix, inverted, mirrored) = normalize(matrix, size);
+    let (level, mask) = read_format_info(&matrix, size)?;
+
+    let mut version = (size - 17) / 4;
+    if version >= 7 {
+        version = read_version_info(&matrix, size)?;
+        if version != (size - 17) / 4 {
+            return Err(DecodeError::InvalidVersionInfo);
+        }
+    }
+
+    // Read the codewords from the data modules, removing the mask as we go
+    let template = drawer::QR::template(version, level);
+    let mut codewords = Vec::new();
+    let mut byte = 0;
+    for (i, (x, y)) in template.get_data_positions().into_iter().enumerate() {
+        let bit = u8::from(is_dark(&matrix, size, x, y)) ^ drawer::get_mask_rule(x, y, mask);
+        byte = (byte << 1) | bit;
+        if i % 8 == 7 {
+            codewords.push(byte);
+            byte = 0;
+        }
+    }
+
+    // Repair each block using its error correction codewords
+    let ecc_count = tables::ECC_DATA[version - 1][level as usize][0] as usize;
+    let mut data = Vec::new();
+    let mut corrected_errors = Vec::new();
+    for mut block in deinterleave(&codewords, version, level) {
+        let corrected = reed_solomon::correct_errors(&mut block, ecc_count, &[])
+            .ok_or(DecodeError::CorruptedData)?;
+        corrected_errors.push(corrected);
+        data.extend_from_slice(&block[..block.len() - ecc_count]);
+    }
+
+    let (payload, modes) = parse_segments(&data, version)?;
+    Ok(Decoded {
+        text: bytes_to_text(payload),
+        version,
+        level,
+        mask,
+        modes,
+        corrected_errors,
+        inverted,
+        mirrored,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::decoder::*;
+
+    const LEVELS: [ErrorCorrection; 4] = [
+        ErrorCorrection::Low,
+        ErrorCorrection::Medium,
+        ErrorCorrection::Quartile,
+        ErrorCorrection::High,
+    ];
+
+    // Get an input of the given mode that fills the whole qr code version
+    fn get_input(version: usize, level: ErrorCorrection, mode: EncodingMode) -> String {
+        let capacity = tables::CHARACTER_CAPACITIES[version - 1][level as usize][mode as usize];
+        let chars = match mode {
+            EncodingMode::Numeric => "0123456789",
+            EncodingMode::Alphanumeric => "HELLO WORLD $%*+-./:0123456789",
+            EncodingMode::Byte => "Hello, world! lorem ipsum [qr] ~",
+        };
+        chars.chars().cycle().take(capacity as usize).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let modes = [
+            EncodingMode::Numeric,
+            EncodingMode::Alphanumeric,
+            EncodingMode::Byte,
+        ];
+        for version in 1..=40 {
+            for level in LEVELS {
+                let mode = modes[version % 3];
+                let input = get_input(version, level, mode);
+                let qr = drawer::QR::create(&input, level).unwrap();
+                let decoded = decode(qr.size, &qr.matrix).unwrap();
+                assert_eq!(decoded.text, input);
+                assert_eq!(decoded.version, version);
+                assert!(decoded.level == level);
+                assert_eq!(decoded.modes, vec![mode]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_inputs() {
+        for input in ["", "0", "A", "hello", "aЉ윇😱", "HELLO WORLD 123 :/"] {
+            let qr = drawer::QR::create(input, ErrorCorrection::Medium).unwrap();
+            let decoded = decode(qr.size, &qr.matrix).unwrap();
+            assert_eq!(decoded.text, input);
+        }
+    }
+
+    #[test]
+    fn test_inverted_and_mirrored() {
+        let input = "https://example.com/inverted";
+        for (invert, mirror) in [(true, false), (false, true), (true, true)] {
+            let mut qr = drawer::QR::create(input, ErrorCorrection::Quartile).unwrap();
+            if invert {
+                qr.invert();
+            }
+            if mirror {
+                qr.mirror();
+            }
+            let decoded = decode(qr.size, &qr.matrix).unwrap();
+            assert_eq!(decoded.text, input);
+            assert_eq!(decoded.inverted, invert);
+            assert_eq!(decoded.mirrored, mirror);
+        }
+    }
+
+    #[test]
+    fn test_damaged_function_patterns() {
+        let input = get_input(12, ErrorCorrection::High, EncodingMode::Byte);
+        let mut qr = drawer::QR::create(&input, ErrorCorrection::High).unwrap();
+
+        // Destroy the first format information copy entirely
+        for (x, y) in drawer::QR::get_format_positions(qr.size)[0] {
+            qr.matrix[y * qr.size + x] = 0;
+        }
+
+        // And flip a few bits of both version information copies
+        for copy in drawer::QR::get_version_positions(qr.size) {
+            for &(x, y) in copy.iter().take(3) {
+                let index = y * qr.size + x;
+                qr.matrix[index] = 255 - qr.matrix[index];
+            }
+        }
+
+        let decoded = decode(qr.size, &qr.matrix).unwrap();
+        assert_eq!(decoded.text, input);
+        assert_eq!(decoded.version, 12);
+    }
+
+    #[test]
+    fn test_damaged_data() {
+        let input = get_input(5, ErrorCorrection::Medium, EncodingMode::Byte);
+        let mut qr = drawer::QR::create(&input, ErrorCorrection::Medium).unwrap();
+
+        // Flip the bits of a whole codeword in each of the two blocks
+        let positions = drawer::QR::template(5, ErrorCorrection::Medium).get_data_positions();
+        for &(x, y) in &positions[..16] {
+            qr.matrix[y * qr.size + x] = 255 - qr.matrix[y * qr.size + x];
+        }
+
+        let decoded = decode(qr.size, &qr.matrix).unwrap();
+        assert_eq!(decoded.text, input);
+        assert_eq!(decoded.corrected_errors, vec![1, 1]);
+
+        // Too many damaged codewords can't be corrected
+        for &(x, y) in &positions[..positions.len() / 2] {
+            qr.matrix[y * qr.size + x] = 0;
+        }
+        assert_eq!(
+            decode(qr.size, &qr.matrix).unwrap_err(),
+            DecodeError::CorruptedData
+        );
+    }
+
+    #[test]
+    fn test_invalid_matrices() {
+        assert_eq!(decode(20, &[0; 400]).unwrap_err(), DecodeError::InvalidSize);
+        assert_eq!(decode(21, &[0; 400]).unwrap_err(), DecodeError::InvalidSize);
+    }
+}
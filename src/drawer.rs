@@ -1,28 +1,61 @@
+use crate::bch;
 use crate::encoder;
 use crate::tables;
 
 pub struct QR {
     pub size: usize,
     pub matrix: Vec<u8>,
+    pub inverted: bool,
+    pub mirrored: bool,
+    pub version: usize,
+    pub level: encoder::ErrorCorrection,
+    /// The mask pattern the data was drawn with
+    pub mask: usize,
 
-    version: usize,
-    level: encoder::ErrorCorrection,
     data: Vec<u8>,
     byte_index: usize,
     bit_index: usize,
 }
 
 impl QR {
-    pub fn create(input: &str, level: encoder::ErrorCorrection) -> Self {
+    /// Create a qr code with the smallest version that can hold the input
+    pub fn create(
+        input: &str,
+        level: encoder::ErrorCorrection,
+    ) -> Result<Self, encoder::EncodeError> {
         let mode = encoder::get_encoding_mode(input);
-        let version = encoder::get_version(level, mode, input.len());
+        let version = encoder::get_version(level, mode, input.len())
+            .ok_or(encoder::EncodeError::DataTooLong)?;
+        Ok(Self::create_with(input, level, version, None))
+    }
+
+    /// Create a qr code with a given version, which must be big enough to hold
+    /// the input, and mask pattern. The mask with the lowest penalty is used when
+    /// none is given.
+    pub fn create_with(
+        input: &str,
+        level: encoder::ErrorCorrection,
+        version: usize,
+        mask: Option<usize>,
+    ) -> Self {
+        let mut qr = Self::template(version, level);
+        qr.data = encoder::assemble_qr_data(input, level, version);
+        qr.draw_data(mask);
+        qr
+    }
 
+    /// Create a qr code that only has its function patterns drawn.
+    /// Modules that would hold data are left as 128.
+    pub fn template(version: usize, level: encoder::ErrorCorrection) -> Self {
         let size = 21 + (version - 1) * 4;
         let mut qr = Self {
             byte_index: 0,
             bit_index: 0,
-            data: encoder::assemble_qr_data(input, level),
+            data: Vec::new(),
             matrix: vec![128; size * size],
+            inverted: false,
+            mirrored: false,
+            mask: 0,
             version,
             size,
             level,
@@ -31,12 +64,28 @@ impl QR {
         qr.draw_initial_patterns();
         qr.draw_dummy_format_areas();
         qr.draw_version_info();
-        qr.draw_data();
         qr
     }
 
-    fn reset(&mut self, matrix_copy: &Vec<u8>) {
-        self.matrix = matrix_copy.clone();
+    /// Swap dark and light modules to get a reflectance reversed
+    /// (light on dark) symbol. The quiet zone must be dark as well.
+    pub fn invert(&mut self) {
+        for module in self.matrix.iter_mut() {
+            *module = 255 - *module;
+        }
+        self.inverted = !self.inverted;
+    }
+
+    /// Flip the symbol horizontally, for symbols that are read from behind glass
+    pub fn mirror(&mut self) {
+        for row in self.matrix.chunks_mut(self.size) {
+            row.reverse();
+        }
+        self.mirrored = !self.mirrored;
+    }
+
+    fn reset(&mut self, matrix_copy: &[u8]) {
+        self.matrix = matrix_copy.to_vec();
         self.byte_index = 0;
         self.bit_index = 0;
     }
@@ -45,7 +94,7 @@ impl QR {
         self.matrix[y * self.size + x] = color;
     }
 
-    fn get_module(&self, x: usize, y: usize) -> u8 {
+    pub fn get_module(&self, x: usize, y: usize) -> u8 {
         self.matrix[y * self.size + x]
     }
 
@@ -64,7 +113,7 @@ impl QR {
         for y in 0..7 {
             for x in 0..7 {
                 let is_border = x == 0 || y == 0 || x == 6 || y == 6;
-                let is_inner = x >= 2 && x <= 4 && y >= 2 && y <= 4;
+                let is_inner = (2..=4).contains(&x) && (2..=4).contains(&y);
                 let color = if is_border || is_inner { 0 } else { 255 };
                 let real_x = startx + x + usize::from(is_right);
                 let real_y = starty + y + usize::from(is_bottom);
@@ -75,7 +124,8 @@ impl QR {
 
     fn draw_alignment_patterns(&mut self) {
         // Center x and y positions of our alignment patterns
-        let alignment_positions = tables::get_alignment_pattern_locations(self.version);
+        let (positions, count) = tables::get_alignment_pattern_locations(self.version);
+        let alignment_positions = &positions[..count];
 
         for a in 0..alignment_positions.len() {
             for b in 0..alignment_positions.len() {
@@ -139,21 +189,32 @@ impl QR {
         }
     }
 
-    fn draw_version_info(&mut self) {
-        if self.version < 7 {
-            return;
-        }
-
-        let bitstring = tables::get_version_bitstring(self.version);
+    /// Get the positions of the two copies of the version information
+    /// bits, with the most significant bit first
+    pub fn get_version_positions(size: usize) -> [[(usize, usize); 18]; 2] {
+        let mut positions = [[(0, 0); 18]; 2];
         let mut index = 0;
         for x in 0..6 {
             for y in 0..3 {
-                let color = if bitstring[index] == 0 { 255 } else { 0 };
-                self.set_module(5 - x, self.size - 9 - y, color);
-                self.set_module(self.size - 9 - y, 5 - x, color);
+                positions[0][index] = (5 - x, size - 9 - y);
+                positions[1][index] = (size - 9 - y, 5 - x);
                 index += 1;
             }
         }
+        positions
+    }
+
+    fn draw_version_info(&mut self) {
+        let Some(info) = bch::get_version_info(self.version) else {
+            return;
+        };
+
+        for copy in Self::get_version_positions(self.size) {
+            for (i, (x, y)) in copy.into_iter().enumerate() {
+                let bit = (info >> (17 - i) & 1) as u8;
+                self.set_module(x, y, self.get_color(bit));
+            }
+        }
     }
 
     fn get_color(&self, bit: u8) -> u8 {
@@ -163,22 +224,37 @@ impl QR {
         0
     }
 
+    /// Get the positions of the two copies of the format information
+    /// bits, with the most significant bit first
+    pub fn get_format_positions(size: usize) -> [[(usize, usize); 15]; 2] {
+        // Around the top left finder pattern, skipping the timing patterns
+        let top_left = std::array::from_fn(|i| match i {
+            0..=5 => (i, 8),
+            6 => (7, 8),
+            7 => (8, 8),
+            8 => (8, 7),
+            _ => (8, 14 - i),
+        });
+
+        // Split between the bottom left and top right finder patterns
+        let split = std::array::from_fn(|i| {
+            if i < 7 {
+                (8, size - 1 - i)
+            } else {
+                (size - 15 + i, 8)
+            }
+        });
+
+        [top_left, split]
+    }
+
     fn draw_format_info(&mut self, mask_index: usize) {
-        let mut x = 0;
-        let mut y = (self.size - 1) as i32;
-        let bits = tables::FORMAT_INFO_BITS[self.level as usize][mask_index];
-
-        self.set_module(self.size - 8, 8, self.get_color(bits[7]));
-        for i in 0..15 {
-            // Draw vertically
-            self.set_module(8, y as usize, self.get_color(bits[i]));
-            y = if i == 6 { 8 } else { y - 1 }; // Skip middle vertical gap
-            y = if y == 6 { 5 } else { y }; // Skip timing pattern
-
-            // Draw horizantally
-            self.set_module(x, 8, self.get_color(bits[i]));
-            x = if i == 7 { self.size - 7 } else { x + 1 }; // Skip middle horizantal gap
-            x = if x == 6 { 7 } else { x }; // Skip timing pattern
+        let info = bch::get_format_info(self.level, mask_index);
+        for copy in Self::get_format_positions(self.size) {
+            for (i, (x, y)) in copy.into_iter().enumerate() {
+                let bit = (info >> (14 - i) & 1) as u8;
+                self.set_module(x, y, self.get_color(bit));
+            }
         }
     }
 
@@ -199,26 +275,14 @@ impl QR {
         bit
     }
 
-    // Return 1 if the mask applies else 0
-    fn get_mask_rule(&self, x: usize, y: usize, mask_index: usize) -> u8 {
-        u8::from(match mask_index {
-            0 => (x + y) % 2 == 0,
-            1 => y % 2 == 0,
-            2 => x % 3 == 0,
-            3 => (x + y) % 3 == 0,
-            4 => (((y as f64) / 2.0).floor() + ((x as f64) / 3.0).floor()) % 2.0 == 0.0,
-            5 => (((x * y) % 2) + ((x * y) % 3)) == 0,
-            6 => (((x * y) % 2) + ((x * y) % 3)) % 2 == 0,
-            7 => (((x + y) % 2) + ((x * y) % 3)) % 2 == 0,
-            _ => false,
-        })
-    }
-
-    fn draw_and_mask_data_bits(&mut self, mask_index: usize) {
+    /// Get the positions of the data modules in the order they're drawn in
+    pub fn get_data_positions(&self) -> Vec<(usize, usize)> {
         let size = self.size as i32;
         let mut x = size - 1;
         let mut y = size - 1;
         let mut going_up = true;
+        let mut visited = vec![false; self.matrix.len()];
+        let mut positions = Vec::new();
 
         while x > 0 {
             // Skip the top timing pattern
@@ -231,13 +295,16 @@ impl QR {
                 x -= 1;
             }
 
-            // Draw right to left
+            // Go right to left
             for i in 0..2 {
                 let p = x - i;
-                if p >= 0 && self.get_module(p as usize, y as usize) == 128 {
-                    let mask = self.get_mask_rule(p as usize, y as usize, mask_index);
-                    let bit = self.get_next_bit() ^ mask;
-                    self.set_module(p as usize, y as usize, self.get_color(bit));
+                if p < 0 {
+                    continue;
+                }
+                let index = y as usize * self.size + p as usize;
+                if self.matrix[index] == 128 && !visited[index] {
+                    visited[index] = true;
+                    positions.push((p as usize, y as usize));
                 }
             }
 
@@ -249,6 +316,15 @@ impl QR {
                 x -= 2;
             }
         }
+
+        positions
+    }
+
+    fn draw_and_mask_data_bits(&mut self, mask_index: usize) {
+        for (x, y) in self.get_data_positions() {
+            let bit = self.get_next_bit() ^ get_mask_rule(x, y, mask_index);
+            self.set_module(x, y, self.get_color(bit));
+        }
     }
 
     // Calculate the penalty for horizantal and vertical 5 module or longer runs
@@ -341,31 +417,121 @@ impl QR {
         std::cmp::min(previous, next) * 10
     }
 
-    // Draw the qr data with the most optimal mask
-    fn draw_data(&mut self) {
-        let matrix_copy = self.matrix.clone();
+    /// Get the penalty scores from the run, block, finder pattern
+    /// and dark ratio rules for the modules as they're drawn now
+    pub fn get_penalty_scores(&self) -> [u32; 4] {
+        [
+            self.get_run_penalty(),
+            self.get_block_penalty(),
+            self.get_finder_penalty(),
+            self.get_ratio_penalty(),
+        ]
+    }
 
+    /// Get the penalty scores of the data drawn with each of the 8 mask patterns
+    pub fn get_mask_penalties(&self) -> [[u32; 4]; 8] {
+        let mut qr = Self::template(self.version, self.level);
+        qr.data = self.data.clone();
+        let matrix_copy = qr.matrix.clone();
+        std::array::from_fn(|mask| {
+            qr.reset(&matrix_copy);
+            qr.draw_and_mask_data_bits(mask);
+            qr.draw_format_info(mask);
+            qr.get_penalty_scores()
+        })
+    }
+
+    // Draw the qr data with the given mask, or the most optimal one
+    fn draw_data(&mut self, mask: Option<usize>) {
         // Find the mask pattern that yields the lowest penalty score
-        let mut min = u32::MAX;
-        let mut mask_to_use = 0;
-        let mut scores = [0; 8];
-        for i in 0..8 {
-            self.reset(&matrix_copy);
-            self.draw_and_mask_data_bits(i);
-            self.draw_format_info(i);
-
-            scores[i] = self.get_run_penalty()
-                + self.get_ratio_penalty()
-                + self.get_block_penalty()
-                + self.get_finder_penalty();
-            if scores[i] < min {
-                min = scores[i];
-                mask_to_use = i;
-            }
-        }
+        let mask_to_use = mask.unwrap_or_else(|| {
+            let penalties = self.get_mask_penalties();
+            (0..8)
+                .min_by_key(|&i| penalties[i].iter().sum::<u32>())
+                .unwrap()
+        });
 
-        self.reset(&matrix_copy);
         self.draw_and_mask_data_bits(mask_to_use);
         self.draw_format_info(mask_to_use);
+        self.mask = mask_to_use;
+    }
+}
+
+// Return 1 if the mask applies else 0
+pub fn get_mask_rule(x: usize, y: usize, mask_index: usize) -> u8 {
+    u8::from(match mask_index {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (((y as f64) / 2.0).floor() + ((x as f64) / 3.0).floor()) % 2.0 == 0.0,
+        5 => (((x * y) % 2) + ((x * y) % 3)) == 0,
+        6 => (((x * y) % 2) + ((x * y) % 3)).is_multiple_of(2),
+        7 => (((x + y) % 2) + ((x * y) % 3)).is_multiple_of(2),
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::drawer::*;
+
+    #[test]
+    fn test_inverting() {
+        let original = QR::create("HELLO WORLD", encoder::ErrorCorrection::Medium).unwrap();
+        let mut qr = QR::create("HELLO WORLD", encoder::ErrorCorrection::Medium).unwrap();
+        qr.invert();
+        assert!(qr.inverted);
+        for (a, b) in qr.matrix.iter().zip(original.matrix.iter()) {
+            assert_eq!(*a, 255 - *b);
+        }
+
+        // The finder pattern corners become light
+        assert_eq!(qr.get_module(0, 0), 255);
+        assert_eq!(qr.get_module(qr.size - 1, 0), 255);
+
+        qr.invert();
+        assert!(!qr.inverted);
+        assert_eq!(qr.matrix, original.matrix);
+    }
+
+    #[test]
+    fn test_mask_selection() {
+        let qr = QR::create("HELLO WORLD", encoder::ErrorCorrection::Quartile).unwrap();
+        let totals = qr
+            .get_mask_penalties()
+            .map(|scores| scores.iter().sum::<u32>());
+        assert!(totals.iter().all(|&total| total >= totals[qr.mask]));
+        assert_eq!(qr.get_penalty_scores(), qr.get_mask_penalties()[qr.mask]);
+
+        let qr = QR::create_with(
+            "HELLO WORLD",
+            encoder::ErrorCorrection::Quartile,
+            3,
+            Some(6),
+        );
+        assert_eq!((qr.version, qr.mask), (3, 6));
+        assert_eq!(qr.get_penalty_scores(), qr.get_mask_penalties()[6]);
+    }
+
+    #[test]
+    fn test_mirroring() {
+        let original = QR::create("hello", encoder::ErrorCorrection::Low).unwrap();
+        let mut qr = QR::create("hello", encoder::ErrorCorrection::Low).unwrap();
+        qr.mirror();
+        assert!(qr.mirrored);
+        for y in 0..qr.size {
+            for x in 0..qr.size {
+                assert_eq!(qr.get_module(x, y), original.get_module(qr.size - x - 1, y));
+            }
+        }
+
+        // The finder patterns are now in the top left, top right and bottom right
+        let last = qr.size - 1;
+        assert_eq!(qr.get_module(last, last - 6), 0);
+        assert_eq!(qr.get_module(last - 7, last), 255);
+
+        qr.mirror();
+        assert_eq!(qr.matrix, original.matrix);
     }
 }
@@ -1,7 +1,8 @@
+use crate::gf256;
 use crate::tables;
 use bitstream_io::{BigEndian, BitWrite, BitWriter};
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum ErrorCorrection {
     Low = 0,      // Recovers 7% of data
     Medium = 1,   // Recovers 15% of data
@@ -17,20 +18,10 @@ pub enum EncodingMode {
 }
 
 fn is_alphanumeric(codepoint: char) -> bool {
-    match codepoint {
-        '0'..='9' => true,
-        'A'..='Z' => true,
-        ' ' => true,
-        '$' => true,
-        '%' => true,
-        '*' => true,
-        '+' => true,
-        '-' => true,
-        '.' => true,
-        '/' => true,
-        ':' => true,
-        _ => false,
-    }
+    matches!(
+        codepoint,
+        '0'..='9' | 'A'..='Z' | ' ' | '$' | '%' | '*' | '+' | '-' | '.' | '/' | ':'
+    )
 }
 
 fn alphanumeric_value(codepoint: char) -> u16 {
@@ -63,7 +54,7 @@ pub fn get_encoding_mode(input: &str) -> EncodingMode {
         }
     }
 
-    if input.len() == 0 {
+    if input.is_empty() {
         return EncodingMode::Byte;
     } else if num_count == input.len() {
         return EncodingMode::Numeric;
@@ -74,17 +65,34 @@ pub fn get_encoding_mode(input: &str) -> EncodingMode {
     EncodingMode::Byte
 }
 
-// Get the minimum qr version that can hold the data
-pub fn get_version(level: ErrorCorrection, mode: EncodingMode, num_chars: usize) -> usize {
-    let mut version = 1;
-    loop {
-        let capacity = tables::CHARACTER_CAPACITIES[version - 1][level as usize][mode as usize];
-        if num_chars <= capacity as usize {
-            break;
+/// Reasons the input can't be turned into a qr code
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum EncodeError {
+    DataTooLong,
+    InvalidVersion(usize),
+    InvalidMask(usize),
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EncodeError::DataTooLong => write!(f, "the input is too long to fit in the qr code"),
+            EncodeError::InvalidVersion(version) => {
+                write!(f, "version {} is not between 1 and 40", version)
+            }
+            EncodeError::InvalidMask(mask) => write!(f, "mask {} is not between 0 and 7", mask),
         }
-        version += 1;
     }
-    version
+}
+
+impl std::error::Error for EncodeError {}
+
+// Get the minimum qr version that can hold the data, or None if even version 40 is too small
+pub fn get_version(level: ErrorCorrection, mode: EncodingMode, num_chars: usize) -> Option<usize> {
+    (1..=40).find(|&version| {
+        let capacity = tables::CHARACTER_CAPACITIES[version - 1][level as usize][mode as usize];
+        num_chars <= capacity as usize
+    })
 }
 
 struct EncodedData {
@@ -133,7 +141,7 @@ fn alphanumeric_encode(input: &str) -> EncodedData {
         let pair = &input[i..end];
 
         let mut num_bits = 6;
-        let mut value = alphanumeric_value(pair.chars().nth(0).unwrap());
+        let mut value = alphanumeric_value(pair.chars().next().unwrap());
 
         if pair.len() == 2 {
             let last = alphanumeric_value(pair.chars().nth(1).unwrap());
@@ -162,9 +170,9 @@ fn byte_encode(input: &str) -> EncodedData {
         // Write the codepoint to a u32 with the most
         // significant byte coming first
         let mut value = 0;
-        for i in 0..num_bytes {
+        for (i, byte) in bytes.iter().take(num_bytes).enumerate() {
             let index = num_bytes - i - 1;
-            value |= (bytes[i] as u32) << index * 8;
+            value |= (*byte as u32) << (index * 8);
         }
 
         let num_bits = num_bytes as u32 * 8;
@@ -178,13 +186,13 @@ fn byte_encode(input: &str) -> EncodedData {
 }
 
 // Get the number of bits needed to represent the encoded data's size
-fn get_count_bits_size(version: usize, mode: EncodingMode) -> u32 {
+pub fn get_count_bits_size(version: usize, mode: EncodingMode) -> u32 {
     const COUNT_SIZES: [[u32; 3]; 3] = [[10, 9, 8], [12, 11, 16], [14, 13, 16]];
     let mode_index = mode as usize;
 
-    if version >= 1 && version <= 9 {
+    if (1..=9).contains(&version) {
         return COUNT_SIZES[0][mode_index];
-    } else if version >= 10 && version <= 26 {
+    } else if (10..=26).contains(&version) {
         return COUNT_SIZES[1][mode_index];
     }
     COUNT_SIZES[2][mode_index]
@@ -198,12 +206,36 @@ fn get_required_bit_length(version: usize, level: ErrorCorrection) -> u32 {
     (block1_size + block2_size) * 8
 }
 
-fn encode_data(input: &str, level: ErrorCorrection) -> Vec<u8> {
-    let escaped = input.replace("\n", "\\n");
-    let input = escaped.as_str(); // Count escaped characters as real characters
+/// The parts the data bitstream is made of, in the order they're written
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SectionKind {
+    ModeIndicator,
+    CharacterCount,
+    Data,
+    Terminator,
+    /// Zeroes up to the next byte boundary
+    BitPadding,
+    /// Alternating 236 and 17 bytes that fill the rest of the symbol
+    PadBytes,
+}
 
-    let mut bitstream = BitWriter::endian(Vec::new(), BigEndian);
+/// A part of the data bitstream, with its bits most significant first
+#[derive(Clone, PartialEq, Debug)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub bits: Vec<bool>,
+}
 
+impl Section {
+    fn new(kind: SectionKind, length: u32, value: u32) -> Self {
+        let bits = (0..length).rev().map(|i| value >> i & 1 == 1).collect();
+        Section { kind, bits }
+    }
+}
+
+/// Split the data bitstream into its mode indicator, character count,
+/// data, terminator and padding. The version must be big enough to hold the input.
+pub fn get_bitstream_sections(input: &str, level: ErrorCorrection, version: usize) -> Vec<Section> {
     let mode = get_encoding_mode(input);
     let mode_indicator = match mode {
         EncodingMode::Numeric => 1,
@@ -211,7 +243,6 @@ fn encode_data(input: &str, level: ErrorCorrection) -> Vec<u8> {
         EncodingMode::Byte => 4,
     };
 
-    let version = get_version(level, mode, input.len());
     let count_bit_size = get_count_bits_size(version, mode);
     let encoded_data = match mode {
         EncodingMode::Numeric => numeric_encode(input),
@@ -224,110 +255,82 @@ fn encode_data(input: &str, level: ErrorCorrection) -> Vec<u8> {
     let temp_length = encoded_data.size_in_bits + count_bit_size + mode_size;
     let terminator_size = std::cmp::min(required_size - temp_length, 4);
 
-    // Write mode and count bits
-    bitstream.write(mode_size, mode_indicator).unwrap();
-    bitstream.write(count_bit_size, input.len() as u32).unwrap();
-
-    // Write the correct amount of data in bits to the bitstream
-    let mut size = encoded_data.size_in_bits;
-    for mut byte in encoded_data.data {
-        let bits = std::cmp::min(8, size);
-        if bits < 8 {
-            // The byte is in big endian format, so we
-            // need to shift right to align the signifcant
-            // remaining bits to their least significant positions
-            byte >>= 8 - bits;
-        }
-        bitstream.write(bits, byte).unwrap();
-        if size > 8 {
-            size -= 8;
-        }
-    }
-
-    bitstream.write(terminator_size, 0).unwrap();
+    // Take the correct amount of data in bits, leaving the
+    // zeroes that aligned the last byte
+    let data_bits = (0..encoded_data.size_in_bits as usize)
+        .map(|i| encoded_data.data[i / 8] << (i % 8) & 0x80 != 0)
+        .collect();
 
     // Pad with zeroes to make the bitstream's size in bits a multiple of 8
-    let mut length_in_bits =
-        encoded_data.size_in_bits + terminator_size + count_bit_size + mode_size;
-    if length_in_bits % 8 != 0 {
-        let next_mutliple = length_in_bits / 8 * 8 + 8;
-        let remaining = next_mutliple - length_in_bits;
-        for _ in 0..remaining {
-            bitstream.write_bit(false).unwrap();
-            length_in_bits += 1;
-        }
-    }
+    let length_in_bits = temp_length + terminator_size;
+    let padding_size = (8 - length_in_bits % 8) % 8;
 
     // Add pad bytes if the bitstream is still smaller than the required length
-    let remaining_bytes = (required_size - length_in_bits) / 8;
-    for i in 0..remaining_bytes {
-        if i % 2 == 0 {
-            bitstream.write(8, 236).unwrap();
-        } else {
-            bitstream.write(8, 17).unwrap();
-        }
-    }
-
-    bitstream.byte_align().unwrap();
-    bitstream.into_writer()
-}
-
-// Russian peasant multiplication
-fn galois_multiply(x: u8, y: u8) -> u8 {
-    let mut z: u8 = 0;
-    for i in (0..8).rev() {
-        z = (z << 1) ^ ((z >> 7) * 0x1D);
-        z ^= ((y >> i) & 1) * x;
-    }
-    z
+    let remaining_bytes = (required_size - length_in_bits - padding_size) / 8;
+    let pad_bytes = (0..remaining_bytes)
+        .flat_map(|i| {
+            Section::new(SectionKind::PadBytes, 8, if i % 2 == 0 { 236 } else { 17 }).bits
+        })
+        .collect();
+
+    vec![
+        Section::new(SectionKind::ModeIndicator, mode_size, mode_indicator),
+        Section::new(
+            SectionKind::CharacterCount,
+            count_bit_size,
+            input.len() as u32,
+        ),
+        Section {
+            kind: SectionKind::Data,
+            bits: data_bits,
+        },
+        Section::new(SectionKind::Terminator, terminator_size, 0),
+        Section::new(SectionKind::BitPadding, padding_size, 0),
+        Section {
+            kind: SectionKind::PadBytes,
+            bits: pad_bytes,
+        },
+    ]
 }
 
-// Compute a generator polynomial up to a certain degree
-fn compute_generator_polynomial(degree: usize) -> Vec<u8> {
-    let mut result = vec![0; degree - 1];
-    result.push(1); // Start off with x ^ 0
-
-    let mut root = 1u8;
-    for _ in 0..degree {
-        for j in 0..degree {
-            result[j] = galois_multiply(result[j], root);
-            if j + 1 < result.len() {
-                result[j] ^= result[j + 1];
-            }
+fn encode_data(input: &str, level: ErrorCorrection, version: usize) -> Vec<u8> {
+    let mut bitstream = BitWriter::endian(Vec::new(), BigEndian);
+    for section in get_bitstream_sections(input, level, version) {
+        for bit in section.bits {
+            bitstream.write_bit(bit).unwrap();
         }
-        root = galois_multiply(root, 0x02);
     }
-
-    result
+    bitstream.byte_align().unwrap();
+    bitstream.into_writer()
 }
 
 // Use the Reed-Solomon algorithm to generate error correction codes
 // for our encoded data. The Reed Solomon algorithm generates a bunch
 // of extra redundant data which can be used to recover the original
 // data even if parts of it are missing or corrupted
-fn generate_error_correction_codes(
-    data: &Vec<u8>,
+pub fn generate_error_correction_codes(
+    data: &[u8],
     level: ErrorCorrection,
     version: usize,
 ) -> Vec<u8> {
     let ecc_count = tables::ECC_DATA[version - 1][level as usize][0] as usize;
-    let generator = compute_generator_polynomial(ecc_count);
+    let generator = gf256::get_generator_polynomial(ecc_count);
     let mut result = vec![0; generator.len()];
     for byte in data {
-        let factor: u8 = byte ^ result.remove(0);
-        result.push(0);
+        let factor: u8 = byte ^ result[0];
+        result.rotate_left(1);
+        result[ecc_count - 1] = 0;
         for (x, &y) in result.iter_mut().zip(generator.iter()) {
-            *x ^= galois_multiply(y, factor);
+            *x ^= gf256::multiply(y, factor);
         }
     }
     result
 }
 
-/// Encode data, generate error correction codes and interleave to get the final qr data
-pub fn assemble_qr_data(input: &str, level: ErrorCorrection) -> Vec<u8> {
-    let data = encode_data(input, level);
-    let mode = get_encoding_mode(input);
-    let version = get_version(level, mode, input.len());
+/// Encode data, generate error correction codes and interleave to get the final qr data.
+/// The version must be big enough to hold the input.
+pub fn assemble_qr_data(input: &str, level: ErrorCorrection, version: usize) -> Vec<u8> {
+    let data = encode_data(input, level, version);
 
     let info = tables::ECC_DATA[version - 1][level as usize];
     let ecc_count = info[0] as usize;
@@ -351,8 +354,8 @@ pub fn assemble_qr_data(input: &str, level: ErrorCorrection) -> Vec<u8> {
                 let index = group_indexes[group] + block_index + i;
                 if i == 0 {
                     let end_index = index + block_lengths[group];
-                    let block_copy = &data[index..end_index].to_vec();
-                    let ecc = generate_error_correction_codes(block_copy, level, version);
+                    let ecc =
+                        generate_error_correction_codes(&data[index..end_index], level, version);
                     error_codewords.extend(ecc);
                 }
                 interleaved.push(data[index]);
@@ -385,6 +388,10 @@ mod test {
         writer.into_writer()
     }
 
+    fn minimum_version(input: &str, level: ErrorCorrection) -> usize {
+        get_version(level, get_encoding_mode(input), input.len()).unwrap()
+    }
+
     #[test]
     fn test_data_analyzing() {
         assert_eq!(get_encoding_mode("Hello world!"), EncodingMode::Byte);
@@ -453,32 +460,64 @@ mod test {
 
     #[test]
     fn test_data_encoding() {
-        let bytes = encode_data("hello!", ErrorCorrection::Low);
+        let bytes = encode_data(
+            "hello!",
+            ErrorCorrection::Low,
+            minimum_version("hello!", ErrorCorrection::Low),
+        );
         let expected = [
             0x40, 0x66, 0x86, 0x56, 0xC6, 0xC6, 0xF2, 0x10, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11,
             0xEC, 0x11, 0xEC, 0x11, 0xEC,
         ];
         assert_eq!(bytes, expected);
 
-        let bytes = encode_data("123", ErrorCorrection::Low);
+        let bytes = encode_data(
+            "123",
+            ErrorCorrection::Low,
+            minimum_version("123", ErrorCorrection::Low),
+        );
         let expected = [
             0x10, 0x0C, 0x7B, 0x00, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11,
             0xEC, 0x11, 0xEC, 0x11, 0xEC,
         ];
         assert_eq!(bytes, expected);
 
-        let bytes = encode_data("aЉ윇😱", ErrorCorrection::Medium);
+        let bytes = encode_data(
+            "aЉ윇😱",
+            ErrorCorrection::Medium,
+            minimum_version("aЉ윇😱", ErrorCorrection::Medium),
+        );
         let expected = [
             0x40, 0xA6, 0x1D, 0x08, 0x9E, 0xC9, 0xC8, 0x7F, 0x09, 0xF9, 0x8B, 0x10, 0xEC, 0x11,
             0xEC, 0x11,
         ];
         assert_eq!(bytes, expected);
 
-        let bytes = encode_data("", ErrorCorrection::High);
+        // Line breaks are encoded as they are
+        let bytes = encode_data(
+            "a\r\nb",
+            ErrorCorrection::Low,
+            minimum_version("a\r\nb", ErrorCorrection::Low),
+        );
+        let expected = [
+            0x40, 0x46, 0x10, 0xD0, 0xA6, 0x20, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11,
+            0xEC, 0x11, 0xEC, 0x11, 0xEC,
+        ];
+        assert_eq!(bytes, expected);
+
+        let bytes = encode_data(
+            "",
+            ErrorCorrection::High,
+            minimum_version("", ErrorCorrection::High),
+        );
         let expected = [0x40, 0x00, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC];
         assert_eq!(bytes, expected);
 
-        let bytes = encode_data("LOREM IPSUM SIT DOLOR AMED", ErrorCorrection::Quartile);
+        let bytes = encode_data(
+            "LOREM IPSUM SIT DOLOR AMED",
+            ErrorCorrection::Quartile,
+            minimum_version("LOREM IPSUM SIT DOLOR AMED", ErrorCorrection::Quartile),
+        );
         let expected = [
             0x20, 0xD3, 0xC9, 0x99, 0xB0, 0x09, 0xA1, 0xD0, 0xA8, 0x05, 0x3F, 0xA9, 0xEA, 0x61,
             0x79, 0x33, 0x8C, 0xEC, 0x28, 0x30, 0xEC, 0x11,
@@ -486,6 +525,32 @@ mod test {
         assert_eq!(bytes, expected);
     }
 
+    #[test]
+    fn test_bitstream_sections() {
+        let sections = get_bitstream_sections("123", ErrorCorrection::Low, 1);
+        let kinds: Vec<SectionKind> = sections.iter().map(|section| section.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                SectionKind::ModeIndicator,
+                SectionKind::CharacterCount,
+                SectionKind::Data,
+                SectionKind::Terminator,
+                SectionKind::BitPadding,
+                SectionKind::PadBytes,
+            ]
+        );
+        let lengths: Vec<usize> = sections.iter().map(|section| section.bits.len()).collect();
+        assert_eq!(lengths, [4, 10, 10, 4, 4, 120]);
+        assert_eq!(sections[0].bits, [false, false, false, true]);
+
+        // A full symbol cuts the terminator short and has no room for padding
+        let input = "1".repeat(41);
+        let sections = get_bitstream_sections(&input, ErrorCorrection::Low, 1);
+        let lengths: Vec<usize> = sections.iter().map(|section| section.bits.len()).collect();
+        assert_eq!(lengths, [4, 10, 137, 1, 0, 0]);
+    }
+
     #[test]
     fn test_error_correction_coding() {
         let level = ErrorCorrection::Low;
@@ -519,14 +584,22 @@ mod test {
 
     #[test]
     fn test_data_assembly() {
-        let data = assemble_qr_data("hello", ErrorCorrection::Low);
+        let data = assemble_qr_data(
+            "hello",
+            ErrorCorrection::Low,
+            minimum_version("hello", ErrorCorrection::Low),
+        );
         let expected = [
             0x40, 0x56, 0x86, 0x56, 0xC6, 0xC6, 0xF0, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC,
             0x11, 0xEC, 0x11, 0xEC, 0x11, 0x25, 0x19, 0xD0, 0xD2, 0x68, 0x59, 0x39,
         ];
         assert_eq!(data, expected);
 
-        let data = assemble_qr_data("LOREM IPSUM SIT DOLOR AMED", ErrorCorrection::High);
+        let data = assemble_qr_data(
+            "LOREM IPSUM SIT DOLOR AMED",
+            ErrorCorrection::High,
+            minimum_version("LOREM IPSUM SIT DOLOR AMED", ErrorCorrection::High),
+        );
         let expected = [
             0x20, 0x61, 0xD3, 0x79, 0xC9, 0x33, 0x99, 0x8C, 0xB0, 0xEC, 0x09, 0x28, 0xA1, 0x30,
             0xD0, 0xEC, 0xA8, 0x11, 0x05, 0xEC, 0x3F, 0x11, 0xA9, 0xEC, 0xEA, 0x11, 0x98, 0x12,
@@ -536,15 +609,23 @@ mod test {
         ];
         assert_eq!(data, expected);
 
-        let data = assemble_qr_data("aЉ윇😱", ErrorCorrection::Medium);
+        let data = assemble_qr_data(
+            "aЉ윇😱",
+            ErrorCorrection::Medium,
+            minimum_version("aЉ윇😱", ErrorCorrection::Medium),
+        );
         let expected = [
             0x40, 0xA6, 0x1D, 0x08, 0x9E, 0xC9, 0xC8, 0x7F, 0x09, 0xF9, 0x8B, 0x10, 0xEC, 0x11,
             0xEC, 0x11, 0xBB, 0x3A, 0x1D, 0x62, 0x99, 0x9D, 0xD8, 0xFF, 0xA9, 0x0C,
         ];
         assert_eq!(data, expected);
 
-        let input: String = std::iter::repeat("Hello, world! 123").take(5).collect();
-        let data = assemble_qr_data(input.as_str(), ErrorCorrection::High);
+        let input = "Hello, world! 123".repeat(5);
+        let data = assemble_qr_data(
+            input.as_str(),
+            ErrorCorrection::High,
+            minimum_version(input.as_str(), ErrorCorrection::High),
+        );
         let expected = [
             0x45, 0xC6, 0xC2, 0x86, 0x12, 0xF7, 0xF2, 0xEC, 0x54, 0x42, 0x07, 0x56, 0x03, 0x26,
             0xC2, 0x11, 0x86, 0x12, 0x76, 0xC6, 0x13, 0xC6, 0x07, 0xEC, 0x56, 0x03, 0xF7, 0xC6,
@@ -572,7 +653,11 @@ mod test {
 
         let input =
             "Lorem ipsum sit dolor amed.Lorem ipsum sit dolor amed.Lorem ipsum sit dolor amed.";
-        let data = assemble_qr_data(input, ErrorCorrection::High);
+        let data = assemble_qr_data(
+            input,
+            ErrorCorrection::High,
+            minimum_version(input, ErrorCorrection::High),
+        );
         let expected = vec![
             0x45, 0x36, 0xE4, 0x97, 0xC6, 0x06, 0x14, 0x97, 0xC6, 0x42, 0xF7, 0x46, 0xC6, 0x42,
             0xF7, 0x06, 0x26, 0xF6, 0xF7, 0x06, 0x26, 0x46, 0x56, 0xC6, 0x26, 0x46, 0x56, 0xF6,
@@ -596,51 +681,59 @@ mod test {
         assert_eq!(data, expected);
 
         let input = "Moon, a hole of light\n Through the big top tent up high\n Here before and after me\n Shinin' down on me\n Moon, tell me if I could\n Send up my heart to you?\n So, when I die, which I must do\n Could it shine down here with you?";
-        let data = assemble_qr_data(input, ErrorCorrection::Quartile);
+        let data = assemble_qr_data(
+            input,
+            ErrorCorrection::Quartile,
+            minimum_version(input, ErrorCorrection::Quartile),
+        );
         let expected = vec![
-            0x40, 0x96, 0x96, 0xE2, 0x46, 0xE2, 0x06, 0x42, 0xF5, 0x86, 0x42, 0x76, 0x0E, 0x76,
-            0x72, 0x04, 0x57, 0x06, 0xD6, 0x07, 0xC6, 0x96, 0x06, 0x97, 0x54, 0x87, 0x07, 0x86,
-            0x22, 0xF6, 0x52, 0x57, 0xE2, 0x36, 0x97, 0x46, 0xD6, 0x45, 0x46, 0x57, 0x06, 0xE2,
-            0x06, 0x02, 0x05, 0x82, 0x42, 0x82, 0xF6, 0xC6, 0xF7, 0x26, 0xD6, 0x06, 0x96, 0x06,
-            0x36, 0x04, 0x07, 0x07, 0xF6, 0xE2, 0x02, 0x52, 0x55, 0xD6, 0x62, 0xD7, 0xF2, 0x92,
-            0x36, 0x96, 0xE2, 0x05, 0x07, 0x06, 0xC6, 0x55, 0x04, 0x92, 0xC2, 0x06, 0x86, 0xF7,
-            0xC2, 0x46, 0x46, 0x26, 0xE2, 0xC6, 0x92, 0x06, 0x07, 0xD7, 0x96, 0x53, 0x06, 0x87,
-            0x56, 0x56, 0x05, 0xE2, 0x06, 0x86, 0x76, 0x57, 0xE6, 0xF0, 0x12, 0x26, 0xE7, 0x66,
-            0x36, 0x04, 0x36, 0x56, 0x86, 0x37, 0x52, 0xEC, 0x06, 0xF7, 0x42, 0xF7, 0x86, 0xD6,
-            0xF7, 0x17, 0x56, 0x42, 0x06, 0x11, 0x86, 0x56, 0x07, 0x26, 0x96, 0xF6, 0x56, 0x27,
-            0xE2, 0x06, 0x46, 0xEC, 0xF6, 0x76, 0x57, 0x52, 0xE6, 0xF6, 0xC6, 0x42, 0x04, 0x46,
-            0xF7, 0x11, 0xC6, 0x82, 0x02, 0x06, 0x96, 0xE2, 0x45, 0x07, 0x92, 0xF5, 0x76, 0xEC,
-            0x52, 0x07, 0x06, 0x16, 0xE2, 0xC2, 0xC6, 0x46, 0x06, 0xC6, 0xE2, 0x11, 0x06, 0x46,
-            0x86, 0xE6, 0x72, 0x07, 0xE2, 0xF2, 0x46, 0xE2, 0x06, 0xEC, 0xF6, 0x86, 0x96, 0x42,
-            0x06, 0x46, 0x05, 0x07, 0x96, 0x04, 0x86, 0x11, 0x62, 0x52, 0x76, 0x06, 0x46, 0x56,
-            0x36, 0x96, 0x52, 0x36, 0x57, 0xEC, 0x06, 0x06, 0x85, 0x16, 0xF7, 0xC6, 0x56, 0xF7,
-            0xC2, 0xF7, 0x26, 0x11, 0xC6, 0x26, 0xC6, 0x67, 0x76, 0xC2, 0xE6, 0x53, 0x07, 0x56,
-            0x52, 0xEC, 0x76, 0xC6, 0x07, 0x11, 0x0C, 0xCE, 0x1D, 0x8C, 0xC1, 0x8C, 0x0A, 0x58,
-            0x0D, 0x36, 0x49, 0xE0, 0xB6, 0xC9, 0x5E, 0x32, 0x5F, 0x15, 0x6B, 0x01, 0xC0, 0xC7,
-            0xAD, 0x16, 0xE3, 0xD2, 0x3D, 0xF5, 0xA0, 0x66, 0xB1, 0xBB, 0xCD, 0xA2, 0xAA, 0x45,
-            0x81, 0x92, 0x79, 0x12, 0xB9, 0x16, 0x54, 0x33, 0xDB, 0x14, 0x1C, 0xC0, 0x90, 0x7B,
-            0x73, 0xFE, 0xD4, 0x40, 0x06, 0x3A, 0x22, 0x9D, 0xB2, 0xCA, 0xE8, 0x83, 0x30, 0x2D,
-            0x7D, 0x7C, 0x57, 0x39, 0x45, 0x88, 0xCC, 0x60, 0x8C, 0xF4, 0xCA, 0xE1, 0x38, 0x6A,
-            0xEA, 0xBC, 0x79, 0x8A, 0x4F, 0xD7, 0xA2, 0xA4, 0xDF, 0xDE, 0xCB, 0x47, 0x3F, 0x53,
-            0x56, 0x21, 0x37, 0x67, 0xDB, 0xA7, 0x23, 0xB5, 0xCF, 0xF5, 0x6C, 0x68, 0x68, 0x26,
-            0x44, 0x24, 0xCA, 0x21, 0x36, 0x7C, 0xD7, 0x30, 0xD7, 0x1A, 0x33, 0xD1, 0x9F, 0xF1,
-            0x70, 0xF3, 0x72, 0x09, 0x56, 0x0C, 0xE6, 0x03, 0x32, 0xD8, 0x8E, 0x09, 0x5F, 0xEF,
-            0xD9, 0xA4, 0xB0, 0x52, 0xAE, 0xC2, 0x55, 0x7A, 0x5C, 0xF3, 0xF7, 0x90, 0x87, 0x1D,
-            0x5A, 0xA6, 0x3C, 0x06, 0x9D, 0x1B, 0x1A, 0x74, 0xF4, 0xC7, 0xBE, 0x15, 0x45, 0x14,
-            0x99, 0xDB, 0x7F, 0xD8, 0xBA, 0xCC, 0xCD, 0x12, 0xB2, 0x22, 0x8E, 0x69, 0xDF, 0x99,
-            0xCE, 0xD6, 0xB8, 0x59, 0x56, 0xCC, 0xAA, 0x70, 0x45, 0x8A, 0x60, 0xA5, 0x26, 0xCD,
-            0x6F, 0x1F, 0xF0, 0x7F, 0x3C, 0xDC, 0x58, 0x3D, 0xC0, 0x44, 0x97, 0x59, 0x83, 0xF3,
-            0x03, 0x84, 0x92, 0xE8, 0x1D, 0xD5, 0x58, 0x7D, 0xE7, 0xE8, 0x57, 0xED, 0xEE, 0x93,
-            0xC4, 0x13, 0x00, 0x1C, 0x26, 0xBD, 0x49, 0xFA, 0x0E, 0x4F, 0x27, 0x85, 0x33, 0x94,
-            0x7E, 0x22, 0xF3, 0x4B, 0x89, 0xFC, 0xCA, 0x52, 0xFC, 0xA9, 0xA4, 0xC3, 0xB3, 0xBD,
-            0xB7, 0x07, 0xED, 0x14, 0x96, 0x69, 0x5B, 0xFF, 0x97, 0x99, 0x10, 0x2C, 0x82, 0x9F,
-            0x1E, 0x46, 0x88, 0xDA, 0xEB, 0x3C, 0xD2, 0x4F, 0xA3, 0x82, 0xD0, 0xBF, 0xD5, 0x9D,
-            0x60, 0x82, 0x16, 0x7F, 0xE5, 0x3E, 0x5C, 0x47, 0x12, 0xF6, 0x03, 0x09, 0xC7, 0x6A,
+            0x40, 0x96, 0x72, 0x86, 0x22, 0xE2, 0x96, 0xD7, 0xC2, 0x06, 0x96, 0x53, 0x0D, 0x76,
+            0x07, 0x57, 0x06, 0x06, 0x62, 0x92, 0x07, 0xD7, 0xE6, 0xF0, 0xE4, 0x87, 0x46, 0x26,
+            0xD6, 0xD6, 0x04, 0x06, 0x76, 0x57, 0x52, 0xEC, 0xD6, 0x40, 0xF7, 0x52, 0x50, 0x50,
+            0x92, 0x86, 0x86, 0x37, 0x06, 0x11, 0xF6, 0xA2, 0x02, 0x06, 0xA2, 0xA2, 0x06, 0x56,
+            0x56, 0x42, 0x46, 0xEC, 0xF6, 0x05, 0x07, 0x26, 0x05, 0x04, 0x36, 0x17, 0xE2, 0x06,
+            0xF7, 0x11, 0xE2, 0x46, 0x46, 0x56, 0x36, 0xD6, 0xF7, 0x27, 0x04, 0x46, 0x76, 0xEC,
+            0xC2, 0x87, 0x56, 0x66, 0x86, 0xF6, 0x56, 0x42, 0x92, 0xF0, 0xE2, 0x11, 0x06, 0x26,
+            0xE7, 0xF7, 0x96, 0xF6, 0xC6, 0x07, 0x06, 0xA2, 0x06, 0xEC, 0x12, 0xF7, 0x42, 0x26,
+            0xE6, 0xE2, 0x40, 0x46, 0x46, 0x04, 0x86, 0x11, 0x06, 0x56, 0x07, 0x52, 0x96, 0xC2,
+            0xA2, 0xF2, 0x96, 0x36, 0x57, 0xEC, 0x86, 0x76, 0x57, 0x06, 0xE2, 0x07, 0x05, 0x07,
+            0x52, 0xF7, 0x26, 0x11, 0xF6, 0x82, 0x02, 0x16, 0x72, 0x46, 0x36, 0x96, 0xC2, 0x56,
+            0x52, 0xEC, 0xC6, 0x07, 0x06, 0xE6, 0x06, 0x56, 0x56, 0xF7, 0x07, 0xC6, 0x07, 0x11,
+            0x52, 0x46, 0x86, 0x42, 0x46, 0xC6, 0xE6, 0x53, 0x76, 0x42, 0x76, 0xEC, 0x06, 0x86,
+            0x96, 0x06, 0xF7, 0xC2, 0x42, 0xF0, 0x86, 0x06, 0x97, 0x11, 0xF6, 0x52, 0x76, 0x16,
+            0x76, 0x06, 0x07, 0xA2, 0x96, 0x97, 0x46, 0xEC, 0x62, 0x06, 0x80, 0x67, 0xE2, 0xD6,
+            0x57, 0x05, 0x36, 0x42, 0x82, 0x11, 0x06, 0x26, 0xA2, 0x46, 0x06, 0x52, 0x02, 0x36,
+            0x82, 0x07, 0x07, 0xEC, 0xC6, 0x96, 0x04, 0x57, 0xF6, 0x06, 0x06, 0xF2, 0x04, 0x36,
+            0x96, 0x11, 0x92, 0x86, 0xF7, 0xEC, 0xA7, 0xD6, 0xF4, 0xE9, 0x84, 0x03, 0x28, 0x2A,
+            0xE7, 0x71, 0x47, 0xE4, 0x99, 0x0D, 0x63, 0x98, 0x19, 0x11, 0x2F, 0x81, 0x7E, 0xE5,
+            0x21, 0x0F, 0x1B, 0x73, 0xA8, 0xE3, 0xCC, 0xFD, 0xB7, 0x5C, 0xC9, 0x0F, 0xEE, 0x2A,
+            0xDB, 0x2E, 0x1A, 0x2B, 0x9D, 0x0B, 0x61, 0x28, 0x49, 0xB0, 0x1A, 0x50, 0x54, 0xFC,
+            0xF6, 0x39, 0xB3, 0xC3, 0x2D, 0x6C, 0x41, 0x3C, 0xBA, 0xF6, 0x4E, 0x3E, 0xBC, 0x69,
+            0xB6, 0xB5, 0xE3, 0x23, 0x3A, 0xE3, 0xC4, 0x93, 0x8F, 0xD1, 0x9F, 0x0A, 0x10, 0x3B,
+            0x3C, 0x00, 0x04, 0xD6, 0xA5, 0xC1, 0x07, 0xB7, 0xFE, 0x59, 0x69, 0x75, 0xB5, 0xDF,
+            0xC6, 0x2D, 0xF7, 0x74, 0x2F, 0x1B, 0xC4, 0x61, 0x1B, 0xDD, 0x68, 0x47, 0xBB, 0xCE,
+            0x04, 0x94, 0xED, 0xAC, 0x8F, 0x92, 0x50, 0xEA, 0xA0, 0xF8, 0x0D, 0xFE, 0x13, 0x49,
+            0x77, 0x15, 0x73, 0xE1, 0x93, 0x09, 0x1E, 0x27, 0x13, 0x0D, 0x15, 0xD1, 0x0F, 0x8A,
+            0xB3, 0x59, 0x03, 0xF8, 0x60, 0x40, 0x51, 0x11, 0x0D, 0x53, 0x0C, 0x61, 0x48, 0xD7,
+            0xEF, 0x3C, 0xCB, 0xC0, 0x2F, 0x89, 0xC2, 0x08, 0xE7, 0x54, 0xCA, 0x95, 0x9E, 0x42,
+            0x0F, 0xE4, 0x8D, 0xC5, 0xCB, 0x60, 0x8C, 0xD1, 0x3B, 0x09, 0x82, 0xF1, 0x14, 0x12,
+            0xA5, 0xA9, 0xBE, 0x0F, 0x4C, 0x5C, 0xE4, 0x5C, 0x34, 0x80, 0xCF, 0x1D, 0x32, 0x56,
+            0x52, 0x76, 0xE6, 0x1B, 0xB2, 0x84, 0x7C, 0xBF, 0x8A, 0xAD, 0xBF, 0x6D, 0x82, 0xF6,
+            0x70, 0x9F, 0xFB, 0x14, 0xA2, 0x4B, 0x21, 0xAD, 0xB7, 0x30, 0x9E, 0xA9, 0xE0, 0x08,
+            0x39, 0x9F, 0xB8, 0x06, 0xAD, 0x14, 0x7B, 0xB9, 0xA6, 0x9C, 0xD6, 0x50, 0x6C, 0xD6,
+            0x03, 0xD3, 0x01, 0x27, 0x68, 0xF1, 0xC0, 0xFB, 0x6E, 0x75, 0x67, 0xE1, 0x9F, 0xC0,
+            0x73, 0x37, 0xBE, 0x4F, 0x54, 0x19, 0x0A, 0x9F, 0xDA, 0xAE, 0xDD, 0xDF, 0xB3, 0x89,
+            0x7C, 0xB0, 0x8E, 0xEB, 0xEB, 0x17, 0x23, 0x97, 0xD2, 0x60, 0x62, 0x89, 0xA8, 0x5C,
+            0x90, 0x44, 0x29, 0x82, 0x3A, 0x03, 0x49, 0xEE, 0xD8, 0x4C, 0x02, 0xB4, 0x82, 0x33,
         ];
         assert_eq!(data, expected);
 
         let input = "00000.UFF7THUFF7000001F8F7THUFF7UF00000000UFF7UFF7F7UFF7UF00000000UFF7UEUFF7T*000005F7UFF7UEUFF7UFF500000001F7T*00000.UFF7UF7QF7SK000.QOM:UPUFF7UFEA0000001+F7UFF7THUFF7UFEA0000001+F7UEUFF7UE0000003ZUFF7UF7QF7UFF7SK000000F7UF";
-        let data = assemble_qr_data(input, ErrorCorrection::Low);
+        let data = assemble_qr_data(
+            input,
+            ErrorCorrection::Low,
+            minimum_version(input, ErrorCorrection::Low),
+        );
         let expected = vec![
             0x27, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA,
             0xAA, 0xAA, 0xAA, 0xAA, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x50, 0x00, 0x00, 0x00,
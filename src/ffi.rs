@@ -0,0 +1,381 @@
+use crate::{EncodeError, ErrorCorrection, Options, QrCode};
+use std::ffi::c_char;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+pub const QR_EC_LOW: u8 = 0;
+pub const QR_EC_MEDIUM: u8 = 1;
+pub const QR_EC_QUARTILE: u8 = 2;
+pub const QR_EC_HIGH: u8 = 3;
+
+pub const QR_FORMAT_PNG: u32 = 0;
+pub const QR_FORMAT_SVG: u32 = 1;
+pub const QR_FORMAT_PDF: u32 = 2;
+pub const QR_FORMAT_TEXT: u32 = 3;
+
+/// The result of every call that can fail
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum QrStatus {
+    QrOk = 0,
+    /// A pointer that has to be set was null
+    QrNullPointer = 1,
+    /// The input isn't valid UTF-8
+    QrInvalidUtf8 = 2,
+    /// The input doesn't fit in a qr code with these options
+    QrDataTooLong = 3,
+    QrInvalidVersion = 4,
+    QrInvalidMask = 5,
+    /// The error correction level, scale or format isn't one of the allowed values
+    QrInvalidOption = 6,
+    /// The output doesn't fit in the buffer, and the length it needs has been written
+    QrBufferTooSmall = 7,
+    /// This build of the library can't write the format
+    QrUnsupportedFormat = 8,
+    /// Something went wrong inside the library
+    QrInternalError = 9,
+}
+
+/// Options for encoding and drawing, see `qr_default_options`
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct QrOptions {
+    /// One of the QR_EC_* levels
+    pub ec: u8,
+    /// Version from 1 to 40, or 0 for the smallest one that fits
+    pub version: u8,
+    /// Mask pattern from 0 to 7, or -1 for the one with the lowest penalty
+    pub mask: i8,
+    /// Draw light modules on a dark background
+    pub inverted: bool,
+    /// Flip the qr code horizontally
+    pub mirrored: bool,
+    /// Width of each module in pixels, or in points for pdfs, from 1 to 100
+    pub scale: u32,
+    /// Width of the border around the symbol in modules
+    pub quiet_zone: u32,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+}
+
+impl QrOptions {
+    fn to_options(self) -> Result<(ErrorCorrection, Options), QrStatus> {
+        let level = match self.ec {
+            QR_EC_LOW => ErrorCorrection::Low,
+            QR_EC_MEDIUM => ErrorCorrection::Medium,
+            QR_EC_QUARTILE => ErrorCorrection::Quartile,
+            QR_EC_HIGH => ErrorCorrection::High,
+            _ => return Err(QrStatus::QrInvalidOption),
+        };
+        if !(1..=100).contains(&self.scale) || self.quiet_zone > 100 {
+            return Err(QrStatus::QrInvalidOption);
+        }
+        let options = Options {
+            inverted: self.inverted,
+            mirrored: self.mirrored,
+            version: Some(self.version as usize).filter(|&version| version != 0),
+            mask: match self.mask {
+                -1 => None,
+                mask => Some(usize::try_from(mask).map_err(|_| QrStatus::QrInvalidMask)?),
+            },
+            scale: self.scale,
+            quiet_zone: self.quiet_zone,
+            foreground: self.foreground,
+            background: self.background,
+            caption: None,
+        };
+        Ok((level, options))
+    }
+}
+
+// Run the body of a call, making sure a panic never unwinds into the caller
+fn guard(body: impl FnOnce() -> Result<(), QrStatus>) -> QrStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => QrStatus::QrOk,
+        Ok(Err(status)) => status,
+        Err(_) => QrStatus::QrInternalError,
+    }
+}
+
+// Copy the output to the buffer if it fits, always reporting the length it needs
+unsafe fn write_buffer(
+    output: &[u8],
+    buffer: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> Result<(), QrStatus> {
+    if !written.is_null() {
+        *written = output.len();
+    }
+    if buffer.is_null() || capacity < output.len() {
+        return Err(QrStatus::QrBufferTooSmall);
+    }
+    std::ptr::copy_nonoverlapping(output.as_ptr(), buffer, output.len());
+    Ok(())
+}
+
+/// Get the default options: medium error correction, the smallest version,
+/// the best mask, a scale of 10 and a quiet zone of 4 modules, in black on white
+#[no_mangle]
+pub extern "C" fn qr_default_options() -> QrOptions {
+    let defaults = Options::default();
+    QrOptions {
+        ec: QR_EC_MEDIUM,
+        version: 0,
+        mask: -1,
+        inverted: defaults.inverted,
+        mirrored: defaults.mirrored,
+        scale: defaults.scale,
+        quiet_zone: defaults.quiet_zone,
+        foreground: defaults.foreground,
+        background: defaults.background,
+    }
+}
+
+/// Encode `length` bytes of UTF-8 text and store the qr code in `code`,
+/// which has to be released with `qr_free`. The default options are used
+/// when `options` is null, and `code` is set to null when encoding fails.
+///
+/// # Safety
+/// `data` has to point to `length` readable bytes, `options` has to be null
+/// or point to a `QrOptions`, and `code` has to point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn qr_create(
+    data: *const u8,
+    length: usize,
+    options: *const QrOptions,
+    code: *mut *mut QrCode,
+) -> QrStatus {
+    guard(|| {
+        if code.is_null() || (data.is_null() && length > 0) {
+            return Err(QrStatus::QrNullPointer);
+        }
+        *code = std::ptr::null_mut();
+
+        let bytes = if length == 0 {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(data, length)
+        };
+        let text = std::str::from_utf8(bytes).map_err(|_| QrStatus::QrInvalidUtf8)?;
+        let options = options
+            .as_ref()
+            .copied()
+            .unwrap_or_else(|| qr_default_options());
+        let (level, options) = options.to_options()?;
+        let qr = QrCode::new(text, level, &options).map_err(|error| match error {
+            EncodeError::DataTooLong => QrStatus::QrDataTooLong,
+            EncodeError::InvalidVersion(_) => QrStatus::QrInvalidVersion,
+            EncodeError::InvalidMask(_) => QrStatus::QrInvalidMask,
+        })?;
+        *code = Box::into_raw(Box::new(qr));
+        Ok(())
+    })
+}
+
+/// Get the width of the symbol in modules, not counting the quiet zone,
+/// or 0 when `code` is null
+///
+/// # Safety
+/// `code` has to be null or come from `qr_create`.
+#[no_mangle]
+pub unsafe extern "C" fn qr_size(code: *const QrCode) -> usize {
+    code.as_ref().map_or(0, QrCode::size)
+}
+
+/// Write the modules row by row to `buffer`, with 1 for a dark module and 0
+/// for a light one. The buffer needs room for `qr_size(code)` squared bytes.
+///
+/// # Safety
+/// `code` has to come from `qr_create` and `buffer` has to point to
+/// `capacity` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn qr_get_modules(
+    code: *const QrCode,
+    buffer: *mut u8,
+    capacity: usize,
+) -> QrStatus {
+    guard(|| {
+        let code = code.as_ref().ok_or(QrStatus::QrNullPointer)?;
+        let size = code.size();
+        let modules: Vec<u8> = (0..size * size)
+            .map(|i| code.is_dark(i % size, i / size) as u8)
+            .collect();
+        write_buffer(&modules, buffer, capacity, std::ptr::null_mut())
+    })
+}
+
+/// Draw the qr code in one of the QR_FORMAT_* formats into `buffer`. The
+/// length of the output is stored in `written` even when the buffer is too
+/// small, so passing a null buffer finds out how much room is needed. Text
+/// and svg output is UTF-8 without a terminating nul.
+///
+/// # Safety
+/// `code` has to come from `qr_create`, `buffer` has to be null or point to
+/// `capacity` writable bytes, and `written` has to be null or point to
+/// writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn qr_render(
+    code: *const QrCode,
+    format: u32,
+    buffer: *mut u8,
+    capacity: usize,
+    written: *mut usize,
+) -> QrStatus {
+    guard(|| {
+        let code = code.as_ref().ok_or(QrStatus::QrNullPointer)?;
+        let output = match format {
+            QR_FORMAT_PNG => render_png(code)?,
+            QR_FORMAT_SVG => code.to_svg().into_bytes(),
+            QR_FORMAT_PDF => code.to_pdf(),
+            QR_FORMAT_TEXT => code.to_text().into_bytes(),
+            _ => return Err(QrStatus::QrInvalidOption),
+        };
+        write_buffer(&output, buffer, capacity, written)
+    })
+}
+
+#[cfg(feature = "image")]
+fn render_png(code: &QrCode) -> Result<Vec<u8>, QrStatus> {
+    let mut png = std::io::Cursor::new(Vec::new());
+    code.to_image()
+        .write_to(&mut png, image::ImageFormat::Png)
+        .map_err(|_| QrStatus::QrInternalError)?;
+    Ok(png.into_inner())
+}
+
+#[cfg(not(feature = "image"))]
+fn render_png(_code: &QrCode) -> Result<Vec<u8>, QrStatus> {
+    Err(QrStatus::QrUnsupportedFormat)
+}
+
+/// Release a qr code from `qr_create`. Passing null does nothing.
+///
+/// # Safety
+/// `code` has to be null or come from `qr_create`, and can't be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn qr_free(code: *mut QrCode) {
+    if !code.is_null() {
+        drop(Box::from_raw(code));
+    }
+}
+
+/// Describe a status as a nul terminated string that lives for the whole program
+#[no_mangle]
+pub extern "C" fn qr_status_message(status: i32) -> *const c_char {
+    let message: &'static [u8] = match status {
+        0 => b"ok\0",
+        1 => b"a required pointer was null\0",
+        2 => b"the input is not valid UTF-8\0",
+        3 => b"the input is too long to fit in the qr code\0",
+        4 => b"the version is not between 1 and 40\0",
+        5 => b"the mask is not between 0 and 7\0",
+        6 => b"an option is out of range\0",
+        7 => b"the buffer is too small for the output\0",
+        8 => b"the format is not supported by this build\0",
+        9 => b"an internal error occurred\0",
+        _ => b"unknown status\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ffi::*;
+    use std::ffi::CStr;
+
+    fn create(text: &[u8], options: &QrOptions) -> Result<*mut QrCode, QrStatus> {
+        let mut code = std::ptr::null_mut();
+        match unsafe { qr_create(text.as_ptr(), text.len(), options, &mut code) } {
+            QrStatus::QrOk => Ok(code),
+            status => Err(status),
+        }
+    }
+
+    #[test]
+    fn test_creating() {
+        let options = qr_default_options();
+        let code = create(b"hello", &options).unwrap();
+        let size = unsafe { qr_size(code) };
+        assert_eq!(size, 21);
+
+        // The top left module is the corner of a finder pattern
+        let mut modules = vec![9; size * size];
+        let status = unsafe { qr_get_modules(code, modules.as_mut_ptr(), modules.len()) };
+        assert_eq!(status, QrStatus::QrOk);
+        assert_eq!(&modules[..8], [1, 1, 1, 1, 1, 1, 1, 0]);
+        assert!(modules.iter().all(|&module| module <= 1));
+        let status = unsafe { qr_get_modules(code, modules.as_mut_ptr(), 10) };
+        assert_eq!(status, QrStatus::QrBufferTooSmall);
+        unsafe { qr_free(code) };
+
+        let invalid = |options: QrOptions| create(b"hello", &options).err();
+        assert_eq!(
+            invalid(QrOptions { ec: 4, ..options }),
+            Some(QrStatus::QrInvalidOption)
+        );
+        assert_eq!(
+            invalid(QrOptions { mask: 8, ..options }),
+            Some(QrStatus::QrInvalidMask)
+        );
+        assert_eq!(
+            invalid(QrOptions {
+                mask: -2,
+                ..options
+            }),
+            Some(QrStatus::QrInvalidMask)
+        );
+        assert_eq!(
+            invalid(QrOptions {
+                version: 41,
+                ..options
+            }),
+            Some(QrStatus::QrInvalidVersion)
+        );
+        assert_eq!(
+            create(&[b'a'; 3000], &options).err(),
+            Some(QrStatus::QrDataTooLong)
+        );
+        assert_eq!(
+            create(&[0xff], &options).err(),
+            Some(QrStatus::QrInvalidUtf8)
+        );
+        let status =
+            unsafe { qr_create(std::ptr::null(), 0, std::ptr::null(), std::ptr::null_mut()) };
+        assert_eq!(status, QrStatus::QrNullPointer);
+    }
+
+    #[test]
+    fn test_rendering() {
+        let code = create(b"hello", &qr_default_options()).unwrap();
+
+        // Asking with no buffer gives the length that's needed
+        let mut written = 0;
+        let status =
+            unsafe { qr_render(code, QR_FORMAT_SVG, std::ptr::null_mut(), 0, &mut written) };
+        assert_eq!(status, QrStatus::QrBufferTooSmall);
+        let mut buffer = vec![0; written];
+        let status = unsafe {
+            qr_render(
+                code,
+                QR_FORMAT_SVG,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                &mut written,
+            )
+        };
+        assert_eq!(status, QrStatus::QrOk);
+        assert_eq!(String::from_utf8(buffer).unwrap(), unsafe {
+            (*code).to_svg()
+        });
+
+        let status = unsafe { qr_render(code, 7, std::ptr::null_mut(), 0, &mut written) };
+        assert_eq!(status, QrStatus::QrInvalidOption);
+        unsafe { qr_free(code) };
+
+        let message = unsafe { CStr::from_ptr(qr_status_message(QrStatus::QrDataTooLong as i32)) };
+        assert_eq!(
+            message.to_str(),
+            Ok("the input is too long to fit in the qr code")
+        );
+    }
+}
@@ -0,0 +1,192 @@
+// Arithmetic in the galois field GF(256) that qr codes use for error correction.
+// Elements are bytes, addition is xor, and multiplication is done modulo
+// the primitive polynomial x^8 + x^4 + x^3 + x^2 + 1.
+//
+// Polynomials here are stored with the lowest degree coefficient first
+
+const PRIMITIVE_POLYNOMIAL: u16 = 0x11D;
+
+/// The largest number of error correction codewords per block in `tables::ECC_DATA`
+pub const MAX_ECC_COUNT: usize = 30;
+
+// Every non zero element is a power of the generator 2, so multiplication can
+// be done by adding logarithms. The exponent table is doubled in length so
+// the sum of two logarithms never needs to be reduced modulo 255.
+const fn build_tables() -> ([u8; 512], [u8; 256]) {
+    let mut exp = [0; 512];
+    let mut log = [0; 256];
+    let mut value: u16 = 1;
+    let mut i = 0;
+    while i < 255 {
+        exp[i] = value as u8;
+        exp[i + 255] = value as u8;
+        log[value as usize] = i as u8;
+        value <<= 1;
+        if value & 0x100 != 0 {
+            value ^= PRIMITIVE_POLYNOMIAL;
+        }
+        i += 1;
+    }
+    (exp, log)
+}
+
+const TABLES: ([u8; 512], [u8; 256]) = build_tables();
+static EXP: [u8; 512] = TABLES.0;
+static LOG: [u8; 256] = TABLES.1;
+
+const fn const_multiply(x: u8, y: u8) -> u8 {
+    if x == 0 || y == 0 {
+        return 0;
+    }
+    TABLES.0[TABLES.1[x as usize] as usize + TABLES.1[y as usize] as usize]
+}
+
+// Compute the generator polynomial (x - 2^0)(x - 2^1)...(x - 2^(degree - 1))
+// for every degree. The leading coefficient is always 1, so only the remaining
+// coefficients are stored, highest degree first, as the encoder divides by them.
+const fn build_generator_polynomials() -> [[u8; MAX_ECC_COUNT]; MAX_ECC_COUNT + 1] {
+    let mut generators = [[0; MAX_ECC_COUNT]; MAX_ECC_COUNT + 1];
+    let mut degree = 1;
+    while degree <= MAX_ECC_COUNT {
+        // Start off with x ^ 0, stored lowest degree first while building
+        let mut result = [0u8; MAX_ECC_COUNT + 1];
+        result[0] = 1;
+        let mut i = 0;
+        while i < degree {
+            // Multiply by (x + 2^i)
+            let root = TABLES.0[i];
+            let mut j = i + 1;
+            while j > 0 {
+                result[j] = result[j - 1] ^ const_multiply(result[j], root);
+                j -= 1;
+            }
+            result[0] = const_multiply(result[0], root);
+            i += 1;
+        }
+
+        let mut j = 0;
+        while j < degree {
+            generators[degree][j] = result[degree - 1 - j];
+            j += 1;
+        }
+        degree += 1;
+    }
+    generators
+}
+
+static GENERATOR_POLYNOMIALS: [[u8; MAX_ECC_COUNT]; MAX_ECC_COUNT + 1] =
+    build_generator_polynomials();
+
+/// Get the generator polynomial for a number of error correction codewords,
+/// without its leading coefficient and with the highest degree coefficient first
+pub fn get_generator_polynomial(degree: usize) -> &'static [u8] {
+    &GENERATOR_POLYNOMIALS[degree][..degree]
+}
+
+/// 2 raised to a power
+pub fn exp(power: usize) -> u8 {
+    EXP[power % 255]
+}
+
+pub fn multiply(x: u8, y: u8) -> u8 {
+    if x == 0 || y == 0 {
+        return 0;
+    }
+    EXP[LOG[x as usize] as usize + LOG[y as usize] as usize]
+}
+
+/// Panics when dividing by zero
+pub fn divide(x: u8, y: u8) -> u8 {
+    assert!(y != 0, "division by zero in GF(256)");
+    if x == 0 {
+        return 0;
+    }
+    EXP[LOG[x as usize] as usize + 255 - LOG[y as usize] as usize]
+}
+
+/// Panics when inverting zero
+pub fn inverse(x: u8) -> u8 {
+    divide(1, x)
+}
+
+pub fn evaluate(polynomial: &[u8], x: u8) -> u8 {
+    polynomial
+        .iter()
+        .rev()
+        .fold(0, |result, &coefficient| multiply(result, x) ^ coefficient)
+}
+
+pub fn multiply_polynomials(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate() {
+        for (j, &y) in b.iter().enumerate() {
+            result[i + j] ^= multiply(x, y);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gf256::*;
+    use crate::tables;
+
+    // The bit by bit multiplication the tables replace
+    fn russian_peasant_multiply(x: u8, y: u8) -> u8 {
+        let mut z: u8 = 0;
+        for i in (0..8).rev() {
+            z = (z << 1) ^ ((z >> 7) * 0x1D);
+            z ^= ((y >> i) & 1) * x;
+        }
+        z
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        for x in 0..=255 {
+            for y in 0..=255 {
+                let product = multiply(x, y);
+                assert_eq!(product, russian_peasant_multiply(x, y));
+                if y != 0 {
+                    assert_eq!(divide(product, y), x);
+                }
+            }
+            if x != 0 {
+                assert_eq!(multiply(x, inverse(x)), 1);
+            }
+        }
+
+        assert_eq!(exp(0), 1);
+        assert_eq!(exp(8), 0x1D);
+        assert_eq!(exp(255), 1);
+
+        // x^2 + 3 at x = 2 is 4 xor 3
+        assert_eq!(evaluate(&[3, 0, 1], 2), 7);
+        // (x + 1)(x + 1) = x^2 + 1 since addition is xor
+        assert_eq!(multiply_polynomials(&[1, 1], &[1, 1]), [1, 0, 1]);
+    }
+
+    #[test]
+    fn test_generator_polynomials() {
+        // Every ecc length in the tables must have a generator polynomial
+        for version in tables::ECC_DATA {
+            for info in version {
+                assert!(info[0] as usize <= MAX_ECC_COUNT);
+            }
+        }
+
+        for degree in 1..=MAX_ECC_COUNT {
+            let mut expected = vec![1];
+            for i in 0..degree {
+                expected = multiply_polynomials(&expected, &[exp(i), 1]);
+            }
+            expected.pop();
+            expected.reverse();
+            assert_eq!(get_generator_polynomial(degree), expected);
+        }
+
+        // The degree 7 polynomial from the specification, as powers of 2
+        let expected = [87, 229, 146, 149, 238, 102, 21].map(exp);
+        assert_eq!(get_generator_polynomial(7), expected);
+    }
+}
@@ -1,45 +1,320 @@
+mod bch;
+mod decoder;
 mod drawer;
 mod encoder;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod font;
+mod gf256;
+pub mod payload;
+mod reed_solomon;
+mod render;
+#[cfg(feature = "image")]
+mod scanner;
+mod sheet;
 mod tables;
+#[cfg(feature = "wasm")]
+mod wasm;
 
-pub enum ErrorCorrection {
-    Low,
-    Medium,
-    Quartile,
-    High,
+pub use decoder::{DecodeError, Decoded};
+pub use encoder::{EncodeError, EncodingMode, ErrorCorrection, Section, SectionKind};
+pub use sheet::{Label, LabelSheet, PageSize, SheetError};
+
+/// Where a caption is placed across the width of the symbol
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// Text printed below the qr code with a built in pixel font. Text that's
+/// wider than the symbol is shortened to fit and ends with dots.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Caption {
+    pub text: String,
+    pub alignment: Alignment,
+    /// Height of the letters in modules. The caption isn't drawn unless it's positive.
+    pub size: f64,
 }
 
-fn map_levels(level: ErrorCorrection) -> encoder::ErrorCorrection {
-    match level {
-        ErrorCorrection::Low => encoder::ErrorCorrection::Low,
-        ErrorCorrection::Medium => encoder::ErrorCorrection::Medium,
-        ErrorCorrection::Quartile => encoder::ErrorCorrection::Quartile,
-        ErrorCorrection::High => encoder::ErrorCorrection::High,
+impl Caption {
+    pub fn new(text: &str) -> Self {
+        Caption {
+            text: text.to_string(),
+            alignment: Alignment::Center,
+            size: 2.0,
+        }
     }
 }
 
-pub fn generate_qr_code(input: &str, level: ErrorCorrection, output_file: &str) {
-    let qr = drawer::QR::create(input, map_levels(level));
+/// Extra options for how the qr code is drawn
+#[derive(Clone)]
+pub struct Options {
+    /// Draw light modules on a dark background
+    pub inverted: bool,
+    /// Flip the qr code horizontally
+    pub mirrored: bool,
+    /// Use this version instead of the smallest one that fits the input
+    pub version: Option<usize>,
+    /// Use this mask pattern instead of the one with the lowest penalty
+    pub mask: Option<usize>,
+    /// Width of each module in pixels, or in points for pdfs
+    pub scale: u32,
+    /// Width of the border around the symbol in modules
+    pub quiet_zone: u32,
+    pub foreground: [u8; 3],
+    pub background: [u8; 3],
+    /// Text to print below the quiet zone
+    pub caption: Option<Caption>,
+}
 
-    let pixel_size = 10;
-    let outer_padding = 4 * pixel_size;
-    let size = qr.size as u32 * pixel_size;
-    let img_size = size + outer_padding * 2;
-    let mut img = image::ImageBuffer::new(img_size, img_size);
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            inverted: false,
+            mirrored: false,
+            version: None,
+            mask: None,
+            scale: 10,
+            quiet_zone: 4,
+            foreground: [0, 0, 0],
+            background: [255, 255, 255],
+            caption: None,
+        }
+    }
+}
 
-    for (x, y, pixel) in img.enumerate_pixels_mut() {
-        let x_inside = x >= outer_padding && x < size + outer_padding;
-        let y_inside = y >= outer_padding && y < size + outer_padding;
-        if x_inside && y_inside {
-            let matrix_x = (x - outer_padding) / pixel_size;
-            let matrix_y = (y - outer_padding) / pixel_size;
-            let index = (matrix_y * qr.size as u32 + matrix_x) as usize;
-            let color = qr.matrix[index];
-            *pixel = image::Rgb([color, color, color]);
-        } else {
-            *pixel = image::Rgb([255, 255, 255]);
+/// An encoded qr code that can be drawn in different formats
+pub struct QrCode {
+    qr: drawer::QR,
+    options: Options,
+}
+
+impl QrCode {
+    pub fn new(
+        input: &str,
+        level: ErrorCorrection,
+        options: &Options,
+    ) -> Result<Self, EncodeError> {
+        if let Some(mask) = options.mask.filter(|&mask| mask > 7) {
+            return Err(EncodeError::InvalidMask(mask));
+        }
+        if let Some(version) = options
+            .version
+            .filter(|version| !(1..=40).contains(version))
+        {
+            return Err(EncodeError::InvalidVersion(version));
+        }
+
+        let mode = encoder::get_encoding_mode(input);
+        let minimum =
+            encoder::get_version(level, mode, input.len()).ok_or(EncodeError::DataTooLong)?;
+        let version = options.version.unwrap_or(minimum);
+        if version < minimum {
+            return Err(EncodeError::DataTooLong);
         }
+
+        let mut qr = drawer::QR::create_with(input, level, version, options.mask);
+        if options.inverted {
+            qr.invert();
+        }
+        if options.mirrored {
+            qr.mirror();
+        }
+        Ok(QrCode {
+            qr,
+            options: options.clone(),
+        })
     }
 
-    img.save(output_file).unwrap();
+    /// Width of the symbol in modules, not counting the quiet zone
+    pub fn size(&self) -> usize {
+        self.qr.size
+    }
+
+    pub fn version(&self) -> usize {
+        self.qr.version
+    }
+
+    pub fn level(&self) -> ErrorCorrection {
+        self.qr.level
+    }
+
+    pub fn mask(&self) -> usize {
+        self.qr.mask
+    }
+
+    /// Check whether the module at (x, y) is drawn in the foreground color
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        self.qr.get_module(x, y) == 0
+    }
+
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> image::RgbImage {
+        render::to_image(&self.qr, &self.options)
+    }
+
+    pub fn to_svg(&self) -> String {
+        render::to_svg(&self.qr, &self.options)
+    }
+
+    pub fn to_pdf(&self) -> Vec<u8> {
+        render::to_pdf(&self.qr, &self.options)
+    }
+
+    /// Draw the qr code as text for printing to a terminal. Each character holds
+    /// two rows of modules, with dark modules drawn as blocks, so terminals with
+    /// a dark background need the inverted option to show the code the right way round.
+    pub fn to_text(&self) -> String {
+        render::to_text(&self.qr, &self.options)
+    }
+}
+
+/// Reasons a qr code can't be generated and saved to a file
+#[cfg(feature = "image")]
+#[derive(Debug)]
+pub enum GenerateError {
+    Encoding(EncodeError),
+    /// The image couldn't be written, or its extension isn't an image format
+    Image(image::ImageError),
+}
+
+#[cfg(feature = "image")]
+impl std::fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GenerateError::Encoding(error) => write!(f, "{}", error),
+            GenerateError::Image(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl std::error::Error for GenerateError {}
+
+#[cfg(feature = "image")]
+pub fn generate_qr_code(
+    input: &str,
+    level: ErrorCorrection,
+    output_file: &str,
+) -> Result<(), GenerateError> {
+    generate_qr_code_with_options(input, level, &Options::default(), output_file)
+}
+
+#[cfg(feature = "image")]
+pub fn generate_qr_code_with_options(
+    input: &str,
+    level: ErrorCorrection,
+    options: &Options,
+    output_file: &str,
+) -> Result<(), GenerateError> {
+    render_qr_code(input, level, options)
+        .map_err(GenerateError::Encoding)?
+        .save(output_file)
+        .map_err(GenerateError::Image)
+}
+
+/// How a qr code is put together, for debugging
+pub struct Inspection {
+    pub mode: EncodingMode,
+    pub version: usize,
+    pub level: ErrorCorrection,
+    pub mask: usize,
+    /// Number of characters in the input, counting bytes in byte mode
+    pub length: usize,
+    /// Number of characters that fit with this version, level and mode
+    pub capacity: usize,
+    /// Number of data codewords in each block
+    pub data_codewords: Vec<usize>,
+    /// Number of error correction codewords added to every block
+    pub ec_codewords: usize,
+    /// The data bitstream split into its parts
+    pub sections: Vec<Section>,
+    /// The run, block, finder pattern and dark ratio penalties of each mask
+    pub penalties: [[u32; 4]; 8],
+}
+
+/// Encode the input and describe each step instead of drawing it
+pub fn inspect_qr_code(
+    input: &str,
+    level: ErrorCorrection,
+    options: &Options,
+) -> Result<Inspection, EncodeError> {
+    let qr = QrCode::new(input, level, options)?.qr;
+    let mode = encoder::get_encoding_mode(input);
+    let info = tables::ECC_DATA[qr.version - 1][level as usize];
+    let mut data_codewords = vec![info[2] as usize; info[1] as usize];
+    data_codewords.extend(vec![info[4] as usize; info[3] as usize]);
+
+    Ok(Inspection {
+        mode,
+        version: qr.version,
+        level,
+        mask: qr.mask,
+        length: input.len(),
+        capacity: tables::CHARACTER_CAPACITIES[qr.version - 1][level as usize][mode as usize]
+            as usize,
+        data_codewords,
+        ec_codewords: info[0] as usize,
+        sections: encoder::get_bitstream_sections(input, level, qr.version),
+        penalties: qr.get_mask_penalties(),
+    })
+}
+
+/// Draw the qr code to an image without saving it
+#[cfg(feature = "image")]
+pub fn render_qr_code(
+    input: &str,
+    level: ErrorCorrection,
+    options: &Options,
+) -> Result<image::RgbImage, EncodeError> {
+    Ok(QrCode::new(input, level, options)?.to_image())
+}
+
+/// Draw the qr code as text for printing to a terminal, see `QrCode::to_text`
+pub fn render_qr_code_to_text(
+    input: &str,
+    level: ErrorCorrection,
+    options: &Options,
+) -> Result<String, EncodeError> {
+    Ok(QrCode::new(input, level, options)?.to_text())
+}
+
+/// Read an error correction level from its letter, L, M, Q or H, in either case
+pub fn parse_level(level: &str) -> Option<ErrorCorrection> {
+    match level.to_ascii_uppercase().as_str() {
+        "L" => Some(ErrorCorrection::Low),
+        "M" => Some(ErrorCorrection::Medium),
+        "Q" => Some(ErrorCorrection::Quartile),
+        "H" => Some(ErrorCorrection::High),
+        _ => None,
+    }
+}
+
+/// Read a hex color like #1a2b3c, where the # is optional
+pub fn parse_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Some([channel(0), channel(2), channel(4)])
+}
+
+/// Decode a qr code from a row major matrix of modules, where 0 is dark and 255 is light
+pub fn decode_matrix(size: usize, matrix: &[u8]) -> Result<Decoded, DecodeError> {
+    decoder::decode(size, matrix)
+}
+
+/// Find and decode all the qr codes in an image
+#[cfg(feature = "image")]
+pub fn decode_image(image: &image::DynamicImage) -> Vec<Decoded> {
+    scanner::scan(&image.to_luma8())
+}
+
+/// Find and decode all the qr codes in an image file
+#[cfg(feature = "image")]
+pub fn decode_image_file(input_file: &str) -> Result<Vec<Decoded>, image::ImageError> {
+    Ok(decode_image(&image::open(input_file)?))
 }
@@ -1,27 +1,49 @@
-use std::io::Write;
+mod cli;
 
-fn get_input(prompt: &str) -> String {
-    print!("{}", prompt);
-    std::io::stdout().flush().unwrap();
-    let mut line = String::new();
-    std::io::stdin().read_line(&mut line).unwrap();
-    line.trim().to_string()
-}
+use clap::{Parser, Subcommand};
+use std::process::ExitCode;
+
+/// Generate a qr code from text. The output format is picked from the
+/// extension of the output file, and the code is printed to the terminal
+/// when there's no output file.
+#[derive(Parser)]
+#[command(
+    name = "qr",
+    disable_version_flag = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(flatten)]
+    encode: cli::encode::EncodeArgs,
 
-fn main() {
-    println!("QR Code Generator v0.1");
-    println!("Possible error correction levels are: (L)ow, (M)edium, (Q)uartile, and (H)igh");
+    #[command(subcommand)]
+    command: Option<Command>,
+}
 
-    let input = get_input("Input > ");
-    let output = get_input("Output file path > ");
-    let level = get_input("Error correction level > ");
+#[derive(Subcommand)]
+enum Command {
+    Batch(cli::batch::BatchArgs),
+    Decode(cli::decode::DecodeArgs),
+    Inspect(cli::inspect::InspectArgs),
+    Serve(cli::serve::ServeArgs),
+    Sheet(cli::sheet::SheetArgs),
+}
 
-    let error_correction = match level.trim() {
-        "L" => qrgen::ErrorCorrection::Low,
-        "M" => qrgen::ErrorCorrection::Medium,
-        "Q" => qrgen::ErrorCorrection::Quartile,
-        "H" => qrgen::ErrorCorrection::High,
-        _ => qrgen::ErrorCorrection::Low,
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match &cli.command {
+        None => cli::encode::run(&cli.encode),
+        Some(Command::Batch(args)) => cli::batch::run(args),
+        Some(Command::Decode(args)) => cli::decode::run(args),
+        Some(Command::Inspect(args)) => cli::inspect::run(args),
+        Some(Command::Serve(args)) => cli::serve::run(args),
+        Some(Command::Sheet(args)) => cli::sheet::run(args),
     };
-    qrgen::generate_qr_code(input.as_str(), error_correction, output.as_str());
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(failure) => {
+            eprintln!("error: {}", failure);
+            failure.exit_code()
+        }
+    }
 }
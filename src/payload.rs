@@ -0,0 +1,326 @@
+//! Builders and parsers for the structured text that common kinds of qr codes hold
+
+mod bitcoin;
+mod contact;
+mod emv;
+mod ethereum;
+mod event;
+mod geo;
+mod girocode;
+mod gs1;
+mod message;
+mod otp;
+mod swiss;
+mod wifi;
+
+use crate::encoder::get_encoding_mode;
+use crate::{EncodeError, EncodingMode};
+
+pub use bitcoin::Bitcoin;
+pub use contact::{Address, Contact, ContactFormat};
+pub use emv::{EmvPayment, Tlv, TlvValue};
+pub use ethereum::Ethereum;
+pub use event::Event;
+pub use geo::Geo;
+pub use girocode::{GiroCode, Remittance};
+pub use gs1::DigitalLink;
+pub use message::{Email, Phone, Sms, SmsFormat};
+pub use otp::{Algorithm, Otp, OtpKind};
+pub use swiss::{Currency, Rectangle, SwissAddress, SwissQrBill, SwissReference};
+pub use wifi::{Security, Wifi};
+
+// Lines longer than this many bytes are folded onto continuation lines
+const MAX_LINE_LENGTH: usize = 75;
+
+#[derive(Debug, PartialEq)]
+pub enum PayloadError {
+    /// The text doesn't start with the scheme or header the payload needs
+    InvalidPrefix,
+    MissingField(&'static str),
+    InvalidField(&'static str),
+    /// A field or the whole payload is longer than allowed
+    TooLong(&'static str),
+    /// A field's check digits don't match
+    InvalidChecksum(&'static str),
+    /// The payload was built but doesn't fit in a qr code with the options given
+    Encoding(EncodeError),
+}
+
+impl std::fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PayloadError::InvalidPrefix => write!(f, "the payload has an unexpected prefix"),
+            PayloadError::MissingField(field) => write!(f, "the {} field is missing", field),
+            PayloadError::InvalidField(field) => write!(f, "the {} field is invalid", field),
+            PayloadError::TooLong(field) => write!(f, "the {} is too long", field),
+            PayloadError::InvalidChecksum(field) => {
+                write!(f, "the {} has the wrong check digits", field)
+            }
+            PayloadError::Encoding(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+// Put a backslash before any of the special characters
+fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// Split text on a separator that isn't escaped by a backslash.
+// The parts keep their backslashes.
+fn split_escaped(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+// Remove the backslashes that escape special characters
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// Escape a vCard or iCalendar text value. Line breaks are written as \n.
+fn escape_text(value: &str) -> String {
+    escape(value, &['\\', ';', ','])
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+// Split a vCard or iCalendar line into lines of at most 75 bytes, with each continuation line starting
+// with a space. Multi-byte characters are kept together.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > MAX_LINE_LENGTH {
+            folded += "\r\n ";
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded
+}
+
+// Percent encode everything other than the characters a URI never needs to escape
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded += &format!("%{:02X}", byte),
+        }
+    }
+    encoded
+}
+
+// Reverse percent encoding, failing on bad escapes or invalid UTF-8
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let high = (iter.next()? as char).to_digit(16)?;
+            let low = (iter.next()? as char).to_digit(16)?;
+            bytes.push((high * 16 + low) as u8);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+// Schemes are case insensitive, so writing one in upper case lets the encoder
+// use alphanumeric mode when the rest of the text is alphanumeric too
+fn with_scheme(scheme: &str, rest: &str) -> String {
+    let upper = format!("{}:{}", scheme.to_ascii_uppercase(), rest);
+    if get_encoding_mode(&upper) == EncodingMode::Alphanumeric {
+        upper
+    } else {
+        format!("{}:{}", scheme, rest)
+    }
+}
+
+/// Write a URL's scheme and host in upper case when that lets the whole URL be
+/// encoded in alphanumeric mode, which gives a smaller symbol. Both are case
+/// insensitive, so the URL still leads to the same place.
+pub fn uppercase_url(url: &str) -> String {
+    let host_start = url.find("://").map_or(0, |i| i + 3);
+    let host_end = url[host_start..]
+        .find('/')
+        .map_or(url.len(), |i| i + host_start);
+    let upper = url[..host_end].to_ascii_uppercase() + &url[host_end..];
+    if get_encoding_mode(&upper) == EncodingMode::Alphanumeric {
+        upper
+    } else {
+        url.to_string()
+    }
+}
+
+// Compute the ISO 7064 mod 97-10 remainder used by IBANs and creditor references.
+// The first 4 characters are moved to the end and letters count as 10 to 35.
+fn get_mod97_remainder(value: &str) -> Option<u32> {
+    if value.len() < 4 || !value.is_ascii() {
+        return None;
+    }
+
+    let (head, tail) = value.split_at(4);
+    let mut remainder = 0;
+    for c in tail.chars().chain(head.chars()) {
+        let digit = c.to_digit(36)?;
+        remainder = if digit < 10 {
+            (remainder * 10 + digit) % 97
+        } else {
+            (remainder * 100 + digit) % 97
+        };
+    }
+    Some(remainder)
+}
+
+// Remove the spaces that IBANs and references are usually printed with
+fn remove_spaces(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+// Check a field of a payload whose fields are separated by line breaks,
+// so it can't contain any, and that it isn't longer than max characters
+fn check_text(value: &str, max: usize, field: &'static str) -> Result<(), PayloadError> {
+    if value.contains(['\r', '\n']) {
+        return Err(PayloadError::InvalidField(field));
+    }
+    if value.chars().count() > max {
+        return Err(PayloadError::TooLong(field));
+    }
+    Ok(())
+}
+
+// Check an IBAN's country code, length and check digits.
+// Returns the IBAN without spaces and in upper case.
+fn validate_iban(iban: &str) -> Result<String, PayloadError> {
+    let iban = remove_spaces(iban).to_ascii_uppercase();
+    let valid_format = (15..=34).contains(&iban.len())
+        && iban.chars().take(2).all(|c| c.is_ascii_uppercase())
+        && iban.chars().skip(2).take(2).all(|c| c.is_ascii_digit())
+        && iban.chars().all(|c| c.is_ascii_alphanumeric());
+    if !valid_format {
+        return Err(PayloadError::InvalidField("iban"));
+    }
+    if get_mod97_remainder(&iban) != Some(1) {
+        return Err(PayloadError::InvalidChecksum("iban"));
+    }
+    Ok(iban)
+}
+
+// Check an ISO 11649 creditor reference, which looks like RF18 5390 0754 7034.
+// Returns the reference without spaces and in upper case.
+fn validate_creditor_reference(reference: &str) -> Result<String, PayloadError> {
+    let reference = remove_spaces(reference).to_ascii_uppercase();
+    let valid_format = (5..=25).contains(&reference.len())
+        && reference.starts_with("RF")
+        && reference
+            .chars()
+            .skip(2)
+            .take(2)
+            .all(|c| c.is_ascii_digit())
+        && reference.chars().all(|c| c.is_ascii_alphanumeric());
+    if !valid_format {
+        return Err(PayloadError::InvalidField("reference"));
+    }
+    if get_mod97_remainder(&reference) != Some(1) {
+        return Err(PayloadError::InvalidChecksum("reference"));
+    }
+    Ok(reference)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape("a;b\\c", &[';', '\\']), "a\\;b\\\\c");
+        assert_eq!(split_escaped("a\\;b;c", ';'), ["a\\;b", "c"]);
+        assert_eq!(unescape("a\\;b\\\\"), "a;b\\");
+        assert_eq!(percent_encode("a b/é"), "a%20b%2F%C3%A9");
+        assert_eq!(percent_decode("a%20b%2f%C3%A9"), Some("a b/é".to_string()));
+        assert_eq!(percent_decode("100%"), None);
+        assert_eq!(percent_decode("%+1"), None);
+        assert_eq!(percent_decode("%FF"), None);
+    }
+
+    #[test]
+    fn test_iban_validation() {
+        assert_eq!(
+            validate_iban("de89 3704 0044 0532 0130 00"),
+            Ok("DE89370400440532013000".to_string())
+        );
+        assert!(validate_iban("GB82WEST12345698765432").is_ok());
+        assert!(validate_iban("CH93 0076 2011 6238 5295 7").is_ok());
+        assert_eq!(
+            validate_iban("DE88370400440532013000"),
+            Err(PayloadError::InvalidChecksum("iban"))
+        );
+        assert_eq!(
+            validate_iban("DE89-3704"),
+            Err(PayloadError::InvalidField("iban"))
+        );
+    }
+
+    #[test]
+    fn test_creditor_reference_validation() {
+        assert_eq!(
+            validate_creditor_reference("RF18 5390 0754 7034"),
+            Ok("RF18539007547034".to_string())
+        );
+        assert!(validate_creditor_reference("RF712348231").is_ok());
+        assert_eq!(
+            validate_creditor_reference("RF19539007547034"),
+            Err(PayloadError::InvalidChecksum("reference"))
+        );
+        assert_eq!(
+            validate_creditor_reference("XX18539007547034"),
+            Err(PayloadError::InvalidField("reference"))
+        );
+        assert_eq!(
+            validate_creditor_reference("RF€12"),
+            Err(PayloadError::InvalidField("reference"))
+        );
+        assert_eq!(
+            GiroCode::new("x", "DE89370400440532013000")
+                .remittance(Remittance::Reference("RF1€2".to_string()))
+                .build(),
+            Err(PayloadError::InvalidField("reference"))
+        );
+    }
+}
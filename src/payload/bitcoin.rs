@@ -0,0 +1,431 @@
+use super::{percent_encode, with_scheme, PayloadError};
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32_ALPHABET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+// What a valid bech32 checksum leaves in the polynomial. Segwit version 0
+// addresses use bech32 and later versions use bech32m.
+const BECH32_CONSTANT: u32 = 1;
+const BECH32M_CONSTANT: u32 = 0x2bc830a3;
+
+const SATOSHIS_PER_BITCOIN: u64 = 100_000_000;
+const MAX_SATOSHIS: u64 = 21_000_000 * SATOSHIS_PER_BITCOIN;
+
+/// A BIP21 `bitcoin:` payment request. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bitcoin {
+    pub address: String,
+    /// The amount in satoshis
+    pub amount: Option<u64>,
+    /// Who is being paid
+    pub label: String,
+    /// What the payment is for
+    pub message: String,
+    /// A BOLT11 lightning invoice that wallets can pay instead
+    pub lightning: String,
+    /// Write bech32 addresses in upper case. Without any parameters this
+    /// lets the whole text be encoded in alphanumeric mode, which gives a
+    /// smaller symbol, but some older wallets only accept lower case.
+    pub uppercase: bool,
+}
+
+impl Bitcoin {
+    pub fn new(address: &str) -> Self {
+        Bitcoin {
+            address: address.to_string(),
+            amount: None,
+            label: String::new(),
+            message: String::new(),
+            lightning: String::new(),
+            uppercase: false,
+        }
+    }
+
+    /// Set the amount in satoshis
+    pub fn amount(mut self, satoshis: u64) -> Self {
+        self.amount = Some(satoshis);
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self
+    }
+
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    pub fn lightning(mut self, invoice: &str) -> Self {
+        self.lightning = invoice.to_string();
+        self
+    }
+
+    pub fn uppercase(mut self, uppercase: bool) -> Self {
+        self.uppercase = uppercase;
+        self
+    }
+
+    /// Validate the address and amount and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        let is_bech32 = is_valid_segwit_address(&self.address);
+        if !is_bech32 && !is_valid_base58_address(&self.address) {
+            return Err(PayloadError::InvalidChecksum("address"));
+        }
+
+        let mut parameters = Vec::new();
+        match self.amount {
+            Some(satoshis) if (1..=MAX_SATOSHIS).contains(&satoshis) => {
+                parameters.push(format!("amount={}", format_bitcoin(satoshis)));
+            }
+            Some(_) => return Err(PayloadError::InvalidField("amount")),
+            None => {}
+        }
+        if !self.label.is_empty() {
+            parameters.push(format!("label={}", percent_encode(&self.label)));
+        }
+        if !self.message.is_empty() {
+            parameters.push(format!("message={}", percent_encode(&self.message)));
+        }
+        if !self.lightning.is_empty() {
+            if !self.lightning.to_ascii_lowercase().starts_with("ln") {
+                return Err(PayloadError::InvalidField("lightning"));
+            }
+            parameters.push(format!("lightning={}", self.lightning));
+        }
+
+        let mut text = if is_bech32 && self.uppercase {
+            self.address.to_ascii_uppercase()
+        } else {
+            self.address.clone()
+        };
+        if !parameters.is_empty() {
+            text += &format!("?{}", parameters.join("&"));
+        }
+        Ok(with_scheme("bitcoin", &text))
+    }
+}
+
+// Write satoshis as bitcoin, without trailing zeros
+fn format_bitcoin(satoshis: u64) -> String {
+    let whole = satoshis / SATOSHIS_PER_BITCOIN;
+    let fraction = satoshis % SATOSHIS_PER_BITCOIN;
+    if fraction == 0 {
+        return whole.to_string();
+    }
+    let fraction = format!("{:08}", fraction);
+    format!("{}.{}", whole, fraction.trim_end_matches('0'))
+}
+
+// Check a legacy address: a version byte, a 20 byte hash and a checksum
+// made from the first 4 bytes of the double SHA-256 of the rest
+fn is_valid_base58_address(address: &str) -> bool {
+    if !(26..=35).contains(&address.len()) {
+        return false;
+    }
+
+    // Convert from base 58 to base 256, with each leading 1 becoming a zero byte
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in address.bytes() {
+        let Some(mut carry) = BASE58_ALPHABET.iter().position(|&x| x == c) else {
+            return false;
+        };
+        for byte in bytes.iter_mut().rev() {
+            carry += *byte as usize * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = address.bytes().take_while(|&c| c == b'1').count();
+    let mut decoded = vec![0; zeros];
+    decoded.extend(bytes);
+
+    // Mainnet and testnet pay to public key hash and pay to script hash versions
+    if decoded.len() != 25 || ![0x00, 0x05, 0x6f, 0xc4].contains(&decoded[0]) {
+        return false;
+    }
+    let (payload, checksum) = decoded.split_at(21);
+    sha256(&sha256(payload))[..4] == *checksum
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum: u32 = 1;
+    for &value in values {
+        let top = checksum >> 25;
+        checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if top >> i & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+// Check a BIP173 or BIP350 segwit address, like bc1q...
+fn is_valid_segwit_address(address: &str) -> bool {
+    // Either case is fine, but not both
+    let lowercase = address.to_ascii_lowercase();
+    if address != lowercase && address != address.to_ascii_uppercase() {
+        return false;
+    }
+    if address.len() > 90 {
+        return false;
+    }
+    let Some((prefix, data)) = lowercase.rsplit_once('1') else {
+        return false;
+    };
+    if !["bc", "tb", "bcrt"].contains(&prefix) || data.len() < 7 {
+        return false;
+    }
+
+    let mut values: Vec<u8> = prefix.bytes().map(|c| c >> 5).collect();
+    values.push(0);
+    values.extend(prefix.bytes().map(|c| c & 31));
+    for c in data.bytes() {
+        match BECH32_ALPHABET.iter().position(|&x| x == c) {
+            Some(value) => values.push(value as u8),
+            None => return false,
+        }
+    }
+
+    let data: Vec<u8> = values[values.len() - data.len()..].to_vec();
+    let version = data[0];
+    let expected = if version == 0 {
+        BECH32_CONSTANT
+    } else {
+        BECH32M_CONSTANT
+    };
+    if version > 16 || bech32_polymod(&values) != expected {
+        return false;
+    }
+
+    // Regroup the 5 bit values between the version and checksum into bytes
+    let mut program = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &value in &data[1..data.len() - 6] {
+        buffer = buffer << 5 | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            program.push((buffer >> bits) as u8);
+        }
+    }
+    // Leftover bits have to be zero padding
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return false;
+    }
+    match version {
+        0 => program.len() == 20 || program.len() == 32,
+        _ => (2..=40).contains(&program.len()),
+    }
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut hash: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    // Pad with a 1 bit, zeros and the length in bits to a multiple of 64 bytes
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = hash;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (x, y) in hash.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *x = x.wrapping_add(y);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (bytes, word) in digest.chunks_mut(4).zip(hash) {
+        bytes.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoder::get_encoding_mode;
+    use crate::payload::bitcoin::{is_valid_base58_address, is_valid_segwit_address, sha256};
+    use crate::payload::*;
+    use crate::EncodingMode;
+
+    #[test]
+    fn test_sha256() {
+        let hex =
+            |digest: [u8; 32]| -> String { digest.iter().map(|b| format!("{:02x}", b)).collect() };
+        assert_eq!(
+            hex(sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        // Long enough to need a second block for the padding
+        assert_eq!(
+            hex(sha256(
+                b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"
+            )),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_address_validation() {
+        assert!(is_valid_base58_address(
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+        ));
+        assert!(is_valid_base58_address(
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"
+        ));
+        assert!(!is_valid_base58_address(
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"
+        ));
+        assert!(!is_valid_base58_address(
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7Divf0a"
+        ));
+
+        assert!(is_valid_segwit_address(
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"
+        ));
+        assert!(is_valid_segwit_address(
+            "BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ"
+        ));
+        assert!(is_valid_segwit_address(
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7"
+        ));
+        assert!(is_valid_segwit_address(
+            "bc1p5d7rjq7g6rdk2yhzks9smlaqtedr4dekq08ge8ztwac72sfr9rusxg3297"
+        ));
+        // Mixed case, a wrong checksum and an unknown prefix
+        assert!(!is_valid_segwit_address(
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5MDQ"
+        ));
+        assert!(!is_valid_segwit_address(
+            "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdp"
+        ));
+        assert!(!is_valid_segwit_address(
+            "ltc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"
+        ));
+    }
+
+    #[test]
+    fn test_building() {
+        let bitcoin = Bitcoin::new("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa")
+            .amount(5_000_000)
+            .label("Luke Jr")
+            .message("Donation for project xyz");
+        assert_eq!(
+            bitcoin.build().unwrap(),
+            "bitcoin:1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa?amount=0.05&label=Luke%20Jr\
+             &message=Donation%20for%20project%20xyz"
+        );
+
+        let bitcoin = Bitcoin::new("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+            .amount(2_100_000_000_000_000)
+            .lightning("lnbc1500n1pdummy");
+        assert_eq!(
+            bitcoin.build().unwrap(),
+            "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?amount=21000000&lightning=lnbc1500n1pdummy"
+        );
+
+        assert_eq!(
+            Bitcoin::new("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa")
+                .amount(1)
+                .build()
+                .unwrap(),
+            "bitcoin:1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa?amount=0.00000001"
+        );
+    }
+
+    #[test]
+    fn test_uppercase() {
+        let bitcoin = Bitcoin::new("bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq").uppercase(true);
+        let text = bitcoin.build().unwrap();
+        assert_eq!(text, "BITCOIN:BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ");
+        assert_eq!(get_encoding_mode(&text), EncodingMode::Alphanumeric);
+
+        // Legacy addresses are case sensitive
+        let bitcoin = Bitcoin::new("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").uppercase(true);
+        assert_eq!(
+            bitcoin.build().unwrap(),
+            "bitcoin:1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let bitcoin = Bitcoin::new("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+        assert_eq!(
+            Bitcoin::new("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb").build(),
+            Err(PayloadError::InvalidChecksum("address"))
+        );
+        assert_eq!(
+            bitcoin.clone().amount(0).build(),
+            Err(PayloadError::InvalidField("amount"))
+        );
+        assert_eq!(
+            bitcoin.clone().amount(2_100_000_000_000_001).build(),
+            Err(PayloadError::InvalidField("amount"))
+        );
+        assert_eq!(
+            bitcoin.lightning("invoice").build(),
+            Err(PayloadError::InvalidField("lightning"))
+        );
+    }
+}
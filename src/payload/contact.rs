@@ -0,0 +1,283 @@
+use super::{escape, escape_text, fold_line};
+
+/// The text formats a contact can be written in
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ContactFormat {
+    VCard3,
+    VCard4,
+    MeCard,
+    /// Whichever of the other formats gives the shortest text
+    Compact,
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    pub region: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
+impl Address {
+    fn components(&self) -> [&str; 5] {
+        [
+            &self.street,
+            &self.city,
+            &self.region,
+            &self.postal_code,
+            &self.country,
+        ]
+    }
+}
+
+/// Contact details for a business card. Use `format` to get the text to encode.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Contact {
+    pub given_name: String,
+    pub family_name: String,
+    pub phones: Vec<String>,
+    pub emails: Vec<String>,
+    pub organization: String,
+    pub address: Option<Address>,
+    pub url: String,
+    pub note: String,
+}
+
+impl Contact {
+    pub fn new(given_name: &str, family_name: &str) -> Self {
+        Contact {
+            given_name: given_name.to_string(),
+            family_name: family_name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Add a phone number. Can be called more than once.
+    pub fn phone(mut self, phone: &str) -> Self {
+        self.phones.push(phone.to_string());
+        self
+    }
+
+    /// Add an email address. Can be called more than once.
+    pub fn email(mut self, email: &str) -> Self {
+        self.emails.push(email.to_string());
+        self
+    }
+
+    pub fn organization(mut self, organization: &str) -> Self {
+        self.organization = organization.to_string();
+        self
+    }
+
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    pub fn note(mut self, note: &str) -> Self {
+        self.note = note.to_string();
+        self
+    }
+
+    fn full_name(&self) -> String {
+        [self.given_name.as_str(), self.family_name.as_str()]
+            .iter()
+            .filter(|name| !name.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn format(&self, format: ContactFormat) -> String {
+        match format {
+            ContactFormat::VCard3 => self.to_vcard("3.0"),
+            ContactFormat::VCard4 => self.to_vcard("4.0"),
+            ContactFormat::MeCard => self.to_mecard(),
+            ContactFormat::Compact => [
+                ContactFormat::MeCard,
+                ContactFormat::VCard3,
+                ContactFormat::VCard4,
+            ]
+            .into_iter()
+            .map(|format| self.format(format))
+            .min_by_key(|text| text.len())
+            .unwrap(),
+        }
+    }
+
+    fn to_vcard(&self, version: &str) -> String {
+        let mut lines = vec!["BEGIN:VCARD".to_string(), format!("VERSION:{}", version)];
+
+        let name = [&self.family_name, &self.given_name, "", "", ""];
+        lines.push(format!("N:{}", escape_vcard_components(&name)));
+        lines.push(format!("FN:{}", escape_text(&self.full_name())));
+        if !self.organization.is_empty() {
+            lines.push(format!("ORG:{}", escape_text(&self.organization)));
+        }
+        for phone in &self.phones {
+            lines.push(format!("TEL:{}", escape_text(phone)));
+        }
+        for email in &self.emails {
+            lines.push(format!("EMAIL:{}", escape_text(email)));
+        }
+        if let Some(address) = &self.address {
+            // The post office box and extended address come first and are left empty
+            let mut components = vec!["", ""];
+            components.extend(address.components());
+            lines.push(format!("ADR:{}", escape_vcard_components(&components)));
+        }
+        if !self.url.is_empty() {
+            lines.push(format!("URL:{}", self.url));
+        }
+        if !self.note.is_empty() {
+            lines.push(format!("NOTE:{}", escape_text(&self.note)));
+        }
+        lines.push("END:VCARD".to_string());
+
+        lines.iter().map(|line| fold_line(line) + "\r\n").collect()
+    }
+
+    fn to_mecard(&self) -> String {
+        const SPECIAL_CHARS: [char; 4] = ['\\', ';', ',', ':'];
+        let escape_mecard = |value: &str| escape(value, &SPECIAL_CHARS);
+
+        let mut text = String::from("MECARD:");
+        let name = if self.given_name.is_empty() {
+            escape_mecard(&self.family_name)
+        } else {
+            format!(
+                "{},{}",
+                escape_mecard(&self.family_name),
+                escape_mecard(&self.given_name)
+            )
+        };
+        text += &format!("N:{};", name);
+        if !self.organization.is_empty() {
+            text += &format!("ORG:{};", escape_mecard(&self.organization));
+        }
+        for phone in &self.phones {
+            text += &format!("TEL:{};", escape_mecard(phone));
+        }
+        for email in &self.emails {
+            text += &format!("EMAIL:{};", escape_mecard(email));
+        }
+        if let Some(address) = &self.address {
+            let components: Vec<String> = address
+                .components()
+                .iter()
+                .map(|c| escape_mecard(c))
+                .collect();
+            text += &format!("ADR:,,{};", components.join(","));
+        }
+        if !self.url.is_empty() {
+            text += &format!("URL:{};", escape_mecard(&self.url));
+        }
+        if !self.note.is_empty() {
+            text += &format!("NOTE:{};", escape_mecard(&self.note));
+        }
+        text + ";"
+    }
+}
+
+fn escape_vcard_components(components: &[&str]) -> String {
+    let escaped: Vec<String> = components.iter().map(|c| escape_text(c)).collect();
+    escaped.join(";")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    fn get_contact() -> Contact {
+        Contact::new("Ada", "Lovelace")
+            .organization("Analytical Engines; Ltd")
+            .phone("+44 20 7946 0000")
+            .phone("+44 7700 900000")
+            .email("ada@example.com")
+            .address(Address {
+                street: "12 St James's Square".to_string(),
+                city: "London".to_string(),
+                postal_code: "SW1Y 4JH".to_string(),
+                country: "United Kingdom".to_string(),
+                ..Default::default()
+            })
+            .url("https://example.com/ada")
+            .note("Met at the conference,\nfollow up in May")
+    }
+
+    #[test]
+    fn test_vcard() {
+        let expected = "BEGIN:VCARD\r\n\
+                        VERSION:3.0\r\n\
+                        N:Lovelace;Ada;;;\r\n\
+                        FN:Ada Lovelace\r\n\
+                        ORG:Analytical Engines\\; Ltd\r\n\
+                        TEL:+44 20 7946 0000\r\n\
+                        TEL:+44 7700 900000\r\n\
+                        EMAIL:ada@example.com\r\n\
+                        ADR:;;12 St James's Square;London;;SW1Y 4JH;United Kingdom\r\n\
+                        URL:https://example.com/ada\r\n\
+                        NOTE:Met at the conference\\,\\nfollow up in May\r\n\
+                        END:VCARD\r\n";
+        assert_eq!(get_contact().format(ContactFormat::VCard3), expected);
+
+        let vcard = Contact::new("Grace", "Hopper").format(ContactFormat::VCard4);
+        assert_eq!(
+            vcard,
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nN:Hopper;Grace;;;\r\nFN:Grace Hopper\r\nEND:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn test_line_folding() {
+        let note = "é".repeat(50);
+        let vcard = Contact::new("A", "B")
+            .note(&note)
+            .format(ContactFormat::VCard3);
+        let lines: Vec<&str> = vcard.split("\r\n").collect();
+        assert!(lines.iter().all(|line| line.len() <= 75));
+
+        // Each é is 2 bytes, so 35 of them fit after the NOTE: prefix
+        let start = lines
+            .iter()
+            .position(|line| line.starts_with("NOTE:"))
+            .unwrap();
+        assert_eq!(lines[start], format!("NOTE:{}", "é".repeat(35)));
+        assert_eq!(lines[start + 1], format!(" {}", "é".repeat(15)));
+    }
+
+    #[test]
+    fn test_mecard() {
+        let expected = "MECARD:N:Lovelace,Ada;ORG:Analytical Engines\\; Ltd;\
+                        TEL:+44 20 7946 0000;TEL:+44 7700 900000;EMAIL:ada@example.com;\
+                        ADR:,,12 St James's Square,London,,SW1Y 4JH,United Kingdom;\
+                        URL:https\\://example.com/ada;\
+                        NOTE:Met at the conference\\,\nfollow up in May;;";
+        assert_eq!(get_contact().format(ContactFormat::MeCard), expected);
+        assert_eq!(
+            get_contact().format(ContactFormat::Compact),
+            get_contact().format(ContactFormat::MeCard)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_encoding() {
+        // Line endings have to survive the round trip byte for byte
+        let vcard = get_contact().format(ContactFormat::VCard4);
+        let image = crate::render_qr_code(
+            &vcard,
+            crate::ErrorCorrection::Low,
+            &crate::Options::default(),
+        )
+        .unwrap();
+        let decoded = crate::decode_image(&image.into());
+        assert_eq!(decoded[0].text, vcard);
+    }
+}
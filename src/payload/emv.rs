@@ -0,0 +1,462 @@
+use super::PayloadError;
+
+// Tags of the fields in the EMVCo merchant presented mode format
+const PAYLOAD_FORMAT: u8 = 0;
+const POINT_OF_INITIATION: u8 = 1;
+const CATEGORY_CODE: u8 = 52;
+const CURRENCY: u8 = 53;
+const AMOUNT: u8 = 54;
+const COUNTRY_CODE: u8 = 58;
+const MERCHANT_NAME: u8 = 59;
+const MERCHANT_CITY: u8 = 60;
+const POSTAL_CODE: u8 = 61;
+const ADDITIONAL_DATA: u8 = 62;
+const CRC: u8 = 63;
+const LANGUAGE_TEMPLATE: u8 = 64;
+
+const PIX_IDENTIFIER: &str = "br.gov.bcb.pix";
+
+/// A tag, length, value data object. Templates hold their own data objects.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Tlv {
+    pub id: u8,
+    pub value: TlvValue,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum TlvValue {
+    Text(String),
+    Template(Vec<Tlv>),
+}
+
+impl Tlv {
+    pub fn text(id: u8, value: &str) -> Self {
+        Tlv {
+            id,
+            value: TlvValue::Text(value.to_string()),
+        }
+    }
+
+    pub fn template(id: u8, fields: Vec<Tlv>) -> Self {
+        Tlv {
+            id,
+            value: TlvValue::Template(fields),
+        }
+    }
+
+    fn write(&self, output: &mut String) -> Result<(), PayloadError> {
+        let value = match &self.value {
+            TlvValue::Text(text) => text.clone(),
+            TlvValue::Template(fields) => {
+                let mut nested = String::new();
+                for field in fields {
+                    field.write(&mut nested)?;
+                }
+                nested
+            }
+        };
+
+        // Lengths are counted in characters and written as 2 digits
+        let length = value.chars().count();
+        if self.id > 99 || length == 0 || length > 99 {
+            return Err(PayloadError::InvalidField("tlv"));
+        }
+        *output += &format!("{:02}{:02}{}", self.id, length, value);
+        Ok(())
+    }
+}
+
+// Merchant account information, the additional data field, the language
+// and the unreserved templates hold nested data objects
+fn is_template(id: u8) -> bool {
+    matches!(id, 26..=51 | ADDITIONAL_DATA | LANGUAGE_TEMPLATE | 80..=99)
+}
+
+// Read the two digits of an id or a length
+fn parse_digits(digits: &[char]) -> Result<usize, PayloadError> {
+    digits.iter().try_fold(0, |number, digit| {
+        digit
+            .to_digit(10)
+            .map(|digit| number * 10 + digit as usize)
+            .ok_or(PayloadError::InvalidField("tlv"))
+    })
+}
+
+fn parse_fields(text: &str, nested: bool) -> Result<Vec<Tlv>, PayloadError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut fields = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let header = chars
+            .get(i..i + 4)
+            .ok_or(PayloadError::InvalidField("tlv"))?;
+        let id = parse_digits(&header[..2])? as u8;
+        let length = parse_digits(&header[2..])?;
+        let value: String = chars
+            .get(i + 4..i + 4 + length)
+            .ok_or(PayloadError::InvalidField("tlv"))?
+            .iter()
+            .collect();
+
+        if !nested && is_template(id) {
+            fields.push(Tlv::template(id, parse_fields(&value, true)?));
+        } else {
+            fields.push(Tlv::text(id, &value));
+        }
+        i += 4 + length;
+    }
+    Ok(fields)
+}
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, starting from 0xFFFF
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |mut crc: u16, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                crc << 1 ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// A merchant presented payment code in the EMVCo format, which PIX,
+/// PromptPay, SGQR and other instant payment schemes build on.
+/// Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct EmvPayment {
+    /// Every data object other than the payload format and the checksum
+    pub fields: Vec<Tlv>,
+}
+
+impl EmvPayment {
+    /// A payment with the mandatory merchant details. The category code is a
+    /// 4 digit ISO 18245 code and the currency a 3 digit ISO 4217 number.
+    pub fn new(
+        merchant_name: &str,
+        merchant_city: &str,
+        country_code: &str,
+        category_code: &str,
+        currency: &str,
+    ) -> Self {
+        EmvPayment { fields: Vec::new() }
+            .field(Tlv::text(CATEGORY_CODE, category_code))
+            .field(Tlv::text(CURRENCY, currency))
+            .field(Tlv::text(COUNTRY_CODE, country_code))
+            .field(Tlv::text(MERCHANT_NAME, merchant_name))
+            .field(Tlv::text(MERCHANT_CITY, merchant_city))
+    }
+
+    /// A payment to a PIX key, with an optional amount in reais and
+    /// a transaction id, which is *** for codes that can be paid many times
+    pub fn pix(
+        key: &str,
+        merchant_name: &str,
+        merchant_city: &str,
+        amount: Option<&str>,
+        transaction_id: &str,
+    ) -> Self {
+        let account = vec![Tlv::text(0, PIX_IDENTIFIER), Tlv::text(1, key)];
+        let mut payment = EmvPayment::new(merchant_name, merchant_city, "BR", "0000", "986")
+            .merchant_account(26, account)
+            .additional_data(5, transaction_id);
+        if let Some(amount) = amount {
+            payment = payment.amount(amount);
+        }
+        payment
+    }
+
+    /// Add a data object, replacing any with the same id, keeping them sorted
+    pub fn field(mut self, field: Tlv) -> Self {
+        match self.fields.binary_search_by_key(&field.id, |f| f.id) {
+            Ok(i) => self.fields[i] = field,
+            Err(i) => self.fields.insert(i, field),
+        }
+        self
+    }
+
+    pub fn get(&self, id: u8) -> Option<&Tlv> {
+        self.fields.iter().find(|field| field.id == id)
+    }
+
+    fn get_text(&self, id: u8) -> Option<&str> {
+        match &self.get(id)?.value {
+            TlvValue::Text(text) => Some(text),
+            TlvValue::Template(_) => None,
+        }
+    }
+
+    /// Mark the code as dynamic, meaning it's for a single transaction
+    pub fn dynamic(self, dynamic: bool) -> Self {
+        self.field(Tlv::text(
+            POINT_OF_INITIATION,
+            if dynamic { "12" } else { "11" },
+        ))
+    }
+
+    /// Add the template for a payment network, with an id from 26 to 51.
+    /// The template's first field is the network's globally unique identifier.
+    pub fn merchant_account(self, id: u8, fields: Vec<Tlv>) -> Self {
+        self.field(Tlv::template(id, fields))
+    }
+
+    /// Set the amount, such as 10.50, in the transaction currency
+    pub fn amount(self, amount: &str) -> Self {
+        self.field(Tlv::text(AMOUNT, amount))
+    }
+
+    pub fn postal_code(self, postal_code: &str) -> Self {
+        self.field(Tlv::text(POSTAL_CODE, postal_code))
+    }
+
+    /// Add a field to the additional data template, such as
+    /// the bill number (1), reference label (5) or terminal label (7)
+    pub fn additional_data(self, id: u8, value: &str) -> Self {
+        let mut data = match self.get(ADDITIONAL_DATA).map(|field| &field.value) {
+            Some(TlvValue::Template(fields)) => fields.clone(),
+            _ => Vec::new(),
+        };
+        match data.iter().position(|field| field.id == id) {
+            Some(i) => data[i] = Tlv::text(id, value),
+            None => data.push(Tlv::text(id, value)),
+        }
+        data.sort_by_key(|field| field.id);
+        self.field(Tlv::template(ADDITIONAL_DATA, data))
+    }
+
+    fn validate(&self) -> Result<(), PayloadError> {
+        let check =
+            |id: u8, field: &'static str, valid: &dyn Fn(&str) -> bool| match self.get_text(id) {
+                Some(text) if valid(text) => Ok(()),
+                Some(_) => Err(PayloadError::InvalidField(field)),
+                None => Err(PayloadError::MissingField(field)),
+            };
+        let digits = |count: usize| {
+            move |text: &str| text.len() == count && text.chars().all(|c| c.is_ascii_digit())
+        };
+        let max_length = |max: usize| move |text: &str| text.chars().count() <= max;
+
+        check(CATEGORY_CODE, "category code", &digits(4))?;
+        check(CURRENCY, "currency", &digits(3))?;
+        check(COUNTRY_CODE, "country code", &|text| {
+            text.len() == 2 && text.chars().all(|c| c.is_ascii_uppercase())
+        })?;
+        check(MERCHANT_NAME, "merchant name", &max_length(25))?;
+        check(MERCHANT_CITY, "merchant city", &max_length(15))?;
+        if self.get(POINT_OF_INITIATION).is_some() {
+            check(POINT_OF_INITIATION, "point of initiation", &|text| {
+                text == "11" || text == "12"
+            })?;
+        }
+        if self.get(AMOUNT).is_some() {
+            check(AMOUNT, "amount", &|text| {
+                text.len() <= 13
+                    && text.parse::<f64>().is_ok_and(|amount| amount > 0.0)
+                    && text.chars().all(|c| c.is_ascii_digit() || c == '.')
+            })?;
+        }
+        if self.get(POSTAL_CODE).is_some() {
+            check(POSTAL_CODE, "postal code", &max_length(10))?;
+        }
+
+        // At least one payment network is needed, and templates
+        // start with the network's identifier
+        let accounts: Vec<&Tlv> = self
+            .fields
+            .iter()
+            .filter(|field| (2..=51).contains(&field.id))
+            .collect();
+        if accounts.is_empty() {
+            return Err(PayloadError::MissingField("merchant account"));
+        }
+        for account in accounts {
+            match &account.value {
+                TlvValue::Template(fields) if account.id >= 26 => {
+                    if fields.first().is_none_or(|field| field.id != 0) {
+                        return Err(PayloadError::MissingField("merchant account identifier"));
+                    }
+                }
+                TlvValue::Text(_) if account.id < 26 => {}
+                _ => return Err(PayloadError::InvalidField("merchant account")),
+            }
+        }
+
+        if self
+            .fields
+            .iter()
+            .any(|field| matches!(field.id, PAYLOAD_FORMAT | CRC))
+        {
+            return Err(PayloadError::InvalidField("tlv"));
+        }
+        Ok(())
+    }
+
+    /// Validate the fields and build the payload, ending with its checksum
+    pub fn build(&self) -> Result<String, PayloadError> {
+        self.validate()?;
+
+        let mut payload = String::new();
+        Tlv::text(PAYLOAD_FORMAT, "01").write(&mut payload)?;
+        for field in &self.fields {
+            field.write(&mut payload)?;
+        }
+
+        // The checksum covers its own tag and length
+        payload += "6304";
+        let crc = crc16(payload.as_bytes());
+        Ok(format!("{}{:04X}", payload, crc))
+    }
+
+    /// Read a payment back, checking its checksum and mandatory fields
+    pub fn parse(text: &str) -> Result<Self, PayloadError> {
+        if !text.starts_with("000201") {
+            return Err(PayloadError::InvalidPrefix);
+        }
+        let split = text
+            .len()
+            .checked_sub(4)
+            .filter(|&i| text.is_char_boundary(i));
+        let Some((data, checksum)) = split.map(|i| text.split_at(i)) else {
+            return Err(PayloadError::MissingField("crc"));
+        };
+        if !data.ends_with("6304") {
+            return Err(PayloadError::MissingField("crc"));
+        }
+        if u16::from_str_radix(checksum, 16) != Ok(crc16(data.as_bytes())) {
+            return Err(PayloadError::InvalidChecksum("crc"));
+        }
+
+        let fields = parse_fields(&data[..data.len() - 4], false)?;
+        let payment = EmvPayment {
+            fields: fields
+                .into_iter()
+                .filter(|field| field.id != PAYLOAD_FORMAT)
+                .collect(),
+        };
+        payment.validate()?;
+        Ok(payment)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::emv::crc16;
+    use crate::payload::*;
+
+    // The static code example from the PIX specification
+    const PIX_EXAMPLE: &str = "00020126580014br.gov.bcb.pix0136123e4567-e12b-12d1-a456-4266554400005204000053039865802BR5913Fulano de Tal6008BRASILIA62070503***63041D3D";
+
+    #[test]
+    fn test_crc() {
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn test_pix() {
+        let payment = EmvPayment::pix(
+            "123e4567-e12b-12d1-a456-426655440000",
+            "Fulano de Tal",
+            "BRASILIA",
+            None,
+            "***",
+        );
+        assert_eq!(payment.build().unwrap(), PIX_EXAMPLE);
+        assert_eq!(EmvPayment::parse(PIX_EXAMPLE), Ok(payment));
+
+        let payment = EmvPayment::pix(
+            "+5561912345678",
+            "Loja",
+            "SAO PAULO",
+            Some("12.50"),
+            "PED123",
+        );
+        let payload = payment.build().unwrap();
+        assert!(payload.contains("540512.50"));
+        assert_eq!(EmvPayment::parse(&payload), Ok(payment));
+    }
+
+    #[test]
+    fn test_building() {
+        // A PromptPay style code with a text merchant account
+        let payment = EmvPayment::new("Noodle Shop", "Bangkok", "TH", "5812", "764")
+            .dynamic(true)
+            .merchant_account(
+                29,
+                vec![
+                    Tlv::text(0, "A000000677010111"),
+                    Tlv::text(1, "0066812345678"),
+                ],
+            )
+            .amount("100.00")
+            .additional_data(7, "T1")
+            .additional_data(1, "INV-1");
+        let payload = payment.build().unwrap();
+        assert!(payload.starts_with("000201010212"));
+        assert!(payload.contains("62150105INV-10702T1"));
+        assert_eq!(EmvPayment::parse(&payload), Ok(payment));
+    }
+
+    #[test]
+    fn test_validation() {
+        let payment = EmvPayment::pix("key", "Loja", "SAO PAULO", None, "***");
+        assert!(payment.build().is_ok());
+
+        let long_name = payment.clone().field(Tlv::text(59, &"n".repeat(26)));
+        assert_eq!(
+            long_name.build(),
+            Err(PayloadError::InvalidField("merchant name"))
+        );
+
+        let bad_amount = payment.clone().amount("1,00");
+        assert_eq!(
+            bad_amount.build(),
+            Err(PayloadError::InvalidField("amount"))
+        );
+
+        let bad_currency = payment.clone().field(Tlv::text(53, "BRL"));
+        assert_eq!(
+            bad_currency.build(),
+            Err(PayloadError::InvalidField("currency"))
+        );
+
+        let no_account = EmvPayment::new("Loja", "SAO PAULO", "BR", "0000", "986");
+        assert_eq!(
+            no_account.build(),
+            Err(PayloadError::MissingField("merchant account"))
+        );
+
+        let no_identifier = no_account.merchant_account(26, vec![Tlv::text(1, "key")]);
+        assert_eq!(
+            no_identifier.build(),
+            Err(PayloadError::MissingField("merchant account identifier"))
+        );
+
+        let too_long = payment.additional_data(5, &"x".repeat(100));
+        assert_eq!(too_long.build(), Err(PayloadError::InvalidField("tlv")));
+    }
+
+    #[test]
+    fn test_parsing() {
+        let damaged = PIX_EXAMPLE.replace("Fulano", "Fulana");
+        assert_eq!(
+            EmvPayment::parse(&damaged),
+            Err(PayloadError::InvalidChecksum("crc"))
+        );
+        assert_eq!(EmvPayment::parse("hello"), Err(PayloadError::InvalidPrefix));
+        assert_eq!(
+            EmvPayment::parse(&PIX_EXAMPLE[..PIX_EXAMPLE.len() - 8]),
+            Err(PayloadError::MissingField("crc"))
+        );
+
+        // A header that isn't made of digits is an error, not a panic
+        let data = "0002015é01x6304";
+        let text = format!("{}{:04X}", data, crc16(data.as_bytes()));
+        assert_eq!(
+            EmvPayment::parse(&text),
+            Err(PayloadError::InvalidField("tlv"))
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use super::PayloadError;
+
+/// An EIP-681 `ethereum:` payment request, for either ether or an ERC-20
+/// token transfer. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Ethereum {
+    /// Who gets paid, as 0x followed by 40 hexadecimal digits
+    pub address: String,
+    /// The chain to pay on, where 1 is mainnet
+    pub chain_id: Option<u64>,
+    /// The amount in wei, or in the token's smallest unit for token transfers
+    pub value: Option<u128>,
+    /// The contract of the token to transfer instead of ether
+    pub token: Option<String>,
+    pub gas_limit: Option<u64>,
+}
+
+impl Ethereum {
+    pub fn new(address: &str) -> Self {
+        Ethereum {
+            address: address.to_string(),
+            chain_id: None,
+            value: None,
+            token: None,
+            gas_limit: None,
+        }
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Set the amount in wei, or in the token's smallest unit
+    pub fn value(mut self, value: u128) -> Self {
+        self.value = Some(value);
+        self
+    }
+
+    /// Pay with the token at this contract address instead of ether
+    pub fn token(mut self, contract: &str) -> Self {
+        self.token = Some(contract.to_string());
+        self
+    }
+
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    /// Validate the addresses and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        if !is_valid_address(&self.address) {
+            return Err(PayloadError::InvalidField("address"));
+        }
+
+        // Token transfers call the contract's transfer function,
+        // with the recipient and amount as its arguments
+        let mut parameters = Vec::new();
+        let mut text = match &self.token {
+            Some(token) => {
+                if !is_valid_address(token) {
+                    return Err(PayloadError::InvalidField("token"));
+                }
+                parameters.push(format!("address={}", self.address));
+                if let Some(value) = self.value {
+                    parameters.push(format!("uint256={}", value));
+                }
+                token.clone()
+            }
+            None => {
+                if let Some(value) = self.value {
+                    parameters.push(format!("value={}", value));
+                }
+                self.address.clone()
+            }
+        };
+        if let Some(gas_limit) = self.gas_limit {
+            parameters.push(format!("gasLimit={}", gas_limit));
+        }
+
+        if let Some(chain_id) = self.chain_id {
+            text += &format!("@{}", chain_id);
+        }
+        if self.token.is_some() {
+            text += "/transfer";
+        }
+        if !parameters.is_empty() {
+            text += &format!("?{}", parameters.join("&"));
+        }
+        Ok(format!("ethereum:{}", text))
+    }
+}
+
+// Addresses may use mixed case as an EIP-55 checksum,
+// which isn't verified since it needs Keccak-256
+fn is_valid_address(address: &str) -> bool {
+    address
+        .strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    const ADDRESS: &str = "0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359";
+    const TOKEN: &str = "0x89205a3a3b2a69de6dbf7f01ed13b2108b2c43e7";
+
+    #[test]
+    fn test_building() {
+        assert_eq!(
+            Ethereum::new(ADDRESS).build().unwrap(),
+            "ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359"
+        );
+        assert_eq!(
+            Ethereum::new(ADDRESS)
+                .chain_id(1)
+                .value(2_014_000_000_000_000_000)
+                .gas_limit(21000)
+                .build()
+                .unwrap(),
+            "ethereum:0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359@1?value=2014000000000000000&gasLimit=21000"
+        );
+        assert_eq!(
+            Ethereum::new(ADDRESS)
+                .token(TOKEN)
+                .value(1_000_000)
+                .build()
+                .unwrap(),
+            "ethereum:0x89205a3a3b2a69de6dbf7f01ed13b2108b2c43e7/transfer\
+             ?address=0xfb6916095ca1df60bb79Ce92cE3Ea74c37c5d359&uint256=1000000"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            Ethereum::new("0x1234").build(),
+            Err(PayloadError::InvalidField("address"))
+        );
+        assert_eq!(
+            Ethereum::new("fb6916095ca1df60bb79Ce92cE3Ea74c37c5d359").build(),
+            Err(PayloadError::InvalidField("address"))
+        );
+        assert_eq!(
+            Ethereum::new(ADDRESS).token("0xzz").build(),
+            Err(PayloadError::InvalidField("token"))
+        );
+    }
+}
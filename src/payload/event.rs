@@ -0,0 +1,176 @@
+use super::{escape_text, fold_line, PayloadError};
+
+/// A calendar event in the iCalendar VEVENT format. Times are written the way
+/// iCalendar writes them: 20240131 for an all day event, 20240131T093000 for
+/// a local time or 20240131T093000Z for UTC. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Event {
+    pub summary: String,
+    pub start: String,
+    pub end: String,
+    pub location: String,
+    pub description: String,
+}
+
+impl Event {
+    pub fn new(summary: &str, start: &str) -> Self {
+        Event {
+            summary: summary.to_string(),
+            start: start.to_string(),
+            end: String::new(),
+            location: String::new(),
+            description: String::new(),
+        }
+    }
+
+    /// Set when the event ends, in the same form as its start
+    pub fn end(mut self, end: &str) -> Self {
+        self.end = end.to_string();
+        self
+    }
+
+    pub fn location(mut self, location: &str) -> Self {
+        self.location = location.to_string();
+        self
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    /// Validate the times and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        if self.summary.is_empty() {
+            return Err(PayloadError::MissingField("summary"));
+        }
+        let all_day = is_all_day(&self.start).ok_or(PayloadError::InvalidField("start"))?;
+        if !self.end.is_empty() {
+            // Comparing the text works since both times have the same form
+            let same_form = is_all_day(&self.end) == Some(all_day)
+                && self.end.ends_with('Z') == self.start.ends_with('Z');
+            if !same_form || self.end < self.start {
+                return Err(PayloadError::InvalidField("end"));
+            }
+        }
+
+        let date_type = if all_day { ";VALUE=DATE" } else { "" };
+        let mut lines = vec!["BEGIN:VEVENT".to_string()];
+        lines.push(format!("SUMMARY:{}", escape_text(&self.summary)));
+        lines.push(format!("DTSTART{}:{}", date_type, self.start));
+        if !self.end.is_empty() {
+            lines.push(format!("DTEND{}:{}", date_type, self.end));
+        }
+        if !self.location.is_empty() {
+            lines.push(format!("LOCATION:{}", escape_text(&self.location)));
+        }
+        if !self.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&self.description)));
+        }
+        lines.push("END:VEVENT".to_string());
+
+        Ok(lines.iter().map(|line| fold_line(line) + "\r\n").collect())
+    }
+}
+
+// Check a date or date and time, returning whether it's only a date
+fn is_all_day(value: &str) -> Option<bool> {
+    let number = |range: std::ops::Range<usize>, min: u32, max: u32| {
+        let part = value.get(range)?;
+        let n: u32 = part.parse().ok()?;
+        let digits = part.chars().all(|c| c.is_ascii_digit());
+        (digits && (min..=max).contains(&n)).then_some(n)
+    };
+    number(0..4, 0, 9999)?;
+    number(4..6, 1, 12)?;
+    number(6..8, 1, 31)?;
+    if value.len() == 8 {
+        return Some(true);
+    }
+
+    let time = value.get(8..)?;
+    let time = time.strip_suffix('Z').unwrap_or(time);
+    if !time.starts_with('T') || time.len() != 7 {
+        return None;
+    }
+    number(9..11, 0, 23)?;
+    number(11..13, 0, 59)?;
+    // 60 allows for leap seconds
+    number(13..15, 0, 60)?;
+    Some(false)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    #[test]
+    fn test_building() {
+        let event = Event::new("Team offsite; day 1", "20240312T090000Z")
+            .end("20240312T170000Z")
+            .location("Room 4, Building B")
+            .description("Bring a laptop\nLunch is provided");
+        assert_eq!(
+            event.build().unwrap(),
+            "BEGIN:VEVENT\r\n\
+             SUMMARY:Team offsite\\; day 1\r\n\
+             DTSTART:20240312T090000Z\r\n\
+             DTEND:20240312T170000Z\r\n\
+             LOCATION:Room 4\\, Building B\r\n\
+             DESCRIPTION:Bring a laptop\\nLunch is provided\r\n\
+             END:VEVENT\r\n"
+        );
+
+        let event = Event::new("Holiday", "20241225");
+        assert_eq!(
+            event.build().unwrap(),
+            "BEGIN:VEVENT\r\nSUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20241225\r\nEND:VEVENT\r\n"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            Event::new("", "20240101").build(),
+            Err(PayloadError::MissingField("summary"))
+        );
+        for start in [
+            "2024-01-01",
+            "20241301",
+            "20240101T250000",
+            "20240101T1200",
+            "",
+        ] {
+            assert_eq!(
+                Event::new("x", start).build(),
+                Err(PayloadError::InvalidField("start"))
+            );
+        }
+
+        let event = Event::new("x", "20240101T120000");
+        assert!(event.clone().end("20240101T130000").build().is_ok());
+        for end in ["20240101T110000", "20240102", "20240101T130000Z"] {
+            assert_eq!(
+                event.clone().end(end).build(),
+                Err(PayloadError::InvalidField("end"))
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_encoding() {
+        let text = Event::new("Launch", "20240601T150000Z")
+            .location("Main hall")
+            .build()
+            .unwrap();
+        let image = crate::render_qr_code(
+            &text,
+            crate::ErrorCorrection::Medium,
+            &crate::Options::default(),
+        )
+        .unwrap();
+        let decoded = crate::decode_image(&image.into());
+        assert_eq!(decoded[0].text, text);
+    }
+}
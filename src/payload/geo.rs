@@ -0,0 +1,95 @@
+use super::{percent_encode, with_scheme, PayloadError};
+
+/// A location as a `geo:` URI, which opens in a map app.
+/// Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Geo {
+    /// Degrees north of the equator, from -90 to 90
+    pub latitude: f64,
+    /// Degrees east of the prime meridian, from -180 to 180
+    pub longitude: f64,
+    /// Meters above sea level
+    pub altitude: Option<f64>,
+    /// A place name or search for map apps to show
+    pub query: String,
+}
+
+impl Geo {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Geo {
+            latitude,
+            longitude,
+            altitude: None,
+            query: String::new(),
+        }
+    }
+
+    pub fn altitude(mut self, altitude: f64) -> Self {
+        self.altitude = Some(altitude);
+        self
+    }
+
+    pub fn query(mut self, query: &str) -> Self {
+        self.query = query.to_string();
+        self
+    }
+
+    /// Validate the coordinates and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            return Err(PayloadError::InvalidField("latitude"));
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(PayloadError::InvalidField("longitude"));
+        }
+
+        let mut text = format!("{},{}", self.latitude, self.longitude);
+        if let Some(altitude) = self.altitude {
+            if !altitude.is_finite() {
+                return Err(PayloadError::InvalidField("altitude"));
+            }
+            text += &format!(",{}", altitude);
+        }
+        if !self.query.is_empty() {
+            text += &format!("?q={}", percent_encode(&self.query));
+        }
+        Ok(with_scheme("geo", &text))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    #[test]
+    fn test_building() {
+        assert_eq!(
+            Geo::new(51.5007, -0.1246).build().unwrap(),
+            "geo:51.5007,-0.1246"
+        );
+        assert_eq!(
+            Geo::new(46.5, 8.0)
+                .altitude(3454.0)
+                .query("Jungfraujoch & Sphinx")
+                .build()
+                .unwrap(),
+            "geo:46.5,8,3454?q=Jungfraujoch%20%26%20Sphinx"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        assert_eq!(
+            Geo::new(90.5, 0.0).build(),
+            Err(PayloadError::InvalidField("latitude"))
+        );
+        assert_eq!(
+            Geo::new(0.0, f64::NAN).build(),
+            Err(PayloadError::InvalidField("longitude"))
+        );
+        assert_eq!(
+            Geo::new(0.0, 0.0).altitude(f64::INFINITY).build(),
+            Err(PayloadError::InvalidField("altitude"))
+        );
+    }
+}
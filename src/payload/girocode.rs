@@ -0,0 +1,265 @@
+use super::{check_text, remove_spaces, validate_creditor_reference, validate_iban, PayloadError};
+use crate::ErrorCorrection;
+
+// The EPC guidelines limit the whole payload to this many bytes
+const MAX_PAYLOAD_LENGTH: usize = 331;
+const MAX_AMOUNT_CENTS: u64 = 99_999_999_999;
+
+/// What the payment is for, as either a structured or a free text reference
+#[derive(Clone, PartialEq, Debug)]
+pub enum Remittance {
+    /// An ISO 11649 creditor reference, like RF18 5390 0754 7034
+    Reference(String),
+    /// Up to 140 characters of text
+    Text(String),
+}
+
+/// A SEPA credit transfer in the EPC069-12 format, known as a GiroCode.
+/// Use `build` to get the text to encode, which has to be encoded with
+/// `GiroCode::ERROR_CORRECTION`, or `render` to do both.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GiroCode {
+    pub bic: String,
+    pub name: String,
+    pub iban: String,
+    /// The amount in euro cents
+    pub amount: Option<u64>,
+    /// A 4 letter ISO 20022 purpose code
+    pub purpose: String,
+    pub remittance: Option<Remittance>,
+    /// A note to show the person paying
+    pub information: String,
+}
+
+impl GiroCode {
+    /// The standard requires error correction level Medium
+    pub const ERROR_CORRECTION: ErrorCorrection = ErrorCorrection::Medium;
+
+    /// A transfer to the beneficiary's account
+    pub fn new(name: &str, iban: &str) -> Self {
+        GiroCode {
+            bic: String::new(),
+            name: name.to_string(),
+            iban: iban.to_string(),
+            amount: None,
+            purpose: String::new(),
+            remittance: None,
+            information: String::new(),
+        }
+    }
+
+    /// Set the beneficiary's bank, which is optional within the EEA
+    pub fn bic(mut self, bic: &str) -> Self {
+        self.bic = bic.to_string();
+        self
+    }
+
+    /// Set the amount in euro cents
+    pub fn amount(mut self, cents: u64) -> Self {
+        self.amount = Some(cents);
+        self
+    }
+
+    pub fn purpose(mut self, purpose: &str) -> Self {
+        self.purpose = purpose.to_string();
+        self
+    }
+
+    pub fn remittance(mut self, remittance: Remittance) -> Self {
+        self.remittance = Some(remittance);
+        self
+    }
+
+    pub fn information(mut self, information: &str) -> Self {
+        self.information = information.to_string();
+        self
+    }
+
+    /// Validate the fields and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        let bic = remove_spaces(&self.bic).to_ascii_uppercase();
+        if !bic.is_empty() && !is_valid_bic(&bic) {
+            return Err(PayloadError::InvalidField("bic"));
+        }
+
+        if self.name.trim().is_empty() {
+            return Err(PayloadError::MissingField("name"));
+        }
+        check_text(&self.name, 70, "name")?;
+        let iban = validate_iban(&self.iban)?;
+
+        let amount = match self.amount {
+            Some(cents) if (1..=MAX_AMOUNT_CENTS).contains(&cents) => {
+                format!("EUR{}.{:02}", cents / 100, cents % 100)
+            }
+            Some(_) => return Err(PayloadError::InvalidField("amount")),
+            None => String::new(),
+        };
+
+        let purpose_length = self.purpose.len();
+        if purpose_length != 0
+            && (purpose_length != 4 || !self.purpose.chars().all(|c| c.is_ascii_uppercase()))
+        {
+            return Err(PayloadError::InvalidField("purpose"));
+        }
+
+        // Only one of the two kinds of remittance information can be given
+        let (reference, text) = match &self.remittance {
+            Some(Remittance::Reference(reference)) => {
+                (validate_creditor_reference(reference)?, String::new())
+            }
+            Some(Remittance::Text(text)) => {
+                check_text(text, 140, "remittance")?;
+                (String::new(), text.clone())
+            }
+            None => (String::new(), String::new()),
+        };
+        check_text(&self.information, 70, "information")?;
+
+        let fields = [
+            "BCD",
+            "002",
+            "1", // UTF-8
+            "SCT",
+            &bic,
+            &self.name,
+            &iban,
+            &amount,
+            &self.purpose,
+            &reference,
+            &text,
+            &self.information,
+        ];
+        // Trailing empty fields can be left out
+        let used = fields.iter().rposition(|field| !field.is_empty()).unwrap() + 1;
+        let payload = fields[..used].join("\n");
+        if payload.len() > MAX_PAYLOAD_LENGTH {
+            return Err(PayloadError::TooLong("payload"));
+        }
+        Ok(payload)
+    }
+
+    /// Build the payload and draw it with the required error correction level
+    #[cfg(feature = "image")]
+    pub fn render(&self, options: &crate::Options) -> Result<image::RgbImage, PayloadError> {
+        crate::render_qr_code(&self.build()?, Self::ERROR_CORRECTION, options)
+            .map_err(PayloadError::Encoding)
+    }
+}
+
+// A bank code, country code, location code and optional branch code
+fn is_valid_bic(bic: &str) -> bool {
+    (bic.len() == 8 || bic.len() == 11)
+        && bic.chars().take(6).all(|c| c.is_ascii_uppercase())
+        && bic.chars().skip(6).all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    #[test]
+    fn test_building() {
+        let giro = GiroCode::new("Red Cross of Belgium", "BE72 0000 0000 1616")
+            .bic("BPOTBEB1")
+            .amount(100)
+            .purpose("CHAR")
+            .remittance(Remittance::Text("Urgency fund".to_string()));
+        assert_eq!(
+            giro.build().unwrap(),
+            "BCD\n002\n1\nSCT\nBPOTBEB1\nRed Cross of Belgium\nBE72000000001616\nEUR1.00\nCHAR\n\nUrgency fund"
+        );
+
+        let giro = GiroCode::new("Stadtwerke", "DE89370400440532013000")
+            .amount(123456)
+            .remittance(Remittance::Reference("rf18 5390 0754 7034".to_string()))
+            .information("Invoice 2024-17");
+        assert_eq!(
+            giro.build().unwrap(),
+            "BCD\n002\n1\nSCT\n\nStadtwerke\nDE89370400440532013000\nEUR1234.56\n\nRF18539007547034\n\nInvoice 2024-17"
+        );
+
+        // Trailing empty fields are left out
+        let giro = GiroCode::new("Stadtwerke", "DE89370400440532013000");
+        assert_eq!(
+            giro.build().unwrap(),
+            "BCD\n002\n1\nSCT\n\nStadtwerke\nDE89370400440532013000"
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let giro = GiroCode::new("Stadtwerke", "DE89370400440532013000");
+        assert_eq!(
+            GiroCode::new("Stadtwerke", "DE89370400440532013001").build(),
+            Err(PayloadError::InvalidChecksum("iban"))
+        );
+        assert_eq!(
+            giro.clone().bic("BPOT").build(),
+            Err(PayloadError::InvalidField("bic"))
+        );
+        assert_eq!(
+            giro.clone().amount(0).build(),
+            Err(PayloadError::InvalidField("amount"))
+        );
+        assert_eq!(
+            giro.clone().amount(100_000_000_000).build(),
+            Err(PayloadError::InvalidField("amount"))
+        );
+        assert!(giro.clone().amount(99_999_999_999).build().is_ok());
+        assert_eq!(
+            giro.clone().purpose("char").build(),
+            Err(PayloadError::InvalidField("purpose"))
+        );
+        assert_eq!(
+            giro.clone()
+                .remittance(Remittance::Reference("RF19539007547034".to_string()))
+                .build(),
+            Err(PayloadError::InvalidChecksum("reference"))
+        );
+        assert_eq!(
+            giro.clone()
+                .remittance(Remittance::Text("x".repeat(141)))
+                .build(),
+            Err(PayloadError::TooLong("remittance"))
+        );
+        assert_eq!(
+            giro.clone().information("two\nlines").build(),
+            Err(PayloadError::InvalidField("information"))
+        );
+        assert_eq!(
+            GiroCode::new("", "DE89370400440532013000").build(),
+            Err(PayloadError::MissingField("name"))
+        );
+        assert_eq!(
+            GiroCode::new(&"n".repeat(71), "DE89370400440532013000").build(),
+            Err(PayloadError::TooLong("name"))
+        );
+
+        // Every field is within its own limit, but multi-byte characters
+        // push the whole payload over 331 bytes
+        let giro = GiroCode::new(&"é".repeat(70), "DE89370400440532013000")
+            .remittance(Remittance::Text("ü".repeat(140)))
+            .information(&"ö".repeat(70));
+        assert_eq!(giro.build(), Err(PayloadError::TooLong("payload")));
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_rendering() {
+        let giro = GiroCode::new("Stadtwerke", "DE89370400440532013000").amount(1999);
+        let image = giro.render(&crate::Options::default()).unwrap();
+        let decoded = crate::decode_image(&image.into());
+        assert_eq!(decoded[0].text, giro.build().unwrap());
+        assert_eq!(decoded[0].level, crate::ErrorCorrection::Medium);
+
+        let small = crate::Options {
+            version: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            giro.render(&small).err(),
+            Some(PayloadError::Encoding(crate::EncodeError::DataTooLong))
+        );
+    }
+}
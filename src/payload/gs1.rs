@@ -0,0 +1,457 @@
+use super::{percent_decode, percent_encode, uppercase_url, PayloadError};
+
+const DEFAULT_DOMAIN: &str = "https://id.gs1.org";
+
+// The punctuation GS1 allows in alphanumeric values, besides letters and digits
+const ALLOWED_PUNCTUATION: &str = "!\"%&'()*+,-./:;<=>?_";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Format {
+    /// A fixed number of digits
+    Numeric(usize),
+    /// A date written as YYMMDD, where a day of 00 means the end of the month
+    Date,
+    /// Up to this many digits
+    VariableNumeric(usize),
+    /// Up to this many characters
+    Text(usize),
+}
+
+#[derive(Debug)]
+struct ApplicationIdentifier {
+    code: &'static str,
+    name: &'static str,
+    format: Format,
+    /// Whether the value ends with a GS1 check digit
+    check_digit: bool,
+    /// For primary keys, the identifiers that can follow them in the path, in order
+    qualifiers: Option<&'static [&'static str]>,
+}
+
+const fn ai(
+    code: &'static str,
+    name: &'static str,
+    format: Format,
+    check_digit: bool,
+    qualifiers: Option<&'static [&'static str]>,
+) -> ApplicationIdentifier {
+    ApplicationIdentifier {
+        code,
+        name,
+        format,
+        check_digit,
+        qualifiers,
+    }
+}
+
+// The application identifiers that can be used: the common primary keys,
+// their qualifiers and data attributes
+const APPLICATION_IDENTIFIERS: [ApplicationIdentifier; 25] = [
+    ai("00", "sscc", Format::Numeric(18), true, Some(&[])),
+    ai(
+        "01",
+        "gtin",
+        Format::Numeric(14),
+        true,
+        Some(&["22", "10", "21"]),
+    ),
+    ai("10", "batch", Format::Text(20), false, None),
+    ai("11", "production date", Format::Date, false, None),
+    ai("13", "packaging date", Format::Date, false, None),
+    ai("15", "best before date", Format::Date, false, None),
+    ai("16", "sell by date", Format::Date, false, None),
+    ai("17", "expiration date", Format::Date, false, None),
+    ai("21", "serial", Format::Text(20), false, None),
+    ai("22", "variant", Format::Text(20), false, None),
+    ai("254", "gln extension", Format::Text(20), false, None),
+    ai("30", "count", Format::VariableNumeric(8), false, None),
+    ai("3100", "net weight", Format::Numeric(6), false, None),
+    ai("3101", "net weight", Format::Numeric(6), false, None),
+    ai("3102", "net weight", Format::Numeric(6), false, None),
+    ai("3103", "net weight", Format::Numeric(6), false, None),
+    ai("37", "count", Format::VariableNumeric(8), false, None),
+    ai("400", "order number", Format::Text(30), false, None),
+    ai("414", "gln", Format::Numeric(13), true, Some(&["254"])),
+    ai("417", "party gln", Format::Numeric(13), true, Some(&[])),
+    ai("7003", "expiration time", Format::Numeric(10), false, None),
+    ai("8004", "giai", Format::Text(30), false, Some(&[])),
+    ai(
+        "8017",
+        "gsrn provider",
+        Format::Numeric(18),
+        true,
+        Some(&[]),
+    ),
+    ai("8018", "gsrn", Format::Numeric(18), true, Some(&["8019"])),
+    ai(
+        "8019",
+        "service relation",
+        Format::VariableNumeric(10),
+        false,
+        None,
+    ),
+];
+
+// An application identifier and its validated value
+type Element = (&'static ApplicationIdentifier, String);
+
+fn find_ai(code: &str) -> Option<&'static ApplicationIdentifier> {
+    APPLICATION_IDENTIFIERS.iter().find(|ai| ai.code == code)
+}
+
+fn is_qualifier(code: &str) -> bool {
+    APPLICATION_IDENTIFIERS
+        .iter()
+        .filter_map(|ai| ai.qualifiers)
+        .any(|qualifiers| qualifiers.contains(&code))
+}
+
+// Compute the GS1 check digit for the digits before it. Weights
+// alternate between 3 and 1, starting with 3 on the rightmost digit.
+fn get_check_digit(digits: &str) -> u32 {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .enumerate()
+        .map(|(i, c)| c.to_digit(10).unwrap() * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+// Check a value against its identifier's format, returning it normalized
+fn validate_value(ai: &ApplicationIdentifier, value: &str) -> Result<String, PayloadError> {
+    let is_numeric = !value.is_empty() && value.chars().all(|c| c.is_ascii_digit());
+    let value = match ai.format {
+        // Shorter GTINs are padded to 14 digits
+        Format::Numeric(14) if is_numeric && [8, 12, 13].contains(&value.len()) => {
+            format!("{:0>14}", value)
+        }
+        Format::Numeric(length) if is_numeric && value.len() == length => value.to_string(),
+        Format::Date if is_numeric && value.len() == 6 => {
+            let month: u32 = value[2..4].parse().unwrap();
+            let day: u32 = value[4..].parse().unwrap();
+            if !(1..=12).contains(&month) || day > 31 {
+                return Err(PayloadError::InvalidField(ai.name));
+            }
+            value.to_string()
+        }
+        Format::VariableNumeric(max) if is_numeric && value.len() <= max => value.to_string(),
+        Format::Text(max) => {
+            let valid_chars = value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || ALLOWED_PUNCTUATION.contains(c));
+            if !valid_chars || value.is_empty() {
+                return Err(PayloadError::InvalidField(ai.name));
+            }
+            if value.len() > max {
+                return Err(PayloadError::TooLong(ai.name));
+            }
+            value.to_string()
+        }
+        _ => return Err(PayloadError::InvalidField(ai.name)),
+    };
+
+    if ai.check_digit {
+        let (digits, check_digit) = value.split_at(value.len() - 1);
+        if check_digit.parse() != Ok(get_check_digit(digits)) {
+            return Err(PayloadError::InvalidChecksum(ai.name));
+        }
+    }
+    Ok(value)
+}
+
+/// A GS1 Digital Link URI, which carries the same application identifiers as a
+/// retail barcode in a web address, like `https://id.gs1.org/01/09506000134352/10/ABC`.
+/// Use `build` to get the text to encode. The compressed form from the GS1
+/// Digital Link compression standard isn't supported.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DigitalLink {
+    /// The scheme and host, with an optional path, that the link starts with
+    pub domain: String,
+    /// Application identifiers, like 01 for a GTIN, and their values
+    pub elements: Vec<(String, String)>,
+}
+
+impl DigitalLink {
+    /// A link on GS1's resolver for a primary key, like 01 and a GTIN
+    pub fn new(ai: &str, value: &str) -> Self {
+        DigitalLink {
+            domain: DEFAULT_DOMAIN.to_string(),
+            elements: vec![(ai.to_string(), value.to_string())],
+        }
+    }
+
+    /// Use a different resolver, like the brand's own website
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.domain = domain.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Add a key qualifier, like a batch or serial number, or a data attribute
+    pub fn element(mut self, ai: &str, value: &str) -> Self {
+        self.elements.push((ai.to_string(), value.to_string()));
+        self
+    }
+
+    // Validate the elements and put them in the order they're written in:
+    // the primary key, its qualifiers in their defined order, then the
+    // attributes. Also returns how many of them go in the path.
+    fn get_ordered_elements(&self) -> Result<(Vec<Element>, usize), PayloadError> {
+        let mut elements: Vec<Element> = Vec::new();
+        for (code, value) in &self.elements {
+            let ai = find_ai(code).ok_or(PayloadError::InvalidField("ai"))?;
+            if elements.iter().any(|(other, _)| other.code == ai.code) {
+                return Err(PayloadError::InvalidField(ai.name));
+            }
+            elements.push((ai, validate_value(ai, value)?));
+        }
+
+        let keys: Vec<&ApplicationIdentifier> = elements
+            .iter()
+            .map(|(ai, _)| *ai)
+            .filter(|ai| ai.qualifiers.is_some())
+            .collect();
+        let key = match keys[..] {
+            [key] => key,
+            [] => return Err(PayloadError::MissingField("primary key")),
+            _ => return Err(PayloadError::InvalidField("primary key")),
+        };
+
+        // Qualifiers can only be used with the key they belong to
+        let qualifiers = key.qualifiers.unwrap();
+        if let Some((ai, _)) = elements
+            .iter()
+            .find(|(ai, _)| is_qualifier(ai.code) && !qualifiers.contains(&ai.code))
+        {
+            return Err(PayloadError::InvalidField(ai.name));
+        }
+
+        let position =
+            |ai: &ApplicationIdentifier| match qualifiers.iter().position(|&q| q == ai.code) {
+                _ if ai.code == key.code => 0,
+                Some(i) => i + 1,
+                None => qualifiers.len() + 1,
+            };
+        // The sort is stable, so attributes keep the order they were added in
+        elements.sort_by_key(|(ai, _)| position(ai));
+        let path_length = 1 + elements
+            .iter()
+            .filter(|(ai, _)| is_qualifier(ai.code))
+            .count();
+        Ok((elements, path_length))
+    }
+
+    /// Validate the elements and build the URI. The scheme and host are written
+    /// in upper case when that lets the whole URI be encoded in alphanumeric mode.
+    pub fn build(&self) -> Result<String, PayloadError> {
+        let (elements, path_length) = self.get_ordered_elements()?;
+        let (path, query) = elements.split_at(path_length);
+
+        let mut url = self.domain.clone();
+        for (ai, value) in path {
+            url += &format!("/{}/{}", ai.code, percent_encode(value));
+        }
+        let query: Vec<String> = query
+            .iter()
+            .map(|(ai, value)| format!("{}={}", ai.code, percent_encode(value)))
+            .collect();
+        if !query.is_empty() {
+            url += &format!("?{}", query.join("&"));
+        }
+        Ok(uppercase_url(&url))
+    }
+
+    /// Read a link back from its URI
+    pub fn parse(text: &str) -> Result<Self, PayloadError> {
+        let (url, query) = text.split_once('?').unwrap_or((text, ""));
+        let host_start = url.find("://").ok_or(PayloadError::InvalidPrefix)? + 3;
+        let path_start = url[host_start..]
+            .find('/')
+            .map_or(url.len(), |i| i + host_start);
+        let segments: Vec<&str> = url[path_start..].split('/').skip(1).collect();
+
+        // The domain can have its own path, so the link starts at the first primary key
+        let is_key = |segment: &str| find_ai(segment).is_some_and(|ai| ai.qualifiers.is_some());
+        let Some(key_index) = (0..segments.len().saturating_sub(1)).find(|&i| is_key(segments[i]))
+        else {
+            return Err(PayloadError::MissingField("primary key"));
+        };
+
+        // The scheme and host might have been written in upper case
+        let mut domain = url[..path_start].to_ascii_lowercase();
+        for segment in &segments[..key_index] {
+            domain += &format!("/{}", segment);
+        }
+
+        let mut elements = Vec::new();
+        let path = &segments[key_index..];
+        if !path.len().is_multiple_of(2) {
+            return Err(PayloadError::InvalidField("path"));
+        }
+        for pair in path.chunks(2) {
+            let value = percent_decode(pair[1]).ok_or(PayloadError::InvalidField("path"))?;
+            elements.push((pair[0].to_string(), value));
+        }
+
+        // Other query parameters, like the link type, aren't identifiers
+        for parameter in query.split('&') {
+            let Some((key, value)) = parameter.split_once('=') else {
+                continue;
+            };
+            if !key.is_empty() && key.chars().all(|c| c.is_ascii_digit()) {
+                let value = percent_decode(value).ok_or(PayloadError::InvalidField("query"))?;
+                elements.push((key.to_string(), value));
+            }
+        }
+
+        let link = DigitalLink { domain, elements };
+        link.get_ordered_elements()?;
+        Ok(link)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoder::get_encoding_mode;
+    use crate::payload::gs1::get_check_digit;
+    use crate::payload::*;
+    use crate::EncodingMode;
+
+    #[test]
+    fn test_check_digits() {
+        assert_eq!(get_check_digit("0950600013435"), 2);
+        assert_eq!(get_check_digit("952012345678"), 8);
+        assert_eq!(get_check_digit("10614141234567890"), 8);
+    }
+
+    #[test]
+    fn test_building() {
+        let link = DigitalLink::new("01", "09506000134352")
+            .element("17", "201225")
+            .element("21", "12345")
+            .element("10", "ABC/1");
+        assert_eq!(
+            link.build().unwrap(),
+            "https://id.gs1.org/01/09506000134352/10/ABC%2F1/21/12345?17=201225"
+        );
+
+        // GTIN-13s are padded, and other resolvers can be used
+        let link = DigitalLink::new("01", "9506000134352")
+            .domain("https://example.com/products/")
+            .element("3103", "000195");
+        assert_eq!(
+            link.build().unwrap(),
+            "https://example.com/products/01/09506000134352?3103=000195"
+        );
+
+        let link = DigitalLink::new("414", "9520123456788").element("254", "32a");
+        assert_eq!(
+            link.build().unwrap(),
+            "https://id.gs1.org/414/9520123456788/254/32a"
+        );
+    }
+
+    #[test]
+    fn test_uppercase() {
+        let text = DigitalLink::new("01", "09506000134352")
+            .element("10", "LOT-42")
+            .build()
+            .unwrap();
+        assert_eq!(text, "HTTPS://ID.GS1.ORG/01/09506000134352/10/LOT-42");
+        assert_eq!(get_encoding_mode(&text), EncodingMode::Alphanumeric);
+        assert_eq!(
+            DigitalLink::parse(&text),
+            Ok(DigitalLink::new("01", "09506000134352").element("10", "LOT-42"))
+        );
+    }
+
+    #[test]
+    fn test_validation() {
+        let gtin = DigitalLink::new("01", "09506000134352");
+        assert_eq!(
+            DigitalLink::new("01", "09506000134353").build(),
+            Err(PayloadError::InvalidChecksum("gtin"))
+        );
+        assert_eq!(
+            DigitalLink::new("01", "950600013435").build(),
+            Err(PayloadError::InvalidChecksum("gtin"))
+        );
+        assert_eq!(
+            DigitalLink::new("01", "0950600013435x").build(),
+            Err(PayloadError::InvalidField("gtin"))
+        );
+        assert_eq!(
+            gtin.clone().element("17", "201325").build(),
+            Err(PayloadError::InvalidField("expiration date"))
+        );
+        assert_eq!(
+            gtin.clone().element("21", &"1".repeat(21)).build(),
+            Err(PayloadError::TooLong("serial"))
+        );
+        assert_eq!(
+            gtin.clone().element("21", "a b").build(),
+            Err(PayloadError::InvalidField("serial"))
+        );
+        assert_eq!(
+            gtin.clone().element("254", "1").build(),
+            Err(PayloadError::InvalidField("gln extension"))
+        );
+        assert_eq!(
+            gtin.clone().element("00", "106141412345678908").build(),
+            Err(PayloadError::InvalidField("primary key"))
+        );
+        assert_eq!(
+            gtin.element("99", "1").build(),
+            Err(PayloadError::InvalidField("ai"))
+        );
+        assert_eq!(
+            DigitalLink::new("17", "201225").build(),
+            Err(PayloadError::MissingField("primary key"))
+        );
+    }
+
+    #[test]
+    fn test_parsing() {
+        let link = DigitalLink::parse(
+            "https://example.com/shop/01/09506000134352/21/x%2Fy?17=201225&linkType=gs1:pip",
+        )
+        .unwrap();
+        assert_eq!(link.domain, "https://example.com/shop");
+        assert_eq!(
+            link.elements,
+            [
+                ("01".to_string(), "09506000134352".to_string()),
+                ("21".to_string(), "x/y".to_string()),
+                ("17".to_string(), "201225".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            DigitalLink::parse("id.gs1.org/01/09506000134352"),
+            Err(PayloadError::InvalidPrefix)
+        );
+        assert_eq!(
+            DigitalLink::parse("https://id.gs1.org/01/09506000134352/21"),
+            Err(PayloadError::InvalidField("path"))
+        );
+        assert_eq!(
+            DigitalLink::parse("https://id.gs1.org/"),
+            Err(PayloadError::MissingField("primary key"))
+        );
+
+        let links = [
+            DigitalLink::new("01", "09506000134352")
+                .element("22", "2A")
+                .element("21", "deadbeef")
+                .element("30", "0042"),
+            DigitalLink::new("8004", "0950600013435Serial_Number-1")
+                .domain("https://example.com")
+                .element("400", "PO:1/A"),
+            DigitalLink::new("00", "106141412345678908").element("7003", "2012251130"),
+        ];
+        for link in links {
+            let full = link.build().unwrap();
+            assert_eq!(DigitalLink::parse(&full).unwrap().build().unwrap(), full);
+        }
+    }
+}
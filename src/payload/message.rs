@@ -0,0 +1,221 @@
+use super::{percent_encode, with_scheme, PayloadError};
+
+/// The text formats a text message can be written in
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SmsFormat {
+    /// `SMSTO:number:message`, which most scanners understand
+    SmsTo,
+    /// An RFC 5724 `sms:number?body=message` URI
+    Uri,
+}
+
+/// A text message ready to send. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sms {
+    pub number: String,
+    pub message: String,
+    pub format: SmsFormat,
+}
+
+impl Sms {
+    pub fn new(number: &str) -> Self {
+        Sms {
+            number: number.to_string(),
+            message: String::new(),
+            format: SmsFormat::SmsTo,
+        }
+    }
+
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    pub fn format(mut self, format: SmsFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Validate the phone number and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        let number = validate_phone_number(&self.number)?;
+        Ok(match self.format {
+            SmsFormat::SmsTo => format!("SMSTO:{}:{}", number, self.message),
+            SmsFormat::Uri if self.message.is_empty() => with_scheme("sms", &number),
+            SmsFormat::Uri => {
+                let text = format!("{}?body={}", number, percent_encode(&self.message));
+                with_scheme("sms", &text)
+            }
+        })
+    }
+}
+
+/// A phone number to call, as a `tel:` URI. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Phone {
+    pub number: String,
+}
+
+impl Phone {
+    pub fn new(number: &str) -> Self {
+        Phone {
+            number: number.to_string(),
+        }
+    }
+
+    /// Validate the phone number and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        Ok(with_scheme("tel", &validate_phone_number(&self.number)?))
+    }
+}
+
+/// An email ready to send, as a `mailto:` URI. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Email {
+    pub fn new(to: &str) -> Self {
+        Email {
+            to: to.to_string(),
+            subject: String::new(),
+            body: String::new(),
+        }
+    }
+
+    pub fn subject(mut self, subject: &str) -> Self {
+        self.subject = subject.to_string();
+        self
+    }
+
+    pub fn body(mut self, body: &str) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    /// Validate the address and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        let valid_address = match self.to.split_once('@') {
+            Some((user, domain)) => {
+                !user.is_empty()
+                    && !domain.is_empty()
+                    && !domain.contains('@')
+                    && !self
+                        .to
+                        .contains(|c: char| c.is_whitespace() || "?&%".contains(c))
+            }
+            None => false,
+        };
+        if !valid_address {
+            return Err(PayloadError::InvalidField("to"));
+        }
+
+        let mut fields = Vec::new();
+        if !self.subject.is_empty() {
+            fields.push(format!("subject={}", percent_encode(&self.subject)));
+        }
+        if !self.body.is_empty() {
+            fields.push(format!("body={}", percent_encode(&self.body)));
+        }
+        let mut text = self.to.clone();
+        if !fields.is_empty() {
+            text += &format!("?{}", fields.join("&"));
+        }
+        Ok(with_scheme("mailto", &text))
+    }
+}
+
+// Remove the spaces and punctuation people write phone numbers with,
+// leaving the digits and an optional leading plus sign
+fn validate_phone_number(number: &str) -> Result<String, PayloadError> {
+    let number: String = number
+        .chars()
+        .filter(|c| !c.is_whitespace() && !"-.()".contains(*c))
+        .collect();
+    let digits = number.strip_prefix('+').unwrap_or(&number);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(PayloadError::InvalidField("number"));
+    }
+    Ok(number)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoder::get_encoding_mode;
+    use crate::payload::*;
+    use crate::EncodingMode;
+
+    #[test]
+    fn test_sms() {
+        let sms = Sms::new("+1 (555) 010-0199").message("Running late, 10 min");
+        assert_eq!(
+            sms.build().unwrap(),
+            "SMSTO:+15550100199:Running late, 10 min"
+        );
+        assert_eq!(
+            sms.format(SmsFormat::Uri).build().unwrap(),
+            "sms:+15550100199?body=Running%20late%2C%2010%20min"
+        );
+        assert_eq!(
+            Sms::new("5550100").format(SmsFormat::Uri).build().unwrap(),
+            "SMS:5550100"
+        );
+        assert_eq!(
+            Sms::new("call me").build(),
+            Err(PayloadError::InvalidField("number"))
+        );
+
+        // Messages in upper case keep the whole text alphanumeric
+        let text = Sms::new("+15550100").message("STOP").build().unwrap();
+        assert_eq!(get_encoding_mode(&text), EncodingMode::Alphanumeric);
+    }
+
+    #[test]
+    fn test_phone() {
+        let text = Phone::new("+44 20 7946 0000").build().unwrap();
+        assert_eq!(text, "TEL:+442079460000");
+        assert_eq!(get_encoding_mode(&text), EncodingMode::Alphanumeric);
+
+        assert_eq!(
+            Phone::new("+").build(),
+            Err(PayloadError::InvalidField("number"))
+        );
+        assert_eq!(
+            Phone::new("555-CALL").build(),
+            Err(PayloadError::InvalidField("number"))
+        );
+    }
+
+    #[test]
+    fn test_email() {
+        assert_eq!(
+            Email::new("ada@example.com").build().unwrap(),
+            "mailto:ada@example.com"
+        );
+        assert_eq!(
+            Email::new("ada@example.com")
+                .subject("Hello & welcome")
+                .body("Line one\r\nLine two é")
+                .build()
+                .unwrap(),
+            "mailto:ada@example.com?subject=Hello%20%26%20welcome&body=Line%20one%0D%0ALine%20two%20%C3%A9"
+        );
+        for to in [
+            "",
+            "ada",
+            "@example.com",
+            "ada@",
+            "a@b@c",
+            "ada smith@example.com",
+            "a?b@c",
+        ] {
+            assert_eq!(
+                Email::new(to).build(),
+                Err(PayloadError::InvalidField("to"))
+            );
+        }
+    }
+}
@@ -0,0 +1,402 @@
+use super::{percent_decode, percent_encode, PayloadError};
+use crate::{ErrorCorrection, Options};
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The hash function the one time passwords are computed with
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA1",
+            Algorithm::Sha256 => "SHA256",
+            Algorithm::Sha512 => "SHA512",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA1" => Some(Algorithm::Sha1),
+            "SHA256" => Some(Algorithm::Sha256),
+            "SHA512" => Some(Algorithm::Sha512),
+            _ => None,
+        }
+    }
+}
+
+/// Whether passwords change over time or each time one is used
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum OtpKind {
+    /// Time based, with a new password every period seconds
+    Totp { period: u32 },
+    /// Counter based, starting from the given counter
+    Hotp { counter: u64 },
+}
+
+/// The `otpauth://` key that authenticator apps scan to set up two factor
+/// authentication. Use `build` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Otp {
+    pub kind: OtpKind,
+    /// The shared secret as raw bytes, which gets written in base32
+    pub secret: Vec<u8>,
+    /// The service the account belongs to
+    pub issuer: String,
+    pub account: String,
+    pub algorithm: Algorithm,
+    pub digits: u32,
+}
+
+impl Otp {
+    /// A time based key with the defaults every app supports:
+    /// SHA1, 6 digits and a new password every 30 seconds
+    pub fn totp(secret: &[u8], issuer: &str, account: &str) -> Self {
+        Otp {
+            kind: OtpKind::Totp { period: 30 },
+            secret: secret.to_vec(),
+            issuer: issuer.to_string(),
+            account: account.to_string(),
+            algorithm: Algorithm::Sha1,
+            digits: 6,
+        }
+    }
+
+    /// A counter based key with SHA1 and 6 digits
+    pub fn hotp(secret: &[u8], issuer: &str, account: &str, counter: u64) -> Self {
+        Otp {
+            kind: OtpKind::Hotp { counter },
+            ..Otp::totp(secret, issuer, account)
+        }
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn digits(mut self, digits: u32) -> Self {
+        self.digits = digits;
+        self
+    }
+
+    /// Set how many seconds each time based password lasts
+    pub fn period(mut self, period: u32) -> Self {
+        self.kind = OtpKind::Totp { period };
+        self
+    }
+
+    /// Validate the fields and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        if self.secret.is_empty() {
+            return Err(PayloadError::MissingField("secret"));
+        }
+        if self.account.is_empty() {
+            return Err(PayloadError::MissingField("account"));
+        }
+        if !(6..=8).contains(&self.digits) {
+            return Err(PayloadError::InvalidField("digits"));
+        }
+
+        // The issuer goes in both the label and its own parameter,
+        // since older apps only read it from the label
+        let mut label = percent_encode(&self.account);
+        let mut parameters = vec![format!("secret={}", encode_base32(&self.secret))];
+        if !self.issuer.is_empty() {
+            label = format!("{}:{}", percent_encode(&self.issuer), label);
+            parameters.push(format!("issuer={}", percent_encode(&self.issuer)));
+        }
+
+        // Leave out the defaults to keep the code small
+        if self.algorithm != Algorithm::Sha1 {
+            parameters.push(format!("algorithm={}", self.algorithm.name()));
+        }
+        if self.digits != 6 {
+            parameters.push(format!("digits={}", self.digits));
+        }
+        let kind = match self.kind {
+            OtpKind::Totp { period: 0 } => return Err(PayloadError::InvalidField("period")),
+            OtpKind::Totp { period } => {
+                if period != 30 {
+                    parameters.push(format!("period={}", period));
+                }
+                "totp"
+            }
+            OtpKind::Hotp { counter } => {
+                parameters.push(format!("counter={}", counter));
+                "hotp"
+            }
+        };
+
+        Ok(format!(
+            "otpauth://{}/{}?{}",
+            kind,
+            label,
+            parameters.join("&")
+        ))
+    }
+
+    /// Read a key back out of a scanned qr code
+    pub fn parse(text: &str) -> Result<Self, PayloadError> {
+        let rest = text
+            .strip_prefix("otpauth://")
+            .ok_or(PayloadError::InvalidPrefix)?;
+        let (kind, rest) = rest
+            .split_once('/')
+            .ok_or(PayloadError::MissingField("label"))?;
+        let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+
+        let decode =
+            |value: &str, field| percent_decode(value).ok_or(PayloadError::InvalidField(field));
+        let (issuer, account) = match label.split_once(':') {
+            Some((issuer, account)) => (decode(issuer, "issuer")?, decode(account, "account")?),
+            None => (String::new(), decode(label, "account")?),
+        };
+        let mut otp = Otp::totp(&[], &issuer, account.trim_start());
+
+        let mut period = None;
+        let mut counter = None;
+        for parameter in query.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            match key {
+                "secret" => {
+                    otp.secret = decode_base32(value).ok_or(PayloadError::InvalidField("secret"))?
+                }
+                "issuer" => otp.issuer = decode(value, "issuer")?,
+                "algorithm" => {
+                    otp.algorithm = Algorithm::from_name(value)
+                        .ok_or(PayloadError::InvalidField("algorithm"))?
+                }
+                "digits" => {
+                    otp.digits = value
+                        .parse()
+                        .map_err(|_| PayloadError::InvalidField("digits"))?
+                }
+                "period" => {
+                    period = Some(
+                        value
+                            .parse()
+                            .map_err(|_| PayloadError::InvalidField("period"))?,
+                    )
+                }
+                "counter" => {
+                    counter = Some(
+                        value
+                            .parse()
+                            .map_err(|_| PayloadError::InvalidField("counter"))?,
+                    )
+                }
+                // Ignore parameters from extensions like image
+                _ => {}
+            }
+        }
+
+        otp.kind = match kind {
+            "totp" => OtpKind::Totp {
+                period: period.unwrap_or(30),
+            },
+            "hotp" => OtpKind::Hotp {
+                counter: counter.ok_or(PayloadError::MissingField("counter"))?,
+            },
+            _ => return Err(PayloadError::InvalidField("type")),
+        };
+        otp.build()?;
+        Ok(otp)
+    }
+
+    /// Build the payload and draw it as text, for enrolling from a terminal
+    pub fn render_text(&self, options: &Options) -> Result<String, PayloadError> {
+        crate::render_qr_code_to_text(&self.build()?, ErrorCorrection::Medium, options)
+            .map_err(PayloadError::Encoding)
+    }
+}
+
+impl std::str::FromStr for Otp {
+    type Err = PayloadError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Otp::parse(text)
+    }
+}
+
+// RFC 4648 base32 without padding, which is how authenticator apps expect secrets
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(BASE32_ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        text.push(BASE32_ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    text
+}
+
+// Decode base32 in either case, ignoring padding and spaces
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in text.chars().filter(|&c| c != '=' && c != ' ') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&letter| letter as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    (!bytes.is_empty()).then_some(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::otp::{decode_base32, encode_base32};
+    use crate::payload::*;
+
+    #[test]
+    fn test_base32() {
+        // The test vectors from RFC 4648, without padding
+        let vectors = [
+            ("f", "MY"),
+            ("fo", "MZXQ"),
+            ("foo", "MZXW6"),
+            ("foob", "MZXW6YQ"),
+            ("fooba", "MZXW6YTB"),
+            ("foobar", "MZXW6YTBOI"),
+        ];
+        for (bytes, text) in vectors {
+            assert_eq!(encode_base32(bytes.as_bytes()), text);
+            assert_eq!(decode_base32(text).unwrap(), bytes.as_bytes());
+        }
+        assert_eq!(decode_base32("mzxw 6ytb oi======").unwrap(), b"foobar");
+        assert_eq!(decode_base32("MZ1"), None);
+    }
+
+    #[test]
+    fn test_building() {
+        let secret = b"Hello!\xde\xad\xbe\xef";
+        let otp = Otp::totp(secret, "Example", "alice@google.com");
+        assert_eq!(
+            otp.build().unwrap(),
+            "otpauth://totp/Example:alice%40google.com?secret=JBSWY3DPEHPK3PXP&issuer=Example"
+        );
+
+        let otp = Otp::hotp(b"12345678901234567890", "ACME Co", "john", 7)
+            .algorithm(Algorithm::Sha256)
+            .digits(8);
+        assert_eq!(
+            otp.build().unwrap(),
+            "otpauth://hotp/ACME%20Co:john?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &issuer=ACME%20Co&algorithm=SHA256&digits=8&counter=7"
+        );
+
+        let otp = Otp::totp(secret, "", "bob").period(60);
+        assert_eq!(
+            otp.build().unwrap(),
+            "otpauth://totp/bob?secret=JBSWY3DPEHPK3PXP&period=60"
+        );
+
+        assert_eq!(
+            Otp::totp(&[], "x", "y").build(),
+            Err(PayloadError::MissingField("secret"))
+        );
+        assert_eq!(
+            Otp::totp(secret, "x", "").build(),
+            Err(PayloadError::MissingField("account"))
+        );
+        assert_eq!(
+            Otp::totp(secret, "x", "y").digits(10).build(),
+            Err(PayloadError::InvalidField("digits"))
+        );
+        assert_eq!(
+            Otp::totp(secret, "x", "y").period(0).build(),
+            Err(PayloadError::InvalidField("period"))
+        );
+    }
+
+    #[test]
+    fn test_parsing() {
+        let otp: Otp =
+            "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ\
+                        &issuer=ACME%20Co&algorithm=SHA1&digits=6&period=30"
+                .parse()
+                .unwrap();
+        assert_eq!(otp.issuer, "ACME Co");
+        assert_eq!(otp.account, "john.doe@email.com");
+        assert_eq!(otp.kind, OtpKind::Totp { period: 30 });
+        assert_eq!(otp.secret.len(), 20);
+
+        // Everything that can be built can be read back
+        let otp =
+            Otp::hotp(b"secret", "Issuer: Inc & co", "name?", 99).algorithm(Algorithm::Sha512);
+        assert_eq!(Otp::parse(&otp.build().unwrap()), Ok(otp));
+
+        assert_eq!(Otp::parse("otpauth:/"), Err(PayloadError::InvalidPrefix));
+        assert_eq!(
+            Otp::parse("otpauth://hotp/a?secret=MZXW6"),
+            Err(PayloadError::MissingField("counter"))
+        );
+        assert_eq!(
+            Otp::parse("otpauth://motp/a?secret=MZXW6"),
+            Err(PayloadError::InvalidField("type"))
+        );
+        assert_eq!(
+            Otp::parse("otpauth://totp/a?secret=MZXW6&algorithm=MD5"),
+            Err(PayloadError::InvalidField("algorithm"))
+        );
+        assert_eq!(
+            Otp::parse("otpauth://totp/a?secret=0000"),
+            Err(PayloadError::InvalidField("secret"))
+        );
+    }
+
+    #[test]
+    fn test_rendering() {
+        let otp = Otp::totp(b"12345678901234567890", "SSO", "ada");
+        let text = otp.render_text(&crate::Options::default()).unwrap();
+
+        // Turn the blocks back into modules, dropping the 4 module quiet zone
+        let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+        let size = lines[0].len() - 8;
+        let mut matrix = vec![255; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                let c = lines[(y + 4) / 2][x + 4];
+                let dark = if (y + 4) % 2 == 0 {
+                    c == '█' || c == '▀'
+                } else {
+                    c == '█' || c == '▄'
+                };
+                if dark {
+                    matrix[y * size + x] = 0;
+                }
+            }
+        }
+
+        let decoded = crate::decode_matrix(size, &matrix).unwrap();
+        assert_eq!(Otp::parse(&decoded.text).as_ref(), Ok(&otp));
+
+        let small = crate::Options {
+            version: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            otp.render_text(&small),
+            Err(PayloadError::Encoding(crate::EncodeError::DataTooLong))
+        );
+    }
+}
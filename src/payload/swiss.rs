@@ -0,0 +1,599 @@
+use super::{check_text, remove_spaces, validate_creditor_reference, validate_iban, PayloadError};
+use crate::ErrorCorrection;
+
+const MAX_PAYLOAD_LENGTH: usize = 997;
+const MAX_AMOUNT_CENTS: u64 = 99_999_999_999;
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+const POINTS_PER_MILLIMETER: f64 = 72.0 / MILLIMETERS_PER_INCH;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Currency {
+    Chf,
+    Eur,
+}
+
+/// A structured address. The name, postal code, town and country are required.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct SwissAddress {
+    pub name: String,
+    pub street: String,
+    pub house_number: String,
+    pub postal_code: String,
+    pub town: String,
+    /// Two letter ISO 3166 country code
+    pub country: String,
+}
+
+impl SwissAddress {
+    fn fields(&self) -> Result<[String; 7], PayloadError> {
+        if self.name.trim().is_empty() {
+            return Err(PayloadError::MissingField("name"));
+        }
+        if self.postal_code.trim().is_empty() {
+            return Err(PayloadError::MissingField("postal code"));
+        }
+        if self.town.trim().is_empty() {
+            return Err(PayloadError::MissingField("town"));
+        }
+        let valid_country =
+            self.country.len() == 2 && self.country.chars().all(|c| c.is_ascii_alphabetic());
+        if !valid_country {
+            return Err(PayloadError::InvalidField("country"));
+        }
+
+        check_text(&self.name, 70, "name")?;
+        check_text(&self.street, 70, "street")?;
+        check_text(&self.house_number, 16, "house number")?;
+        check_text(&self.postal_code, 16, "postal code")?;
+        check_text(&self.town, 35, "town")?;
+
+        // S marks a structured address
+        Ok([
+            "S".to_string(),
+            self.name.clone(),
+            self.street.clone(),
+            self.house_number.clone(),
+            self.postal_code.clone(),
+            self.town.clone(),
+            self.country.to_ascii_uppercase(),
+        ])
+    }
+}
+
+/// How the payment refers to the bill being paid
+#[derive(Clone, PartialEq, Debug)]
+pub enum SwissReference {
+    /// A 27 digit QR reference, which must be used with a QR-IBAN
+    Qr(String),
+    /// An ISO 11649 creditor reference, which can't be used with a QR-IBAN
+    Creditor(String),
+}
+
+impl SwissReference {
+    /// Create a QR reference from up to 26 digits, such as a customer and
+    /// invoice number, by padding them with zeros and adding the check digit
+    pub fn qr(number: &str) -> Result<Self, PayloadError> {
+        let number = remove_spaces(number);
+        if number.len() > 26 || !number.chars().all(|c| c.is_ascii_digit()) {
+            return Err(PayloadError::InvalidField("reference"));
+        }
+        let padded = format!("{:0>26}", number);
+        let check_digit = (10 - get_mod10_carry(&padded)) % 10;
+        Ok(SwissReference::Qr(format!("{}{}", padded, check_digit)))
+    }
+}
+
+// Run the recursive mod 10 algorithm over a string of digits
+fn get_mod10_carry(digits: &str) -> u32 {
+    const TABLE: [u32; 10] = [0, 9, 4, 6, 8, 2, 7, 1, 3, 5];
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .fold(0, |carry, digit| TABLE[((carry + digit) % 10) as usize])
+}
+
+fn validate_qr_reference(reference: &str) -> Result<String, PayloadError> {
+    let reference = remove_spaces(reference);
+    if reference.len() != 27 || !reference.chars().all(|c| c.is_ascii_digit()) {
+        return Err(PayloadError::InvalidField("reference"));
+    }
+    if get_mod10_carry(&reference) != 0 {
+        return Err(PayloadError::InvalidChecksum("reference"));
+    }
+    Ok(reference)
+}
+
+// QR-IBANs have an institution id between 30000 and 31999
+fn is_qr_iban(iban: &str) -> bool {
+    iban.get(4..9)
+        .and_then(|id| id.parse::<u32>().ok())
+        .is_some_and(|id| (30000..=31999).contains(&id))
+}
+
+/// A rectangle in millimeters, measured from the top left of the qr code
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rectangle {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub dark: bool,
+}
+
+impl Rectangle {
+    #[cfg(feature = "image")]
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// The payment part of a Swiss QR-bill, in the SPC format version 2.0.
+/// Use `build` to get the text to encode and `render`, `to_svg` or `to_pdf`
+/// to draw it at its required size with the Swiss cross in the middle.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SwissQrBill {
+    /// A Swiss or Liechtenstein IBAN or QR-IBAN
+    pub iban: String,
+    pub creditor: SwissAddress,
+    /// The amount in cents, or None to let the payer fill it in
+    pub amount: Option<u64>,
+    pub currency: Currency,
+    pub debtor: Option<SwissAddress>,
+    pub reference: Option<SwissReference>,
+    /// A message for the payer
+    pub message: String,
+    /// Structured billing information such as the Swico S1 format
+    pub billing_information: String,
+}
+
+impl SwissQrBill {
+    /// The standard requires error correction level Medium
+    pub const ERROR_CORRECTION: ErrorCorrection = ErrorCorrection::Medium;
+    /// Width and height of the qr code, not including the quiet zone
+    pub const SIZE_MM: f64 = 46.0;
+    /// Width of the light margin around the qr code
+    pub const QUIET_ZONE_MM: f64 = 5.0;
+    /// Width and height of the Swiss cross, including its light border
+    pub const CROSS_SIZE_MM: f64 = 7.0;
+
+    pub fn new(iban: &str, creditor: SwissAddress, currency: Currency) -> Self {
+        SwissQrBill {
+            iban: iban.to_string(),
+            creditor,
+            amount: None,
+            currency,
+            debtor: None,
+            reference: None,
+            message: String::new(),
+            billing_information: String::new(),
+        }
+    }
+
+    /// Set the amount in cents (rappen for francs)
+    pub fn amount(mut self, cents: u64) -> Self {
+        self.amount = Some(cents);
+        self
+    }
+
+    pub fn debtor(mut self, debtor: SwissAddress) -> Self {
+        self.debtor = Some(debtor);
+        self
+    }
+
+    pub fn reference(mut self, reference: SwissReference) -> Self {
+        self.reference = Some(reference);
+        self
+    }
+
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = message.to_string();
+        self
+    }
+
+    pub fn billing_information(mut self, billing_information: &str) -> Self {
+        self.billing_information = billing_information.to_string();
+        self
+    }
+
+    /// Validate the fields and build the payload
+    pub fn build(&self) -> Result<String, PayloadError> {
+        let iban = validate_iban(&self.iban)?;
+        if !iban.starts_with("CH") && !iban.starts_with("LI") {
+            return Err(PayloadError::InvalidField("iban"));
+        }
+
+        // QR-IBANs must be paired with QR references, and other IBANs can't be
+        let qr_iban = is_qr_iban(&iban);
+        let (reference_type, reference) = match &self.reference {
+            Some(SwissReference::Qr(reference)) if qr_iban => {
+                ("QRR", validate_qr_reference(reference)?)
+            }
+            Some(SwissReference::Creditor(reference)) if !qr_iban => {
+                ("SCOR", validate_creditor_reference(reference)?)
+            }
+            None if !qr_iban => ("NON", String::new()),
+            _ => return Err(PayloadError::InvalidField("reference")),
+        };
+
+        let amount = match self.amount {
+            Some(cents) if (1..=MAX_AMOUNT_CENTS).contains(&cents) => {
+                format!("{}.{:02}", cents / 100, cents % 100)
+            }
+            Some(_) => return Err(PayloadError::InvalidField("amount")),
+            None => String::new(),
+        };
+        let currency = match self.currency {
+            Currency::Chf => "CHF",
+            Currency::Eur => "EUR",
+        };
+
+        check_text(&self.message, 140, "message")?;
+        check_text(&self.billing_information, 140, "billing information")?;
+        if self.message.chars().count() + self.billing_information.chars().count() > 140 {
+            return Err(PayloadError::TooLong("message"));
+        }
+
+        let mut fields = vec!["SPC".to_string(), "0200".to_string(), "1".to_string(), iban];
+        fields.extend(self.creditor.fields()?);
+        // The ultimate creditor is reserved for future use and left empty
+        fields.extend(std::iter::repeat_n(String::new(), 7));
+        fields.push(amount);
+        fields.push(currency.to_string());
+        match &self.debtor {
+            Some(debtor) => fields.extend(debtor.fields()?),
+            None => fields.extend(std::iter::repeat_n(String::new(), 7)),
+        }
+        fields.push(reference_type.to_string());
+        fields.push(reference);
+        fields.push(self.message.clone());
+        fields.push("EPD".to_string());
+        if !self.billing_information.is_empty() {
+            fields.push(self.billing_information.clone());
+        }
+
+        let payload = fields.join("\r\n");
+        if payload.chars().count() > MAX_PAYLOAD_LENGTH {
+            return Err(PayloadError::TooLong("payload"));
+        }
+        Ok(payload)
+    }
+
+    /// Get the Swiss cross that goes over the middle of the qr code: a dark
+    /// square with a light cross, inside a light border. Vector renderers
+    /// can draw these rectangles on top of the modules.
+    pub fn get_cross_rectangles() -> [Rectangle; 4] {
+        // The cross has the proportions of the Swiss flag, where each arm is
+        // 6 units wide and 7 long and the square is 32 units across
+        let border = Self::CROSS_SIZE_MM / 14.0;
+        let square = Self::CROSS_SIZE_MM - border * 2.0;
+        let unit = square / 32.0;
+        let center = Self::SIZE_MM / 2.0;
+        let start = center - Self::CROSS_SIZE_MM / 2.0;
+        [
+            Rectangle {
+                x: start,
+                y: start,
+                width: Self::CROSS_SIZE_MM,
+                height: Self::CROSS_SIZE_MM,
+                dark: false,
+            },
+            Rectangle {
+                x: start + border,
+                y: start + border,
+                width: square,
+                height: square,
+                dark: true,
+            },
+            Rectangle {
+                x: center - unit * 10.0,
+                y: center - unit * 3.0,
+                width: unit * 20.0,
+                height: unit * 6.0,
+                dark: false,
+            },
+            Rectangle {
+                x: center - unit * 3.0,
+                y: center - unit * 10.0,
+                width: unit * 6.0,
+                height: unit * 20.0,
+                dark: false,
+            },
+        ]
+    }
+
+    fn create(&self) -> Result<crate::drawer::QR, PayloadError> {
+        crate::drawer::QR::create(&self.build()?, Self::ERROR_CORRECTION)
+            .map_err(PayloadError::Encoding)
+    }
+
+    // Only the symbol is drawn, and the quiet zone is left to the caller
+    fn get_symbol_options() -> crate::Options {
+        crate::Options {
+            quiet_zone: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Draw the qr code at 46 x 46 mm with its quiet zone and the Swiss cross,
+    /// at the given print resolution
+    #[cfg(feature = "image")]
+    pub fn render(&self, dots_per_inch: u32) -> Result<image::RgbImage, PayloadError> {
+        let qr = self.create()?;
+        let cross = Self::get_cross_rectangles();
+
+        let pixel_size = MILLIMETERS_PER_INCH / dots_per_inch as f64;
+        let image_size = (Self::SIZE_MM + Self::QUIET_ZONE_MM * 2.0) / pixel_size;
+        let image_size = image_size.round() as u32;
+        let module_size = Self::SIZE_MM / qr.size as f64;
+
+        let image = image::ImageBuffer::from_fn(image_size, image_size, |x, y| {
+            // Sample the middle of each pixel
+            let x = (x as f64 + 0.5) * pixel_size - Self::QUIET_ZONE_MM;
+            let y = (y as f64 + 0.5) * pixel_size - Self::QUIET_ZONE_MM;
+            let color = if let Some(rectangle) = cross.iter().rev().find(|r| r.contains(x, y)) {
+                if rectangle.dark {
+                    0
+                } else {
+                    255
+                }
+            } else if x >= 0.0 && y >= 0.0 && x < Self::SIZE_MM && y < Self::SIZE_MM {
+                let column = (x / module_size) as usize;
+                let row = (y / module_size) as usize;
+                qr.matrix[row * qr.size + column]
+            } else {
+                255
+            };
+            image::Rgb([color, color, color])
+        });
+        Ok(image)
+    }
+
+    /// Draw the qr code at 46 x 46 mm as an svg image, with its quiet zone
+    /// and the Swiss cross
+    pub fn to_svg(&self) -> Result<String, PayloadError> {
+        let qr = self.create()?;
+        let size = Self::SIZE_MM + Self::QUIET_ZONE_MM * 2.0;
+        let module_size = Self::SIZE_MM / qr.size as f64;
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}mm\" height=\"{size}mm\" \
+             viewBox=\"0 0 {size} {size}\">\n\
+             <rect width=\"{size}\" height=\"{size}\" fill=\"#ffffff\"/>\n\
+             <path transform=\"translate({} {}) scale({})\" d=\"{}\" fill=\"#000000\"/>\n",
+            Self::QUIET_ZONE_MM,
+            Self::QUIET_ZONE_MM,
+            module_size,
+            crate::render::get_svg_path(&qr, &Self::get_symbol_options()),
+        );
+        for rectangle in Self::get_cross_rectangles() {
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                rectangle.x + Self::QUIET_ZONE_MM,
+                rectangle.y + Self::QUIET_ZONE_MM,
+                rectangle.width,
+                rectangle.height,
+                if rectangle.dark { "#000000" } else { "#ffffff" }
+            );
+        }
+        Ok(svg + "</svg>\n")
+    }
+
+    /// Draw the qr code at 46 x 46 mm as a single page pdf, with its
+    /// quiet zone and the Swiss cross
+    pub fn to_pdf(&self) -> Result<Vec<u8>, PayloadError> {
+        let qr = self.create()?;
+        let size = Self::SIZE_MM + Self::QUIET_ZONE_MM * 2.0;
+        let module_size = Self::SIZE_MM / qr.size as f64;
+
+        // Lengths are in millimeters, with the origin at the bottom left of the page
+        let mut content = format!("{0} 0 0 {0} 0 0 cm\n", POINTS_PER_MILLIMETER);
+        content += &format!("1 g\n0 0 {size} {size} re f\n");
+        content += &format!(
+            "q\n{0} 0 0 {0} {1} {1} cm\n",
+            module_size,
+            Self::QUIET_ZONE_MM
+        );
+        content += &crate::render::get_pdf_commands(&qr, &Self::get_symbol_options());
+        content += "Q\n";
+        for rectangle in Self::get_cross_rectangles() {
+            content += &format!(
+                "{} g\n{} {} {} {} re f\n",
+                if rectangle.dark { 0 } else { 1 },
+                rectangle.x + Self::QUIET_ZONE_MM,
+                size - Self::QUIET_ZONE_MM - rectangle.y - rectangle.height,
+                rectangle.width,
+                rectangle.height
+            );
+        }
+
+        let points = size * POINTS_PER_MILLIMETER;
+        Ok(crate::render::write_pdf(&[(points, points, content)]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    fn get_creditor() -> SwissAddress {
+        SwissAddress {
+            name: "Robert Schneider AG".to_string(),
+            street: "Rue du Lac".to_string(),
+            house_number: "1268".to_string(),
+            postal_code: "2501".to_string(),
+            town: "Biel".to_string(),
+            country: "CH".to_string(),
+        }
+    }
+
+    fn get_debtor() -> SwissAddress {
+        SwissAddress {
+            name: "Pia-Maria Rutschmann-Schnyder".to_string(),
+            street: "Grosse Marktgasse".to_string(),
+            house_number: "28".to_string(),
+            postal_code: "9400".to_string(),
+            town: "Rorschach".to_string(),
+            country: "CH".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_qr_reference() {
+        assert_eq!(
+            SwissReference::qr("21 00000 00003 13947 14300 09017").unwrap_err(),
+            PayloadError::InvalidField("reference")
+        );
+        assert_eq!(
+            SwissReference::qr("21000000000313947143000901").unwrap(),
+            SwissReference::Qr("210000000003139471430009017".to_string())
+        );
+        assert_eq!(
+            SwissReference::qr("12345").unwrap(),
+            SwissReference::Qr("000000000000000000000123457".to_string())
+        );
+    }
+
+    #[test]
+    fn test_building() {
+        // The example from the implementation guidelines
+        let bill = SwissQrBill::new("CH44 3199 9123 0008 8901 2", get_creditor(), Currency::Chf)
+            .amount(194975)
+            .debtor(get_debtor())
+            .reference(SwissReference::Qr(
+                "21 00000 00003 13947 14300 09017".to_string(),
+            ))
+            .message("Order of 15 June 2020")
+            .billing_information("//S1/10/10201409/11/200701/20/140.000-53/30/102673831/31/200615/32/7.7/33/7.7:3.57/40/0:30");
+        let expected = [
+            "SPC",
+            "0200",
+            "1",
+            "CH4431999123000889012",
+            "S",
+            "Robert Schneider AG",
+            "Rue du Lac",
+            "1268",
+            "2501",
+            "Biel",
+            "CH",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "1949.75",
+            "CHF",
+            "S",
+            "Pia-Maria Rutschmann-Schnyder",
+            "Grosse Marktgasse",
+            "28",
+            "9400",
+            "Rorschach",
+            "CH",
+            "QRR",
+            "210000000003139471430009017",
+            "Order of 15 June 2020",
+            "EPD",
+            "//S1/10/10201409/11/200701/20/140.000-53/30/102673831/31/200615/32/7.7/33/7.7:3.57/40/0:30",
+        ];
+        assert_eq!(bill.build().unwrap(), expected.join("\r\n"));
+
+        // Without a reference, amount or debtor
+        let bill = SwissQrBill::new("CH93 0076 2011 6238 5295 7", get_creditor(), Currency::Eur);
+        let payload = bill.build().unwrap();
+        let lines: Vec<&str> = payload.split("\r\n").collect();
+        assert_eq!(lines.len(), 31);
+        assert_eq!(lines[18..20], ["", "EUR"]);
+        assert_eq!(lines[27..], ["NON", "", "", "EPD"]);
+    }
+
+    #[test]
+    fn test_validation() {
+        let qr_iban = "CH4431999123000889012";
+        let iban = "CH9300762011623852957";
+        let creditor = get_creditor();
+
+        // QR-IBANs need QR references and other IBANs can't have them
+        let bill = SwissQrBill::new(qr_iban, creditor.clone(), Currency::Chf);
+        assert_eq!(bill.build(), Err(PayloadError::InvalidField("reference")));
+        let bill = SwissQrBill::new(iban, creditor.clone(), Currency::Chf)
+            .reference(SwissReference::qr("1234").unwrap());
+        assert_eq!(bill.build(), Err(PayloadError::InvalidField("reference")));
+        let bill = SwissQrBill::new(iban, creditor.clone(), Currency::Chf)
+            .reference(SwissReference::Creditor("RF18539007547034".to_string()));
+        assert!(bill.build().is_ok());
+
+        let bill = SwissQrBill::new(qr_iban, creditor.clone(), Currency::Chf).reference(
+            SwissReference::Qr("210000000003139471430009018".to_string()),
+        );
+        assert_eq!(
+            bill.build(),
+            Err(PayloadError::InvalidChecksum("reference"))
+        );
+
+        let bill = SwissQrBill::new("DE89370400440532013000", creditor.clone(), Currency::Eur);
+        assert_eq!(bill.build(), Err(PayloadError::InvalidField("iban")));
+
+        let bill = SwissQrBill::new(iban, creditor.clone(), Currency::Chf).amount(0);
+        assert_eq!(bill.build(), Err(PayloadError::InvalidField("amount")));
+
+        let bill = SwissQrBill::new(iban, creditor.clone(), Currency::Chf)
+            .message(&"m".repeat(100))
+            .billing_information(&"b".repeat(41));
+        assert_eq!(bill.build(), Err(PayloadError::TooLong("message")));
+
+        let mut address = creditor.clone();
+        address.town.clear();
+        let bill = SwissQrBill::new(iban, address, Currency::Chf);
+        assert_eq!(bill.build(), Err(PayloadError::MissingField("town")));
+
+        let mut address = creditor;
+        address.country = "CHE".to_string();
+        let bill = SwissQrBill::new(iban, address, Currency::Chf);
+        assert_eq!(bill.build(), Err(PayloadError::InvalidField("country")));
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_rendering() {
+        let bill = SwissQrBill::new("CH4431999123000889012", get_creditor(), Currency::Chf)
+            .amount(5000)
+            .reference(SwissReference::qr("3139471430009017").unwrap());
+
+        // 56 mm including the quiet zone is 661 pixels at 300 dpi
+        let image = bill.render(300).unwrap();
+        assert_eq!(image.dimensions(), (661, 661));
+
+        // The middle of the cross is light, with the dark square around it
+        let center = 661 / 2;
+        assert_eq!(image.get_pixel(center, center)[0], 255);
+        assert_eq!(image.get_pixel(center - 30, center - 30)[0], 0);
+
+        // The cross covers some modules, which error correction makes up for
+        let decoded = crate::decode_image(&image.into());
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].text, bill.build().unwrap());
+        assert_eq!(decoded[0].level, crate::ErrorCorrection::Medium);
+    }
+
+    #[test]
+    fn test_vector_rendering() {
+        let bill = SwissQrBill::new("CH4431999123000889012", get_creditor(), Currency::Chf)
+            .amount(5000)
+            .reference(SwissReference::qr("3139471430009017").unwrap());
+
+        let svg = bill.to_svg().unwrap();
+        assert!(svg.contains("width=\"56mm\" height=\"56mm\" viewBox=\"0 0 56 56\""));
+        assert!(
+            svg.contains("<rect x=\"24.5\" y=\"24.5\" width=\"7\" height=\"7\" fill=\"#ffffff\"/>")
+        );
+        assert_eq!(svg.matches("<rect").count(), 5);
+
+        // 56 mm is 158.74 points
+        let pdf = String::from_utf8(bill.to_pdf().unwrap()).unwrap();
+        assert!(pdf.contains("/MediaBox [0 0 158.74015748031496 158.74015748031496]"));
+        assert!(pdf.contains("1 g\n24.5 24.5 7 7 re f\n"));
+    }
+}
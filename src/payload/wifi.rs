@@ -0,0 +1,222 @@
+use super::{escape, split_escaped, unescape, PayloadError};
+
+const SPECIAL_CHARS: [char; 5] = ['\\', ';', ',', ':', '"'];
+
+/// How a wifi network is secured
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Security {
+    Wpa,
+    Wpa2,
+    /// Written as SAE, which is how Android expects WPA3 networks
+    Wpa3,
+    Wep,
+    /// An open network, with no password
+    None,
+}
+
+impl Security {
+    fn name(&self) -> &'static str {
+        match self {
+            Security::Wpa => "WPA",
+            Security::Wpa2 => "WPA2",
+            Security::Wpa3 => "SAE",
+            Security::Wep => "WEP",
+            Security::None => "nopass",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "WPA" => Some(Security::Wpa),
+            "WPA2" => Some(Security::Wpa2),
+            "WPA3" | "SAE" => Some(Security::Wpa3),
+            "WEP" => Some(Security::Wep),
+            "NOPASS" | "" => Some(Security::None),
+            _ => None,
+        }
+    }
+}
+
+/// Credentials for joining a wifi network, in the `WIFI:T:WPA;S:name;P:password;;`
+/// format that phone cameras understand. Use `to_string` to get the text to encode.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Wifi {
+    pub ssid: String,
+    pub password: String,
+    pub security: Security,
+    pub hidden: bool,
+}
+
+impl Wifi {
+    /// An open network with the given name
+    pub fn new(ssid: &str) -> Self {
+        Wifi {
+            ssid: ssid.to_string(),
+            password: String::new(),
+            security: Security::None,
+            hidden: false,
+        }
+    }
+
+    /// Set the password, switching an open network to WPA2
+    pub fn password(mut self, password: &str) -> Self {
+        self.password = password.to_string();
+        if self.security == Security::None {
+            self.security = Security::Wpa2;
+        }
+        self
+    }
+
+    pub fn security(mut self, security: Security) -> Self {
+        self.security = security;
+        self
+    }
+
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Read the network credentials back out of a scanned qr code
+    pub fn parse(text: &str) -> Result<Self, PayloadError> {
+        let fields = text
+            .strip_prefix("WIFI:")
+            .ok_or(PayloadError::InvalidPrefix)?;
+
+        let mut wifi = Wifi::new("");
+        let mut ssid = None;
+        for field in split_escaped(fields, ';') {
+            if field.is_empty() {
+                continue;
+            }
+            let (key, value) = field
+                .split_once(':')
+                .ok_or(PayloadError::InvalidField("wifi"))?;
+            let value = unescape(strip_quotes(value));
+            match key {
+                "T" => {
+                    wifi.security = Security::from_name(&value)
+                        .ok_or(PayloadError::InvalidField("security"))?;
+                }
+                "S" => ssid = Some(value),
+                "P" => wifi.password = value,
+                "H" => wifi.hidden = value.eq_ignore_ascii_case("true"),
+                // Ignore fields from extensions like WPA2-Enterprise
+                _ => {}
+            }
+        }
+
+        wifi.ssid = ssid.ok_or(PayloadError::MissingField("ssid"))?;
+        Ok(wifi)
+    }
+}
+
+// Some generators quote names that would otherwise look like hexadecimal
+fn strip_quotes(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .filter(|v| !v.ends_with('\\'))
+        .unwrap_or(value)
+}
+
+impl std::fmt::Display for Wifi {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "WIFI:T:{};", self.security.name())?;
+        write!(f, "S:{};", escape(&self.ssid, &SPECIAL_CHARS))?;
+        if self.security != Security::None {
+            write!(f, "P:{};", escape(&self.password, &SPECIAL_CHARS))?;
+        }
+        if self.hidden {
+            write!(f, "H:true;")?;
+        }
+        write!(f, ";")
+    }
+}
+
+impl std::str::FromStr for Wifi {
+    type Err = PayloadError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Wifi::parse(text)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::payload::*;
+
+    #[test]
+    fn test_building() {
+        let wifi = Wifi::new("Office Guest").password("welcome1");
+        assert_eq!(wifi.to_string(), "WIFI:T:WPA2;S:Office Guest;P:welcome1;;");
+
+        let wifi = Wifi::new("cafe")
+            .security(Security::Wpa3)
+            .password("p")
+            .hidden(true);
+        assert_eq!(wifi.to_string(), "WIFI:T:SAE;S:cafe;P:p;H:true;;");
+
+        // Open networks leave out the password
+        assert_eq!(Wifi::new("open").to_string(), "WIFI:T:nopass;S:open;;");
+
+        let wifi = Wifi::new(r#"a;b,c:d\e"f"#).password("x;y");
+        assert_eq!(
+            wifi.to_string(),
+            r#"WIFI:T:WPA2;S:a\;b\,c\:d\\e\"f;P:x\;y;;"#
+        );
+    }
+
+    #[test]
+    fn test_parsing() {
+        let wifi = Wifi::parse("WIFI:S:home;T:WPA;P:secret;H:true;;").unwrap();
+        assert_eq!(
+            wifi,
+            Wifi::new("home")
+                .security(Security::Wpa)
+                .password("secret")
+                .hidden(true)
+        );
+
+        let wifi = Wifi::parse(r#"WIFI:T:WEP;S:"012345";P:ab\;cd;;"#).unwrap();
+        assert_eq!(wifi.ssid, "012345");
+        assert_eq!(wifi.password, "ab;cd");
+        assert_eq!(wifi.security, Security::Wep);
+
+        assert_eq!(Wifi::parse("WIFI:S:open;;").unwrap(), Wifi::new("open"));
+        assert_eq!(Wifi::parse("wifi"), Err(PayloadError::InvalidPrefix));
+        assert_eq!(
+            Wifi::parse("WIFI:T:WPA;P:x;;"),
+            Err(PayloadError::MissingField("ssid"))
+        );
+        assert_eq!(
+            Wifi::parse("WIFI:T:WPA4;S:x;;"),
+            Err(PayloadError::InvalidField("security"))
+        );
+
+        // Everything that can be built can be read back
+        let special = r#"\;,:" "#;
+        let quoted = r#""quoted""#;
+        for security in [Security::Wpa, Security::Wpa2, Security::Wpa3, Security::Wep] {
+            let wifi = Wifi::new(special).password(special).security(security);
+            assert_eq!(wifi.to_string().parse(), Ok(wifi));
+
+            let wifi = Wifi::new(quoted).password(quoted).security(security);
+            assert_eq!(wifi.to_string().parse(), Ok(wifi));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_encoding() {
+        let wifi = Wifi::new("Guest; 5GHz").password("pa:ss");
+        let image = crate::render_qr_code(
+            &wifi.to_string(),
+            crate::ErrorCorrection::Medium,
+            &crate::Options::default(),
+        )
+        .unwrap();
+        let decoded = crate::decode_image(&image.into());
+        assert_eq!(Wifi::parse(&decoded[0].text), Ok(wifi));
+    }
+}
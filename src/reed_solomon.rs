@@ -0,0 +1,268 @@
+use crate::gf256::{self, divide, evaluate, inverse, multiply};
+
+// Polynomials here are stored with the lowest degree coefficient first
+
+// Evaluate the codeword at each of the generator polynomial's roots.
+// The codeword's first byte is its highest degree coefficient.
+fn compute_syndromes(codeword: &[u8], ecc_count: usize) -> Vec<u8> {
+    (0..ecc_count)
+        .map(|i| {
+            let root = gf256::exp(i);
+            codeword
+                .iter()
+                .fold(0, |result, &byte| multiply(result, root) ^ byte)
+        })
+        .collect()
+}
+
+// Use the Berlekamp-Massey algorithm to find the shortest polynomial
+// whose roots are the inverses of the error locations
+fn find_error_locator(syndromes: &[u8]) -> Vec<u8> {
+    let mut locator = vec![1];
+    let mut previous = vec![1];
+    let mut length = 0;
+    let mut shift = 1;
+    let mut previous_discrepancy = 1;
+
+    for n in 0..syndromes.len() {
+        let mut discrepancy = syndromes[n];
+        for i in 1..=length.min(n) {
+            discrepancy ^= multiply(locator.get(i).copied().unwrap_or(0), syndromes[n - i]);
+        }
+
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        // locator = locator - (discrepancy / previous_discrepancy) * x ^ shift * previous
+        let scale = divide(discrepancy, previous_discrepancy);
+        let mut updated = locator.clone();
+        updated.resize(std::cmp::max(locator.len(), previous.len() + shift), 0);
+        for (i, &coefficient) in previous.iter().enumerate() {
+            updated[i + shift] ^= multiply(scale, coefficient);
+        }
+
+        if 2 * length <= n {
+            previous = locator;
+            length = n + 1 - length;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+        locator = updated;
+    }
+
+    locator.truncate(length + 1);
+    locator
+}
+
+// Get the codeword position that corresponds to an error location x ^ power
+fn get_location(codeword_length: usize, position: usize) -> u8 {
+    gf256::exp(codeword_length - 1 - position)
+}
+
+/// Repair a codeword (data codewords followed by error correction codewords)
+/// in place. Erasures are positions that are already known to be wrong.
+/// Up to 2 * errors + erasures <= ecc_count can be corrected.
+/// Returns the number of bytes that were corrected, or None if the
+/// codeword is damaged beyond repair, in which case it's left untouched.
+pub fn correct_errors(codeword: &mut [u8], ecc_count: usize, erasures: &[usize]) -> Option<usize> {
+    if erasures.len() > ecc_count || erasures.iter().any(|&p| p >= codeword.len()) {
+        return None;
+    }
+
+    let syndromes = compute_syndromes(codeword, ecc_count);
+    if syndromes.iter().all(|&s| s == 0) {
+        return Some(0);
+    }
+
+    // Build the erasure locator, whose roots are the inverses of the erased locations
+    let mut erasure_locator = vec![1];
+    for &position in erasures {
+        erasure_locator = gf256::multiply_polynomials(
+            &erasure_locator,
+            &[1, get_location(codeword.len(), position)],
+        );
+    }
+
+    // Remove the erasures' contribution from the syndromes (Forney syndromes)
+    // so that Berlekamp-Massey only has to find the unknown errors
+    let mut forney_syndromes = gf256::multiply_polynomials(&syndromes, &erasure_locator);
+    forney_syndromes.truncate(ecc_count);
+    let error_locator = find_error_locator(&forney_syndromes[erasures.len()..]);
+    let num_errors = error_locator.len() - 1;
+    if 2 * num_errors + erasures.len() > ecc_count {
+        return None;
+    }
+
+    // Use the Chien search to find the positions of all the errata
+    let locator = gf256::multiply_polynomials(&error_locator, &erasure_locator);
+    let positions: Vec<usize> = (0..codeword.len())
+        .filter(|&p| evaluate(&locator, inverse(get_location(codeword.len(), p))) == 0)
+        .collect();
+    if positions.len() != locator.len() - 1 {
+        return None;
+    }
+
+    // Use the Forney algorithm to find the error magnitudes
+    let mut evaluator = gf256::multiply_polynomials(&syndromes, &locator);
+    evaluator.truncate(ecc_count);
+    let derivative: Vec<u8> = locator
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| if i % 2 == 1 { c } else { 0 })
+        .collect();
+
+    let mut repaired = codeword.to_vec();
+    let mut corrected = 0;
+    for &position in &positions {
+        let location = get_location(codeword.len(), position);
+        let inverse = inverse(location);
+        let denominator = evaluate(&derivative, inverse);
+        if denominator == 0 {
+            return None;
+        }
+        let magnitude = multiply(location, divide(evaluate(&evaluator, inverse), denominator));
+        repaired[position] ^= magnitude;
+        corrected += usize::from(magnitude != 0);
+    }
+
+    // Make sure the repaired codeword is actually valid
+    if compute_syndromes(&repaired, ecc_count)
+        .iter()
+        .any(|&s| s != 0)
+    {
+        return None;
+    }
+    codeword.copy_from_slice(&repaired);
+    Some(corrected)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::encoder::*;
+    use crate::reed_solomon::*;
+    use crate::tables;
+
+    // Small deterministic pseudo random number generator so the tests are repeatable
+    struct Random(u32);
+
+    impl Random {
+        fn next(&mut self, max: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 as usize % max
+        }
+
+        fn positions(&mut self, count: usize, max: usize) -> Vec<usize> {
+            let mut positions = Vec::new();
+            while positions.len() < count {
+                let p = self.next(max);
+                if !positions.contains(&p) {
+                    positions.push(p);
+                }
+            }
+            positions
+        }
+    }
+
+    fn create_codeword(
+        random: &mut Random,
+        data_count: usize,
+        version: usize,
+        level: ErrorCorrection,
+    ) -> Vec<u8> {
+        let mut codeword: Vec<u8> = (0..data_count).map(|_| random.next(256) as u8).collect();
+        let ecc = generate_error_correction_codes(&codeword, level, version);
+        codeword.extend(ecc);
+        codeword
+    }
+
+    #[test]
+    fn test_error_correction() {
+        let mut random = Random(0x12345678);
+        let levels = [
+            ErrorCorrection::Low,
+            ErrorCorrection::Medium,
+            ErrorCorrection::Quartile,
+            ErrorCorrection::High,
+        ];
+
+        // Test every block configuration
+        for version in 1..=40 {
+            for level in levels {
+                let info = tables::ECC_DATA[version - 1][level as usize];
+                let ecc_count = info[0] as usize;
+                for data_count in [info[2], info[4]] {
+                    if data_count == 0 {
+                        continue;
+                    }
+
+                    let original =
+                        create_codeword(&mut random, data_count as usize, version, level);
+                    let length = original.len();
+                    let mut codeword = original.clone();
+                    assert_eq!(correct_errors(&mut codeword, ecc_count, &[]), Some(0));
+
+                    // Corrupt as many bytes as can be corrected
+                    let max_errors = ecc_count / 2;
+                    for p in random.positions(max_errors, length) {
+                        codeword[p] ^= 1 + random.next(255) as u8;
+                    }
+                    assert_eq!(
+                        correct_errors(&mut codeword, ecc_count, &[]),
+                        Some(max_errors)
+                    );
+                    assert_eq!(codeword, original);
+
+                    // Erase as many bytes as can be corrected
+                    let erasures = random.positions(ecc_count, length);
+                    for &p in &erasures {
+                        codeword[p] = 0;
+                    }
+                    let expected = erasures.iter().filter(|&&p| original[p] != 0).count();
+                    assert_eq!(
+                        correct_errors(&mut codeword, ecc_count, &erasures),
+                        Some(expected)
+                    );
+                    assert_eq!(codeword, original);
+
+                    // Mix errors and erasures
+                    let num_errors = ecc_count / 4;
+                    let num_erasures = ecc_count - 2 * num_errors;
+                    let positions = random.positions(num_errors + num_erasures, length);
+                    for &p in &positions {
+                        codeword[p] ^= 1 + random.next(255) as u8;
+                    }
+                    let corrected =
+                        correct_errors(&mut codeword, ecc_count, &positions[..num_erasures]);
+                    assert_eq!(corrected, Some(positions.len()));
+                    assert_eq!(codeword, original);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_uncorrectable() {
+        let mut random = Random(0xCAFEBABE);
+        let original = create_codeword(&mut random, 16, 1, ErrorCorrection::Medium);
+
+        // 10 error correction codewords can't fix 6 errors
+        let mut codeword = original.clone();
+        for p in [0, 3, 5, 9, 14, 20] {
+            codeword[p] ^= 0xFF;
+        }
+        assert_eq!(correct_errors(&mut codeword, 10, &[]), None);
+        assert_ne!(codeword, original);
+
+        // Or more erasures than there are error correction codewords
+        let mut codeword = original.clone();
+        let erasures: Vec<usize> = (0..11).collect();
+        assert_eq!(correct_errors(&mut codeword, 10, &erasures), None);
+    }
+}
@@ -0,0 +1,594 @@
+use crate::drawer::QR;
+use crate::font;
+use crate::{Alignment, Caption, Options};
+
+// Check whether a module is dark, where the coordinates include the quiet
+// zone. The quiet zone gets inverted along with the rest of the symbol.
+fn is_dark(qr: &QR, quiet_zone: usize, x: usize, y: usize) -> bool {
+    let inside = |i: usize| (quiet_zone..quiet_zone + qr.size).contains(&i);
+    if inside(x) && inside(y) {
+        qr.matrix[(y - quiet_zone) * qr.size + x - quiet_zone] == 0
+    } else {
+        qr.inverted
+    }
+}
+
+// Get the horizontal runs of dark modules in a row as (start, length) pairs
+fn get_dark_runs(qr: &QR, quiet_zone: usize, y: usize) -> Vec<(usize, usize)> {
+    let size = qr.size + quiet_zone * 2;
+    let mut runs = Vec::new();
+    let mut x = 0;
+    while x < size {
+        if !is_dark(qr, quiet_zone, x, y) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < size && is_dark(qr, quiet_zone, x, y) {
+            x += 1;
+        }
+        runs.push((start, x - start));
+    }
+    runs
+}
+
+// Where the caption goes in the strip below the quiet zone, in modules from the top left
+struct CaptionLayout {
+    text: String,
+    x: f64,
+    y: f64,
+    /// Width of each pixel of the font
+    pixel_size: f64,
+    /// Height of the strip, which leaves a pixel of space above and below the letters
+    height: f64,
+}
+
+// Get the caption to draw, leaving out ones without text or with a size that isn't positive
+fn get_caption(options: &Options) -> Option<&Caption> {
+    options.caption.as_ref().filter(|caption| {
+        !caption.text.is_empty() && caption.size > 0.0 && caption.size.is_finite()
+    })
+}
+
+// Fit the caption under the symbol, so that it doesn't reach into the quiet zone on either side
+fn get_caption_layout(qr: &QR, options: &Options, pixel_size: f64) -> Option<CaptionLayout> {
+    let caption = get_caption(options)?;
+    let quiet_zone = options.quiet_zone as f64;
+    let symbol = qr.size as f64;
+    let text = font::truncate(&caption.text, (symbol / pixel_size) as usize);
+    let width = font::get_text_width(&text) as f64 * pixel_size;
+    let x = match caption.alignment {
+        Alignment::Left => quiet_zone,
+        Alignment::Center => quiet_zone + (symbol - width) / 2.0,
+        Alignment::Right => quiet_zone + symbol - width,
+    };
+
+    // A mirrored code is read from the back, so the caption is mirrored with it
+    let x = if qr.mirrored {
+        symbol + quiet_zone * 2.0 - x - width
+    } else {
+        x
+    };
+    Some(CaptionLayout {
+        text,
+        x,
+        y: symbol + quiet_zone * 2.0 + pixel_size,
+        pixel_size,
+        height: pixel_size * (font::GLYPH_HEIGHT + 2) as f64,
+    })
+}
+
+impl CaptionLayout {
+    // Get the runs of pixels in the text, flipped when the code is mirrored
+    fn get_runs(&self, mirrored: bool) -> Vec<(usize, usize, usize)> {
+        let width = font::get_text_width(&self.text);
+        let runs = font::get_text_runs(&self.text).into_iter();
+        if mirrored {
+            runs.map(|(x, y, length)| (width - x - length, y, length))
+                .collect()
+        } else {
+            runs.collect()
+        }
+    }
+}
+
+// Get the colors of the caption strip and its text, where the strip matches the quiet zone
+fn get_caption_colors(qr: &QR, options: &Options) -> ([u8; 3], [u8; 3]) {
+    if qr.inverted {
+        (options.foreground, options.background)
+    } else {
+        (options.background, options.foreground)
+    }
+}
+
+#[cfg(feature = "image")]
+pub fn to_image(qr: &QR, options: &Options) -> image::RgbImage {
+    let quiet_zone = options.quiet_zone as usize;
+    let scale = options.scale;
+    let img_size = (qr.size as u32 + options.quiet_zone * 2) * scale;
+
+    // Font pixels have to cover a whole number of image pixels to stay sharp
+    let pixel_size = options.caption.as_ref().map_or(1, |caption| {
+        let size = caption.size * scale as f64 / font::GLYPH_HEIGHT as f64;
+        size.round().max(1.0) as u32
+    });
+    let caption = get_caption_layout(qr, options, pixel_size as f64 / scale as f64);
+    let caption_height = caption
+        .as_ref()
+        .map_or(0, |caption| (caption.height * scale as f64).round() as u32);
+    let (strip, text) = get_caption_colors(qr, options);
+
+    let mut img = image::ImageBuffer::from_fn(img_size, img_size + caption_height, |x, y| {
+        if y >= img_size {
+            return image::Rgb(strip);
+        }
+        let x = (x / scale) as usize;
+        let y = (y / scale) as usize;
+        if is_dark(qr, quiet_zone, x, y) {
+            image::Rgb(options.foreground)
+        } else {
+            image::Rgb(options.background)
+        }
+    });
+
+    if let Some(caption) = caption {
+        let left = (caption.x * scale as f64).round() as u32;
+        let top = img_size + pixel_size;
+        for (x, y, length) in caption.get_runs(qr.mirrored) {
+            let x = left + x as u32 * pixel_size;
+            let y = top + y as u32 * pixel_size;
+            for i in 0..length as u32 * pixel_size {
+                for j in 0..pixel_size {
+                    img.put_pixel(x + i, y + j, image::Rgb(text));
+                }
+            }
+        }
+    }
+    img
+}
+
+pub fn to_hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+/// Get the path data of the dark modules, where each module is one unit wide
+pub fn get_svg_path(qr: &QR, options: &Options) -> String {
+    let quiet_zone = options.quiet_zone as usize;
+    let mut path = String::new();
+    for y in 0..qr.size + quiet_zone * 2 {
+        for (x, length) in get_dark_runs(qr, quiet_zone, y) {
+            path += &format!("M{} {}h{}v1h-{}z", x, y, length, length);
+        }
+    }
+    path
+}
+
+// Get the pixel size of the caption font in modules for the vector formats
+fn get_vector_caption(qr: &QR, options: &Options) -> Option<CaptionLayout> {
+    let caption = options.caption.as_ref()?;
+    get_caption_layout(qr, options, caption.size / font::GLYPH_HEIGHT as f64)
+}
+
+/// Draw the dark modules as a single path in a viewbox where each module is one unit wide
+pub fn to_svg(qr: &QR, options: &Options) -> String {
+    let size = qr.size + options.quiet_zone as usize * 2;
+    let pixels = size * options.scale as usize;
+    let path = get_svg_path(qr, options);
+    let caption = get_vector_caption(qr, options);
+    let caption_height = caption.as_ref().map_or(0.0, |caption| caption.height);
+    let height = size as f64 + caption_height;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{pixels}\" height=\"{}\" \
+         viewBox=\"0 0 {size} {height}\" shape-rendering=\"crispEdges\">\n\
+         <rect width=\"{size}\" height=\"{height}\" fill=\"{}\"/>\n\
+         <path d=\"{path}\" fill=\"{}\"/>\n",
+        height * options.scale as f64,
+        to_hex(options.background),
+        to_hex(options.foreground),
+    );
+    if let Some(caption) = caption {
+        let (strip, text) = get_caption_colors(qr, options);
+        if strip != options.background {
+            svg += &format!(
+                "<rect y=\"{size}\" width=\"{size}\" height=\"{}\" fill=\"{}\"/>\n",
+                caption.height,
+                to_hex(strip)
+            );
+        }
+        svg += &format!(
+            "<path transform=\"translate({} {}) scale({})\" d=\"{}\" fill=\"{}\"/>\n",
+            caption.x,
+            caption.y,
+            caption.pixel_size,
+            font::get_text_path(&caption.get_runs(qr.mirrored)),
+            to_hex(text)
+        );
+    }
+    svg + "</svg>\n"
+}
+
+pub fn to_pdf_color(color: [u8; 3], operator: &str) -> String {
+    let [r, g, b] = color.map(|c| c as f64 / 255.0);
+    format!("{:.3} {:.3} {:.3} {}\n", r, g, b, operator)
+}
+
+/// Get the pdf commands that draw the qr code, where each module is one
+/// unit wide and the origin is at the bottom left of the quiet zone
+pub fn get_pdf_commands(qr: &QR, options: &Options) -> String {
+    let quiet_zone = options.quiet_zone as usize;
+    let size = qr.size + quiet_zone * 2;
+
+    let mut content = to_pdf_color(options.background, "rg");
+    content += &format!("0 0 {} {} re f\n", size, size);
+    content += &to_pdf_color(options.foreground, "rg");
+    for y in 0..size {
+        for (x, length) in get_dark_runs(qr, quiet_zone, y) {
+            content += &format!("{} {} {} 1 re\n", x, size - y - 1, length);
+        }
+    }
+    content + "f\n"
+}
+
+/// Draw the qr code as a single page pdf, where each module is `scale` points wide
+pub fn to_pdf(qr: &QR, options: &Options) -> Vec<u8> {
+    let size = qr.size + options.quiet_zone as usize * 2;
+    let scale = options.scale as f64;
+    let mut content = format!("{} 0 0 {} 0 0 cm\n", scale, scale);
+
+    // The caption strip goes at the bottom of the page and the code is moved up above it
+    let caption = get_vector_caption(qr, options);
+    let caption_height = caption.as_ref().map_or(0.0, |caption| caption.height);
+    if let Some(caption) = caption {
+        let (strip, text) = get_caption_colors(qr, options);
+        content += &to_pdf_color(strip, "rg");
+        content += &format!("0 0 {} {} re f\n", size, caption.height);
+        content += &format!(
+            "q\n{} 0 0 {} {} {} cm\n",
+            caption.pixel_size, caption.pixel_size, caption.x, caption.pixel_size
+        );
+        content += &to_pdf_color(text, "rg");
+        for (x, y, length) in caption.get_runs(qr.mirrored) {
+            let y = font::GLYPH_HEIGHT - y - 1;
+            content += &format!("{} {} {} 1 re\n", x, y, length);
+        }
+        content += "f\nQ\n";
+        content += &format!("1 0 0 1 0 {} cm\n", caption.height);
+    }
+    content += &get_pdf_commands(qr, options);
+
+    let width = size as f64 * scale;
+    let height = (size as f64 + caption_height) * scale;
+    write_pdf(&[(width, height, content)])
+}
+
+/// Write a pdf with a page for each (width, height, content stream), with sizes in points
+pub fn write_pdf(pages: &[(f64, f64, String)]) -> Vec<u8> {
+    // The catalog and page tree come first, then a page and content stream for each page
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", 3 + i * 2))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+    ];
+    for (i, (width, height, content)) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R >>",
+            width,
+            height,
+            4 + i * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).bytes());
+    }
+
+    // The cross reference table gives the byte offset of each object
+    let xref_offset = pdf.len();
+    let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        xref += &format!("{:010} 00000 n \n", offset);
+    }
+    xref += &format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    );
+    pdf.extend(xref.bytes());
+    pdf
+}
+
+/// Draw the qr code with Unicode half blocks, two rows of modules per line
+pub fn to_text(qr: &QR, options: &Options) -> String {
+    let quiet_zone = options.quiet_zone as usize;
+    let size = qr.size + quiet_zone * 2;
+    let is_dark = |x: usize, y: usize| is_dark(qr, quiet_zone, x, y);
+
+    let mut text = String::new();
+    for y in (0..size).step_by(2) {
+        for x in 0..size {
+            text.push(match (is_dark(x, y), is_dark(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        text.push('\n');
+    }
+
+    // The caption goes on its own line with a character for each module,
+    // and is written backwards when the code is mirrored
+    if let Some(caption) = get_caption(options) {
+        let mut line: Vec<char> = caption.text.chars().collect();
+        if line.len() > qr.size {
+            line.truncate(qr.size.saturating_sub(3));
+            line.extend("...".chars().take(qr.size));
+        }
+        let padding = match caption.alignment {
+            Alignment::Left => 0,
+            Alignment::Center => (qr.size - line.len()) / 2,
+            Alignment::Right => qr.size - line.len(),
+        };
+        let padding = if qr.mirrored {
+            line.reverse();
+            qr.size - line.len() - padding
+        } else {
+            padding
+        };
+        text += &" ".repeat(quiet_zone + padding);
+        text.extend(line);
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn test_chosen_version_and_mask() {
+        let options = Options {
+            version: Some(7),
+            mask: Some(5),
+            ..Default::default()
+        };
+        let qr = QrCode::new("hello", ErrorCorrection::Quartile, &options).unwrap();
+        assert_eq!((qr.size(), qr.version(), qr.mask()), (45, 7, 5));
+
+        let matrix: Vec<u8> = (0..qr.size() * qr.size())
+            .map(|i| {
+                if qr.is_dark(i % qr.size(), i / qr.size()) {
+                    0
+                } else {
+                    255
+                }
+            })
+            .collect();
+        let decoded = decode_matrix(qr.size(), &matrix).unwrap();
+        assert_eq!(decoded.text, "hello");
+        assert_eq!(decoded.version, 7);
+        assert_eq!(decoded.mask, 5);
+        assert_eq!(decoded.level, ErrorCorrection::Quartile);
+    }
+
+    #[test]
+    fn test_encoding_errors() {
+        let encode = |input: &str, version, mask| {
+            let options = Options {
+                version,
+                mask,
+                ..Default::default()
+            };
+            QrCode::new(input, ErrorCorrection::High, &options).err()
+        };
+        assert_eq!(
+            encode("hello", None, Some(8)),
+            Some(EncodeError::InvalidMask(8))
+        );
+        assert_eq!(
+            encode("hello", Some(41), None),
+            Some(EncodeError::InvalidVersion(41))
+        );
+        assert_eq!(
+            encode("hello", Some(0), None),
+            Some(EncodeError::InvalidVersion(0))
+        );
+        assert_eq!(
+            encode(&"a".repeat(20), Some(1), None),
+            Some(EncodeError::DataTooLong)
+        );
+        assert_eq!(
+            encode(&"a".repeat(1300), None, None),
+            Some(EncodeError::DataTooLong)
+        );
+        assert_eq!(encode(&"a".repeat(1273), None, None), None);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_image_rendering() {
+        let options = Options {
+            scale: 3,
+            quiet_zone: 2,
+            foreground: [10, 20, 30],
+            background: [200, 210, 220],
+            ..Default::default()
+        };
+        let image = render_qr_code("hello", ErrorCorrection::Low, &options).unwrap();
+        assert_eq!(image.dimensions(), (75, 75));
+        assert_eq!(image.get_pixel(0, 0).0, [200, 210, 220]);
+        assert_eq!(image.get_pixel(6, 6).0, [10, 20, 30]);
+        assert_eq!(image.get_pixel(8, 8).0, [10, 20, 30]);
+        assert_eq!(image.get_pixel(9, 9).0, [200, 210, 220]);
+    }
+
+    #[test]
+    fn test_svg_rendering() {
+        let options = Options {
+            scale: 2,
+            quiet_zone: 1,
+            ..Default::default()
+        };
+        let svg = QrCode::new("hello", ErrorCorrection::Low, &options)
+            .unwrap()
+            .to_svg();
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"46\" height=\"46\"")
+        );
+        assert!(svg.contains("viewBox=\"0 0 23 23\""));
+        assert!(svg.contains("<rect width=\"23\" height=\"23\" fill=\"#ffffff\"/>"));
+
+        // The top row of modules starts with a finder pattern edge
+        assert!(svg.contains("<path d=\"M1 1h7v1h-7z"));
+        assert!(svg.contains("fill=\"#000000\"/>"));
+
+        // The quiet zone is drawn dark when inverted
+        let options = Options {
+            inverted: true,
+            ..options
+        };
+        let svg = QrCode::new("hello", ErrorCorrection::Low, &options)
+            .unwrap()
+            .to_svg();
+        assert!(svg.contains("<path d=\"M0 0h23v1h-23z"));
+
+        // A caption with no height is left out
+        let options = Options {
+            caption: Some(Caption {
+                size: 0.0,
+                ..Caption::new("hello")
+            }),
+            ..options
+        };
+        let svg = QrCode::new("hello", ErrorCorrection::Low, &options)
+            .unwrap()
+            .to_svg();
+        assert!(svg.contains("viewBox=\"0 0 23 23\""));
+    }
+
+    #[test]
+    fn test_pdf_rendering() {
+        let options = Options {
+            scale: 5,
+            ..Default::default()
+        };
+        let pdf = QrCode::new("hello", ErrorCorrection::Low, &options)
+            .unwrap()
+            .to_pdf();
+        let text = String::from_utf8(pdf).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        assert!(text.contains("/MediaBox [0 0 145 145]"));
+        assert!(text.contains("5 0 0 5 0 0 cm\n1.000 1.000 1.000 rg\n0 0 29 29 re f\n"));
+
+        // Each object offset in the cross reference table points at its object
+        let xref = text.find("\nxref\n").unwrap() + 1;
+        let startxref = text.rfind("startxref\n").unwrap();
+        assert_eq!(
+            text[startxref + 10..].lines().next(),
+            Some(xref.to_string().as_str())
+        );
+        for (i, line) in text[xref..].lines().skip(3).take(4).enumerate() {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn test_caption_rendering() {
+        let caption = |text: &str, alignment| Caption {
+            alignment,
+            ..Caption::new(text)
+        };
+        let options = Options {
+            scale: 7,
+            quiet_zone: 2,
+            caption: Some(caption("ID-42", Alignment::Left)),
+            ..Default::default()
+        };
+        let qr = QrCode::new("ID-42", ErrorCorrection::Low, &options).unwrap();
+
+        // The letters are two modules tall with a pixel above and below them
+        let image = qr.to_image();
+        assert_eq!(image.dimensions(), (175, 193));
+        let strip =
+            |x0, x1| (x0..x1).any(|x| (175..193).any(|y| image.get_pixel(x, y).0 == [0; 3]));
+        assert!(!strip(0, 14));
+        assert!(strip(14, 20));
+        assert!(!strip(14 + 29 * 2, 175));
+        let decoded = decode_image(&image::DynamicImage::ImageRgb8(image));
+        assert_eq!(decoded[0].text, "ID-42");
+
+        let svg = qr.to_svg();
+        assert!(svg.contains("width=\"175\" height=\"193\" viewBox=\"0 0 25 27.57142857142857\""));
+        assert!(svg.contains(
+            "<path transform=\"translate(2 25.285714285714285) scale(0.2857142857142857)\""
+        ));
+        let pdf = String::from_utf8(qr.to_pdf()).unwrap();
+        assert!(pdf.contains("/MediaBox [0 0 175 193]"));
+        assert!(pdf.contains("1 0 0 1 0 2.571428571428571 cm\n"));
+
+        // Long captions are cut short instead of running into the quiet zone
+        let options = Options {
+            caption: Some(caption(&"W".repeat(40), Alignment::Right)),
+            ..options
+        };
+        let qr = QrCode::new("ID-42", ErrorCorrection::Low, &options).unwrap();
+        let image = qr.to_image();
+        assert!(!(0..14).any(|x| (175..193).any(|y| image.get_pixel(x, y).0 == [0; 3])));
+        assert!((161..175).all(|x| (175..193).all(|y| image.get_pixel(x, y).0 == [255; 3])));
+        let text = qr.to_text();
+        assert!(text.ends_with(&format!("\n  {}...\n", "W".repeat(18))));
+    }
+
+    #[test]
+    fn test_text_rendering() {
+        let options = Options {
+            quiet_zone: 0,
+            ..Default::default()
+        };
+        let text = render_qr_code_to_text("hello", ErrorCorrection::Low, &options).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert!(lines.iter().all(|line| line.chars().count() == 21));
+        assert!(lines[0].starts_with("█▀▀▀▀▀█"));
+        assert!(lines[10].starts_with("▀▀▀▀▀▀▀"));
+
+        let caption = |size| Caption {
+            alignment: Alignment::Left,
+            size,
+            ..Caption::new("ID-42")
+        };
+        let options = Options {
+            caption: Some(caption(2.0)),
+            mirrored: true,
+            ..options
+        };
+        let text = render_qr_code_to_text("hello", ErrorCorrection::Low, &options).unwrap();
+        assert!(text.ends_with(&format!("\n{}24-DI\n", " ".repeat(16))));
+
+        let options = Options {
+            caption: Some(caption(0.0)),
+            ..options
+        };
+        let text = render_qr_code_to_text("hello", ErrorCorrection::Low, &options).unwrap();
+        assert_eq!(text.lines().count(), 11);
+    }
+}
@@ -0,0 +1,825 @@
+use crate::decoder::{self, Decoded};
+use image::GrayImage;
+
+const FINDER_RATIOS: [f64; 5] = [1.0, 1.0, 3.0, 1.0, 1.0];
+const ALIGNMENT_RATIOS: [f64; 3] = [1.0, 1.0, 1.0];
+
+// A binarized image where true is a dark pixel
+#[derive(Clone)]
+struct BitMatrix {
+    width: usize,
+    height: usize,
+    bits: Vec<bool>,
+}
+
+impl BitMatrix {
+    // Pixels outside of the image are treated as light
+    fn get(&self, x: i64, y: i64) -> bool {
+        let inside = x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height;
+        inside && self.bits[y as usize * self.width + x as usize]
+    }
+
+    fn invert(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            bits: self.bits.iter().map(|&bit| !bit).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    fn distance(&self, other: Point) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+struct FinderPattern {
+    center: Point,
+    module_size: f64,
+    count: usize,
+}
+
+// Find the threshold that best separates the image's histogram into two classes
+fn get_otsu_threshold(image: &GrayImage) -> f64 {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total = (image.width() * image.height()) as f64;
+    let sum: f64 = (0..256).map(|i| i as f64 * histogram[i] as f64).sum();
+    let mut background_weight = 0.0;
+    let mut background_sum = 0.0;
+    let mut best = (0.0, 0);
+    for (i, &count) in histogram.iter().enumerate() {
+        background_weight += count as f64;
+        let foreground_weight = total - background_weight;
+        if background_weight == 0.0 || foreground_weight == 0.0 {
+            continue;
+        }
+
+        background_sum += i as f64 * count as f64;
+        let background_mean = background_sum / background_weight;
+        let foreground_mean = (sum - background_sum) / foreground_weight;
+        let variance =
+            background_weight * foreground_weight * (background_mean - foreground_mean).powi(2);
+        if variance > best.0 {
+            best = (variance, i);
+        }
+    }
+    best.1 as f64 + 0.5
+}
+
+// Threshold each pixel against the mean of the pixels around it, so uneven lighting
+// doesn't matter. Flat regions don't have a meaningful local mean, so they're
+// thresholded against the whole image instead.
+fn binarize(image: &GrayImage) -> BitMatrix {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let global_threshold = get_otsu_threshold(image);
+
+    // Summed area tables of the pixel values and their squares
+    let stride = width + 1;
+    let mut sums = vec![0u64; stride * (height + 1)];
+    let mut squares = vec![0u64; stride * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            let value = image.get_pixel(x as u32, y as u32)[0] as u64;
+            let index = (y + 1) * stride + x + 1;
+            sums[index] = value + sums[index - 1] + sums[index - stride] - sums[index - stride - 1];
+            squares[index] = value * value + squares[index - 1] + squares[index - stride]
+                - squares[index - stride - 1];
+        }
+    }
+
+    let area = |table: &[u64], top: usize, bottom: usize, left: usize, right: usize| {
+        table[bottom * stride + right] + table[top * stride + left]
+            - table[top * stride + right]
+            - table[bottom * stride + left]
+    };
+
+    let radius = std::cmp::max(width, height) / 12 + 4;
+    let mut bits = vec![false; width * height];
+    for y in 0..height {
+        let top = y.saturating_sub(radius);
+        let bottom = std::cmp::min(y + radius + 1, height);
+        for x in 0..width {
+            let left = x.saturating_sub(radius);
+            let right = std::cmp::min(x + radius + 1, width);
+            let count = ((bottom - top) * (right - left)) as f64;
+            let mean = area(&sums, top, bottom, left, right) as f64 / count;
+            let deviation = (area(&squares, top, bottom, left, right) as f64 / count - mean * mean)
+                .max(0.0)
+                .sqrt();
+            let threshold = if deviation < 16.0 {
+                global_threshold
+            } else {
+                mean * 0.9
+            };
+
+            // Compare the average of the 3x3 neighbourhood to smooth out noise
+            let (top, bottom) = (y.saturating_sub(1), std::cmp::min(y + 2, height));
+            let (left, right) = (x.saturating_sub(1), std::cmp::min(x + 2, width));
+            let count = ((bottom - top) * (right - left)) as f64;
+            bits[y * width + x] =
+                (area(&sums, top, bottom, left, right) as f64 / count) < threshold;
+        }
+    }
+
+    BitMatrix {
+        width,
+        height,
+        bits,
+    }
+}
+
+// Check that the runs are proportional to the ratios, allowing each run to be off by half
+fn check_ratios(runs: &[usize], ratios: &[f64]) -> Option<f64> {
+    let total = runs.iter().sum::<usize>() as f64;
+    let module_size = total / ratios.iter().sum::<f64>();
+    if module_size < 1.0 {
+        return None;
+    }
+
+    let matches = runs
+        .iter()
+        .zip(ratios.iter())
+        .all(|(&run, &ratio)| (run as f64 - module_size * ratio).abs() < module_size * ratio / 2.0);
+    matches.then_some(module_size)
+}
+
+// Walk outwards from (x, y) in both directions along (dx, dy), measuring the runs of
+// alternating colors. There are `count` runs on each side including the center run.
+// Returns the position of the center run's middle along with all the runs.
+fn measure_runs(
+    bits: &BitMatrix,
+    start: (i64, i64),
+    direction: (i64, i64),
+    count: usize,
+    max_run: usize,
+) -> Option<(Point, Vec<usize>)> {
+    let color = bits.get(start.0, start.1);
+    let mut sides = [vec![0; count], vec![0; count]];
+    for (side, sign) in [(0, -1), (1, 1)] {
+        let mut position = if side == 0 { 0 } else { 1 };
+        for (i, run) in sides[side].iter_mut().enumerate() {
+            let expected = if i % 2 == 0 { color } else { !color };
+            loop {
+                let x = start.0 + sign * position * direction.0;
+                let y = start.1 + sign * position * direction.1;
+                let outside = x < 0 || y < 0 || x >= bits.width as i64 || y >= bits.height as i64;
+                if outside || bits.get(x, y) != expected || *run > max_run {
+                    break;
+                }
+                *run += 1;
+                position += 1;
+            }
+            // The center run may end right at the starting point
+            let empty = *run == 0 && !(side == 1 && i == 0);
+            if empty || *run > max_run {
+                return None;
+            }
+        }
+    }
+
+    let [before, after] = sides;
+    let mut runs: Vec<usize> = before.iter().skip(1).rev().copied().collect();
+    runs.push(before[0] + after[0]);
+    runs.extend(after.iter().skip(1));
+
+    // Middle of the center run in pixel coordinates
+    let offset = (after[0] as f64 - before[0] as f64 + 1.0) / 2.0;
+    let center = Point {
+        x: start.0 as f64 + 0.5 + offset * direction.0 as f64,
+        y: start.1 as f64 + 0.5 + offset * direction.1 as f64,
+    };
+    Some((center, runs))
+}
+
+// Check that a finder pattern is centered around a point by
+// measuring it along a line through that point
+fn cross_check(
+    bits: &BitMatrix,
+    center: Point,
+    direction: (i64, i64),
+    max_run: usize,
+) -> Option<(Point, f64)> {
+    let start = (center.x.floor() as i64, center.y.floor() as i64);
+    if !bits.get(start.0, start.1) {
+        return None;
+    }
+    let (center, runs) = measure_runs(bits, start, direction, 3, max_run)?;
+    let module_size = check_ratios(&runs, &FINDER_RATIOS)?;
+    Some((center, module_size))
+}
+
+fn add_finder_pattern(patterns: &mut Vec<FinderPattern>, center: Point, module_size: f64) {
+    for pattern in patterns.iter_mut() {
+        let close = pattern.center.distance(center) < pattern.module_size * 2.0;
+        let similar = (pattern.module_size - module_size).abs() < pattern.module_size / 2.0;
+        if close && similar {
+            // Average the estimates
+            let count = pattern.count as f64;
+            pattern.center.x = (pattern.center.x * count + center.x) / (count + 1.0);
+            pattern.center.y = (pattern.center.y * count + center.y) / (count + 1.0);
+            pattern.module_size = (pattern.module_size * count + module_size) / (count + 1.0);
+            pattern.count += 1;
+            return;
+        }
+    }
+
+    patterns.push(FinderPattern {
+        center,
+        module_size,
+        count: 1,
+    });
+}
+
+// Scan each row for runs of dark and light pixels in a 1:1:3:1:1 ratio,
+// then confirm the candidates vertically, horizontally and diagonally
+fn find_finder_patterns(bits: &BitMatrix) -> Vec<FinderPattern> {
+    let mut patterns = Vec::new();
+    for y in 0..bits.height {
+        // Split the row into runs of (start, length)
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        let mut start = 0;
+        for x in 1..=bits.width {
+            if x == bits.width || bits.get(x as i64, y as i64) != bits.get(start as i64, y as i64) {
+                runs.push((start, x - start));
+                start = x;
+            }
+        }
+
+        let first_dark = usize::from(!bits.get(0, y as i64));
+        for i in (first_dark..runs.len().saturating_sub(4)).step_by(2) {
+            let lengths: Vec<usize> = runs[i..i + 5].iter().map(|run| run.1).collect();
+            if check_ratios(&lengths, &FINDER_RATIOS).is_none() {
+                continue;
+            }
+
+            let total: usize = lengths.iter().sum();
+            let center = Point {
+                x: runs[i + 2].0 as f64 + runs[i + 2].1 as f64 / 2.0,
+                y: y as f64 + 0.5,
+            };
+            let Some((center, vertical)) = cross_check(bits, center, (0, 1), total) else {
+                continue;
+            };
+            let Some((center, horizontal)) = cross_check(bits, center, (1, 0), total) else {
+                continue;
+            };
+            if cross_check(bits, center, (1, 1), total).is_none() {
+                continue;
+            }
+            add_finder_pattern(&mut patterns, center, (vertical + horizontal) / 2.0);
+        }
+    }
+
+    // Ignore patterns that were only seen once, they're most likely noise
+    patterns.retain(|pattern| pattern.count > 1);
+    patterns
+}
+
+// Find sets of 3 finder patterns that form a right angled isosceles triangle,
+// ordered as top left, top right and bottom left. The best fitting sets come first.
+fn group_finder_patterns(patterns: &[FinderPattern]) -> Vec<[usize; 3]> {
+    let mut groups = Vec::new();
+    for i in 0..patterns.len() {
+        for j in i + 1..patterns.len() {
+            for k in j + 1..patterns.len() {
+                let sizes = [i, j, k].map(|n| patterns[n].module_size);
+                let min_size = sizes.iter().copied().fold(f64::MAX, f64::min);
+                let max_size = sizes.iter().copied().fold(0.0, f64::max);
+                if max_size / min_size > 1.5 {
+                    continue;
+                }
+
+                // The top left pattern is opposite the longest side
+                let [a, b, c] = [i, j, k].map(|n| patterns[n].center);
+                let (corner, mut first, mut second) =
+                    if b.distance(c) >= a.distance(b) && b.distance(c) >= a.distance(c) {
+                        (i, j, k)
+                    } else if a.distance(c) >= a.distance(b) {
+                        (j, i, k)
+                    } else {
+                        (k, i, j)
+                    };
+
+                let origin = patterns[corner].center;
+                let leg1 = origin.distance(patterns[first].center);
+                let leg2 = origin.distance(patterns[second].center);
+                let hypotenuse = patterns[first].center.distance(patterns[second].center);
+                let legs = leg1.min(leg2) / leg1.max(leg2);
+                let pythagoras = hypotenuse.powi(2) / (leg1.powi(2) + leg2.powi(2));
+                let modules = leg1.min(leg2) / max_size;
+                if legs < 0.7 || !(0.7..1.3).contains(&pythagoras) || modules < 10.0 {
+                    continue;
+                }
+
+                // Going from the top right to the bottom left pattern is clockwise
+                let u = patterns[first].center;
+                let v = patterns[second].center;
+                let cross =
+                    (u.x - origin.x) * (v.y - origin.y) - (u.y - origin.y) * (v.x - origin.x);
+                if cross < 0.0 {
+                    std::mem::swap(&mut first, &mut second);
+                }
+
+                let score = (1.0 - legs) + (1.0 - pythagoras).abs();
+                groups.push((score, [corner, first, second]));
+            }
+        }
+    }
+
+    groups.sort_by(|a, b| a.0.total_cmp(&b.0));
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+// Look for the 1:1:1 light, dark, light cross section of an alignment pattern
+// within radius modules of where it's expected to be, returning the closest match
+fn find_alignment_pattern(
+    bits: &BitMatrix,
+    estimate: Point,
+    module_size: f64,
+    radius: f64,
+) -> Option<Point> {
+    let radius = (module_size * radius) as i64;
+    let max_run = (module_size * 2.0) as usize + 1;
+    let mut best: Option<(f64, Point)> = None;
+    for y in estimate.y as i64 - radius..=estimate.y as i64 + radius {
+        for x in estimate.x as i64 - radius..=estimate.x as i64 + radius {
+            if !bits.get(x, y) {
+                continue;
+            }
+
+            let Some((center, runs)) = measure_runs(bits, (x, y), (1, 0), 2, max_run) else {
+                continue;
+            };
+            let Some(size) = check_ratios(&runs, &ALIGNMENT_RATIOS) else {
+                continue;
+            };
+            let start = (center.x.floor() as i64, y);
+            let Some((center, runs)) = measure_runs(bits, start, (0, 1), 2, max_run) else {
+                continue;
+            };
+            if check_ratios(&runs, &ALIGNMENT_RATIOS).is_none()
+                || (size - module_size).abs() > module_size / 2.0
+            {
+                continue;
+            }
+
+            let distance = center.distance(estimate);
+            if best.is_none_or(|(d, _)| distance < d) {
+                best = Some((distance, center));
+            }
+        }
+    }
+    best.map(|(_, center)| center)
+}
+
+// Solve for the perspective transform that maps each source point onto its destination point
+fn get_perspective_transform(source: [Point; 4], destination: [Point; 4]) -> Option<[f64; 8]> {
+    let mut rows = [[0.0; 9]; 8];
+    for i in 0..4 {
+        let (u, v) = (source[i].x, source[i].y);
+        let (x, y) = (destination[i].x, destination[i].y);
+        rows[i * 2] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x];
+        rows[i * 2 + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y];
+    }
+
+    // Gaussian elimination with partial pivoting
+    for column in 0..8 {
+        let pivot =
+            (column..8).max_by(|&a, &b| rows[a][column].abs().total_cmp(&rows[b][column].abs()))?;
+        if rows[pivot][column].abs() < 1e-9 {
+            return None;
+        }
+        rows.swap(column, pivot);
+        let pivot_row = rows[column];
+        for (i, row) in rows.iter_mut().enumerate() {
+            if i != column {
+                let factor = row[column] / pivot_row[column];
+                for (value, pivot_value) in row.iter_mut().zip(pivot_row.iter()).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    Some(std::array::from_fn(|i| rows[i][8] / rows[i][i]))
+}
+
+fn transform(coefficients: &[f64; 8], point: Point) -> Point {
+    let [a, b, c, d, e, f, g, h] = *coefficients;
+    let denominator = g * point.x + h * point.y + 1.0;
+    Point {
+        x: (a * point.x + b * point.y + c) / denominator,
+        y: (d * point.x + e * point.y + f) / denominator,
+    }
+}
+
+// Sample the module grid of a qr code with the given dimension and decode it
+fn sample_and_decode(
+    bits: &BitMatrix,
+    finders: [Point; 3],
+    module_size: f64,
+    dimension: usize,
+) -> Option<Decoded> {
+    let [top_left, top_right, bottom_left] = finders;
+    let d = dimension as f64;
+
+    // Assume the symbol is a parallelogram to begin with
+    let mut source = [
+        Point { x: 3.5, y: 3.5 },
+        Point { x: d - 3.5, y: 3.5 },
+        Point { x: 3.5, y: d - 3.5 },
+        Point {
+            x: d - 3.5,
+            y: d - 3.5,
+        },
+    ];
+    let mut destination = [
+        top_left,
+        top_right,
+        bottom_left,
+        Point {
+            x: top_right.x + bottom_left.x - top_left.x,
+            y: top_right.y + bottom_left.y - top_left.y,
+        },
+    ];
+
+    // Then use the bottom right alignment pattern to correct for perspective
+    if dimension > 21 {
+        let ratio = (d - 10.0) / (d - 7.0);
+        let estimate = Point {
+            x: top_left.x + (destination[3].x - top_left.x) * ratio,
+            y: top_left.y + (destination[3].y - top_left.y) * ratio,
+        };
+        // Widen the search as perspective can push it far from the estimate
+        let center = [4.0, 8.0, 16.0]
+            .into_iter()
+            .find_map(|radius| find_alignment_pattern(bits, estimate, module_size, radius));
+        if let Some(center) = center {
+            source[3] = Point {
+                x: d - 6.5,
+                y: d - 6.5,
+            };
+            destination[3] = center;
+        }
+    }
+
+    let coefficients = get_perspective_transform(source, destination)?;
+    let mut matrix = Vec::with_capacity(dimension * dimension);
+    for y in 0..dimension {
+        for x in 0..dimension {
+            let point = transform(
+                &coefficients,
+                Point {
+                    x: x as f64 + 0.5,
+                    y: y as f64 + 0.5,
+                },
+            );
+            let dark = bits.get(point.x.floor() as i64, point.y.floor() as i64);
+            matrix.push(if dark { 0 } else { 255 });
+        }
+    }
+
+    if let Ok(decoded) = decoder::decode(dimension, &matrix) {
+        return Some(decoded);
+    }
+
+    // A symbol seen from behind looks transposed once it's been sampled
+    let transposed: Vec<u8> = (0..dimension * dimension)
+        .map(|i| matrix[(i % dimension) * dimension + i / dimension])
+        .collect();
+    let mut decoded = decoder::decode(dimension, &transposed).ok()?;
+    decoded.mirrored = !decoded.mirrored;
+    Some(decoded)
+}
+
+// Measure the distance from the center of a finder pattern to its outer edge
+// along the line towards another finder pattern. That's 3.5 modules long.
+fn measure_finder_radius(bits: &BitMatrix, from: Point, to: Point) -> Option<f64> {
+    let length = from.distance(to);
+    let (dx, dy) = ((to.x - from.x) / length, (to.y - from.y) / length);
+    let mut color = true;
+    let mut transitions = 0;
+    for step in 0..length as usize {
+        let x = from.x + dx * step as f64;
+        let y = from.y + dy * step as f64;
+        if bits.get(x.floor() as i64, y.floor() as i64) != color {
+            color = !color;
+            transitions += 1;
+            if transitions == 3 {
+                return Some(step as f64);
+            }
+        }
+    }
+    None
+}
+
+// The module size is measured along the lines between the finder patterns,
+// since those lines run parallel to the module grid however the symbol is rotated
+fn get_module_size(bits: &BitMatrix, finders: [Point; 3]) -> Option<f64> {
+    let mut total = 0.0;
+    for (a, b) in [(0, 1), (1, 0), (0, 2), (2, 0)] {
+        total += measure_finder_radius(bits, finders[a], finders[b])?;
+    }
+    Some(total / 4.0 / 3.5)
+}
+
+fn decode_symbol(
+    bits: &BitMatrix,
+    patterns: &[FinderPattern],
+    group: [usize; 3],
+) -> Option<Decoded> {
+    let finders = group.map(|i| patterns[i].center);
+    let module_size = get_module_size(bits, finders)?;
+
+    // Estimate the dimension from the distance between the finder patterns,
+    // then try the nearest valid dimensions
+    let distance = (finders[0].distance(finders[1]) + finders[0].distance(finders[2])) / 2.0;
+    let estimate = distance / module_size + 7.0;
+    let version = ((estimate - 17.0) / 4.0).round().max(1.0) as usize;
+    for version in [version, version + 1, version - 1] {
+        if (1..=40).contains(&version) {
+            let decoded = sample_and_decode(bits, finders, module_size, version * 4 + 17);
+            if decoded.is_some() {
+                return decoded;
+            }
+        }
+    }
+    None
+}
+
+/// Find and decode all the qr codes in a grayscale image
+pub fn scan(image: &GrayImage) -> Vec<Decoded> {
+    let binarized = binarize(image);
+    let mut results = Vec::new();
+
+    // Inverted symbols are found by looking for light finder patterns
+    for inverted in [false, true] {
+        let bits = if inverted {
+            binarized.invert()
+        } else {
+            binarized.clone()
+        };
+        let patterns = find_finder_patterns(&bits);
+        let mut used = vec![false; patterns.len()];
+        for group in group_finder_patterns(&patterns) {
+            if group.iter().any(|&i| used[i]) {
+                continue;
+            }
+            if let Some(mut decoded) = decode_symbol(&bits, &patterns, group) {
+                decoded.inverted = inverted;
+                for i in group {
+                    used[i] = true;
+                }
+                results.push(decoded);
+            }
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod test {
+    use crate::scanner::*;
+    use crate::{render_qr_code, ErrorCorrection, Options};
+    use image::{imageops, Luma};
+
+    // Small deterministic pseudo random number generator so the tests are repeatable
+    struct Random(u32);
+
+    impl Random {
+        fn next(&mut self, max: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0 % max
+        }
+    }
+
+    fn render(input: &str, level: ErrorCorrection, options: &Options) -> GrayImage {
+        image::DynamicImage::ImageRgb8(render_qr_code(input, level, options).unwrap()).to_luma8()
+    }
+
+    // Rotate an image around its center, growing it so nothing is cut off
+    fn rotate(image: &GrayImage, degrees: f64) -> GrayImage {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (width, height) = (image.width() as f64, image.height() as f64);
+        let size = (width * width + height * height).sqrt().ceil() as u32;
+        let mut rotated = GrayImage::from_pixel(size, size, Luma([255]));
+        let half = size as f64 / 2.0;
+        for (x, y, pixel) in rotated.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f64 - half, y as f64 - half);
+            let source_x = cos * dx + sin * dy + width / 2.0;
+            let source_y = -sin * dx + cos * dy + height / 2.0;
+            if source_x >= 0.0 && source_y >= 0.0 && source_x < width && source_y < height {
+                *pixel = *image.get_pixel(source_x as u32, source_y as u32);
+            }
+        }
+        rotated
+    }
+
+    fn add_noise(image: &mut GrayImage, amount: i32, random: &mut Random) {
+        for pixel in image.pixels_mut() {
+            let noise = random.next(amount as u32 * 2 + 1) as i32 - amount;
+            pixel[0] = (pixel[0] as i32 + noise).clamp(0, 255) as u8;
+        }
+    }
+
+    fn scan_texts(image: &GrayImage) -> Vec<String> {
+        scan(image)
+            .into_iter()
+            .map(|decoded| decoded.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_images() {
+        let inputs = [
+            "hello",
+            "HELLO WORLD",
+            "0123456789012345678901234567890123456789",
+            "https://github.com/aabiji/qr?query=a+long+enough+string+for+version+7&x=1234567890",
+        ];
+        let levels = [
+            ErrorCorrection::Low,
+            ErrorCorrection::Medium,
+            ErrorCorrection::Quartile,
+            ErrorCorrection::High,
+        ];
+        for input in inputs {
+            for level in levels {
+                let image = render(input, level, &Options::default());
+                let decoded = scan(&image);
+                assert_eq!(decoded.len(), 1);
+                assert_eq!(decoded[0].text, input);
+                assert!(decoded[0].level == level);
+            }
+        }
+    }
+
+    #[test]
+    fn test_png_file() {
+        let path = std::env::temp_dir().join("qrgen_scanner_test.png");
+        let path = path.to_str().unwrap();
+        crate::generate_qr_code("Read me back from disk", ErrorCorrection::Medium, path).unwrap();
+        let decoded = crate::decode_image_file(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].text, "Read me back from disk");
+
+        // Nothing is written when the input doesn't fit
+        let options = Options {
+            version: Some(1),
+            ..Default::default()
+        };
+        let input = "Too long for the smallest version";
+        assert!(matches!(
+            crate::generate_qr_code_with_options(input, ErrorCorrection::High, &options, path),
+            Err(crate::GenerateError::Encoding(
+                crate::EncodeError::DataTooLong
+            ))
+        ));
+        assert!(!std::path::Path::new(path).exists());
+        assert!(matches!(
+            crate::generate_qr_code("hello", ErrorCorrection::Low, "/nonexistent/qr.png"),
+            Err(crate::GenerateError::Image(_))
+        ));
+    }
+
+    #[test]
+    fn test_noise_and_blur() {
+        let mut random = Random(0xDEADBEEF);
+        let input = "The quick brown fox jumps over the lazy dog";
+        let mut image = render(input, ErrorCorrection::Medium, &Options::default());
+        image = imageops::blur(&image, 2.0);
+        add_noise(&mut image, 60, &mut random);
+
+        // Sprinkle in some dead pixels
+        for _ in 0..image.width() * image.height() / 200 {
+            let (x, y) = (random.next(image.width()), random.next(image.height()));
+            image.put_pixel(x, y, Luma([random.next(2) as u8 * 255]));
+        }
+        assert_eq!(scan_texts(&image), [input]);
+    }
+
+    #[test]
+    fn test_uneven_lighting() {
+        let input = "Lit from the side";
+        let mut image = render(input, ErrorCorrection::Low, &Options::default());
+        let width = image.width();
+        for (x, _, pixel) in image.enumerate_pixels_mut() {
+            // Fade the right side of the image into the shadows
+            let light = 1.0 - 0.6 * x as f64 / width as f64;
+            pixel[0] = (pixel[0] as f64 * light + 20.0) as u8;
+        }
+        assert_eq!(scan_texts(&image), [input]);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let input = "https://example.com/rotated";
+        let image = render(input, ErrorCorrection::Quartile, &Options::default());
+        for degrees in [10.0, 30.0, 45.0, 135.0, 200.0, 290.0] {
+            assert_eq!(scan_texts(&rotate(&image, degrees)), [input]);
+        }
+        assert_eq!(scan_texts(&imageops::rotate90(&image)), [input]);
+        assert_eq!(scan_texts(&imageops::rotate180(&image)), [input]);
+
+        let mut random = Random(0xF00DF00D);
+        let mut image = rotate(&imageops::blur(&image, 1.5), 20.0);
+        add_noise(&mut image, 40, &mut random);
+        assert_eq!(scan_texts(&image), [input]);
+    }
+
+    #[test]
+    fn test_perspective() {
+        let input = "Photographed at an angle, large enough to have alignment patterns";
+        let image = render(input, ErrorCorrection::Medium, &Options::default());
+        let (width, height) = (image.width() as f64, image.height() as f64);
+
+        // Map a trapezoid in the output image back onto the qr code image,
+        // squashing the top edge as if the code was leaning away from the camera
+        let output = [(80.0, 0.0), (520.0, 0.0), (0.0, 600.0), (600.0, 600.0)];
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)];
+        let coefficients = get_perspective_transform(
+            output.map(|(x, y)| Point { x, y }),
+            corners.map(|(x, y)| Point { x, y }),
+        )
+        .unwrap();
+
+        let mut warped = GrayImage::from_pixel(600, 600, Luma([255]));
+        for (x, y, pixel) in warped.enumerate_pixels_mut() {
+            let source = transform(
+                &coefficients,
+                Point {
+                    x: x as f64,
+                    y: y as f64,
+                },
+            );
+            if source.x >= 0.0 && source.y >= 0.0 && source.x < width && source.y < height {
+                *pixel = *image.get_pixel(source.x as u32, source.y as u32);
+            }
+        }
+        assert_eq!(scan_texts(&warped), [input]);
+    }
+
+    #[test]
+    fn test_inverted_and_mirrored() {
+        let input = "Etched on anodised metal";
+        let options = Options {
+            inverted: true,
+            mirrored: true,
+            ..Default::default()
+        };
+        let image = rotate(&render(input, ErrorCorrection::High, &options), 15.0);
+        let decoded = scan(&image);
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].text, input);
+        assert!(decoded[0].inverted);
+        assert!(decoded[0].mirrored);
+    }
+
+    #[test]
+    fn test_multiple_codes() {
+        let inputs = ["FIRST CODE", "second code", "1234567890"];
+        let mut canvas = GrayImage::from_pixel(1400, 700, Luma([255]));
+        let first = render(inputs[0], ErrorCorrection::Low, &Options::default());
+        let second = imageops::rotate90(&render(
+            inputs[1],
+            ErrorCorrection::High,
+            &Options::default(),
+        ));
+        let third = rotate(
+            &render(inputs[2], ErrorCorrection::Medium, &Options::default()),
+            30.0,
+        );
+        imageops::overlay(&mut canvas, &first, 20, 20);
+        imageops::overlay(&mut canvas, &second, 450, 300);
+        imageops::overlay(&mut canvas, &third, 850, 50);
+
+        let mut texts = scan_texts(&canvas);
+        texts.sort();
+        let mut expected = inputs.map(String::from).to_vec();
+        expected.sort();
+        assert_eq!(texts, expected);
+    }
+
+    #[test]
+    fn test_no_codes() {
+        let mut random = Random(0x600DCAFE);
+        let mut image = GrayImage::from_pixel(300, 300, Luma([200]));
+        add_noise(&mut image, 50, &mut random);
+        assert!(scan(&image).is_empty());
+    }
+}
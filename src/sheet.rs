@@ -0,0 +1,381 @@
+use crate::{font, render, QrCode};
+
+/// Paper sizes for label sheets
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PageSize {
+    A4,
+    Letter,
+}
+
+impl PageSize {
+    /// Get the width and height in points
+    pub fn dimensions(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+/// Reasons the labels can't be laid out on a page
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum SheetError {
+    /// There has to be at least one row and column
+    EmptyGrid,
+    /// The margins and gutters leave no room for the labels
+    NoRoom,
+}
+
+impl std::fmt::Display for SheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SheetError::EmptyGrid => write!(f, "the sheet needs at least one row and column"),
+            SheetError::NoRoom => write!(f, "the margins and gutters leave no room for labels"),
+        }
+    }
+}
+
+impl std::error::Error for SheetError {}
+
+/// A qr code to put on a label, with text to print below it
+pub struct Label {
+    pub code: QrCode,
+    pub caption: Option<String>,
+}
+
+/// A grid of labels on a page, like the ones sold on sticker sheets.
+/// All sizes are in points, where an inch is 72 points.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LabelSheet {
+    pub page: PageSize,
+    pub rows: usize,
+    pub columns: usize,
+    /// Space between the labels and the top, right, bottom and left edges of the page
+    pub margins: [f64; 4],
+    /// Horizontal space between columns
+    pub column_gutter: f64,
+    /// Vertical space between rows
+    pub row_gutter: f64,
+    /// Height of the caption letters
+    pub caption_size: f64,
+}
+
+// A rectangle on the page measured from the top left corner
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Rect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+// Where the parts of a label go inside of its cell
+struct LabelLayout {
+    /// The qr code including its quiet zone
+    code: Rect,
+    /// The caption text and the size of each of its pixels
+    caption: Option<(String, Rect, f64)>,
+}
+
+impl LabelSheet {
+    pub fn new(page: PageSize, rows: usize, columns: usize) -> Self {
+        LabelSheet {
+            page,
+            rows,
+            columns,
+            margins: [36.0; 4],
+            column_gutter: 9.0,
+            row_gutter: 9.0,
+            caption_size: 8.0,
+        }
+    }
+
+    pub fn margins(mut self, top: f64, right: f64, bottom: f64, left: f64) -> Self {
+        self.margins = [top, right, bottom, left];
+        self
+    }
+
+    pub fn gutters(mut self, column_gutter: f64, row_gutter: f64) -> Self {
+        self.column_gutter = column_gutter;
+        self.row_gutter = row_gutter;
+        self
+    }
+
+    pub fn caption_size(mut self, caption_size: f64) -> Self {
+        self.caption_size = caption_size;
+        self
+    }
+
+    pub fn labels_per_page(&self) -> usize {
+        self.rows * self.columns
+    }
+
+    // Get the cells of the labels on a page, going across each row
+    fn get_cells(&self) -> Result<Vec<Rect>, SheetError> {
+        if self.rows == 0 || self.columns == 0 {
+            return Err(SheetError::EmptyGrid);
+        }
+
+        let (page_width, page_height) = self.page.dimensions();
+        let [top, right, bottom, left] = self.margins;
+        let gutters = self.column_gutter * (self.columns - 1) as f64;
+        let width = (page_width - left - right - gutters) / self.columns as f64;
+        let gutters = self.row_gutter * (self.rows - 1) as f64;
+        let height = (page_height - top - bottom - gutters) / self.rows as f64;
+        if width <= 0.0 || height <= 0.0 {
+            return Err(SheetError::NoRoom);
+        }
+
+        let cells = (0..self.labels_per_page()).map(|i| Rect {
+            x: left + (i % self.columns) as f64 * (width + self.column_gutter),
+            y: top + (i / self.columns) as f64 * (height + self.row_gutter),
+            width,
+            height,
+        });
+        Ok(cells.collect())
+    }
+
+    // Fit the code and caption in the cell, centering them both ways
+    fn get_layout(&self, label: &Label, cell: Rect) -> LabelLayout {
+        let pixel_size = self.caption_size / font::GLYPH_HEIGHT as f64;
+        let caption = label
+            .caption
+            .as_ref()
+            .filter(|caption| !caption.is_empty() && pixel_size > 0.0 && pixel_size.is_finite());
+
+        // The caption sits below the code with a gap of two pixels
+        let caption_height = if caption.is_some() {
+            pixel_size * (font::GLYPH_HEIGHT + 2) as f64
+        } else {
+            0.0
+        };
+        let code_size = cell.width.min(cell.height - caption_height).max(0.0);
+        let top = cell.y + (cell.height - code_size - caption_height) / 2.0;
+        let code = Rect {
+            x: cell.x + (cell.width - code_size) / 2.0,
+            y: top,
+            width: code_size,
+            height: code_size,
+        };
+
+        let caption = caption.map(|caption| {
+            let text = font::truncate(caption, (cell.width / pixel_size) as usize);
+            let width = font::get_text_width(&text) as f64 * pixel_size;
+            let rect = Rect {
+                x: cell.x + (cell.width - width) / 2.0,
+                y: top + code_size + pixel_size * 2.0,
+                width,
+                height: self.caption_size,
+            };
+            (text, rect, pixel_size)
+        });
+        LabelLayout { code, caption }
+    }
+
+    // Lay out each page of labels, skipping the cells that aren't needed on the last page
+    fn get_pages<'a>(
+        &self,
+        labels: &'a [Label],
+    ) -> Result<Vec<Vec<(&'a Label, LabelLayout)>>, SheetError> {
+        let cells = self.get_cells()?;
+        let pages = labels.chunks(cells.len()).map(|labels| {
+            labels
+                .iter()
+                .zip(&cells)
+                .map(|(label, &cell)| (label, self.get_layout(label, cell)))
+                .collect()
+        });
+        Ok(pages.collect())
+    }
+
+    /// Draw the labels as a pdf, adding pages as they're needed
+    pub fn to_pdf(&self, labels: &[Label]) -> Result<Vec<u8>, SheetError> {
+        let (page_width, page_height) = self.page.dimensions();
+        let mut pages = Vec::new();
+        for page in self.get_pages(labels)? {
+            let mut content = String::new();
+            for (label, layout) in page {
+                let code = &label.code;
+                let modules = code.qr.size + code.options.quiet_zone as usize * 2;
+                let scale = layout.code.width / modules as f64;
+                let bottom = page_height - layout.code.y - layout.code.height;
+                content += &format!(
+                    "q\n{} 0 0 {} {} {} cm\n{}Q\n",
+                    scale,
+                    scale,
+                    layout.code.x,
+                    bottom,
+                    render::get_pdf_commands(&code.qr, &code.options)
+                );
+
+                if let Some((text, rect, pixel_size)) = layout.caption {
+                    let bottom = page_height - rect.y - rect.height;
+                    content += &format!(
+                        "q\n{} 0 0 {} {} {} cm\n",
+                        pixel_size, pixel_size, rect.x, bottom
+                    );
+                    content += &render::to_pdf_color(code.options.foreground, "rg");
+                    for (x, y, length) in font::get_text_runs(&text) {
+                        let y = font::GLYPH_HEIGHT - y - 1;
+                        content += &format!("{} {} {} 1 re\n", x, y, length);
+                    }
+                    content += "f\nQ\n";
+                }
+            }
+            pages.push((page_width, page_height, content));
+        }
+        Ok(render::write_pdf(&pages))
+    }
+
+    /// Draw the labels as svg images, one for each page
+    pub fn to_svg(&self, labels: &[Label]) -> Result<Vec<String>, SheetError> {
+        let (page_width, page_height) = self.page.dimensions();
+        let mut pages = Vec::new();
+        for page in self.get_pages(labels)? {
+            let mut svg = format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}pt\" height=\"{h}pt\" \
+                 viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\n",
+                w = page_width,
+                h = page_height
+            );
+            for (label, layout) in page {
+                let code = &label.code;
+                let modules = code.qr.size + code.options.quiet_zone as usize * 2;
+                svg += &format!(
+                    "<g transform=\"translate({} {}) scale({})\">\
+                     <rect width=\"{m}\" height=\"{m}\" fill=\"{}\"/>\
+                     <path d=\"{}\" fill=\"{}\"/></g>\n",
+                    layout.code.x,
+                    layout.code.y,
+                    layout.code.width / modules as f64,
+                    render::to_hex(code.options.background),
+                    render::get_svg_path(&code.qr, &code.options),
+                    render::to_hex(code.options.foreground),
+                    m = modules
+                );
+
+                if let Some((text, rect, pixel_size)) = layout.caption {
+                    let path = font::get_text_path(&font::get_text_runs(&text));
+                    svg += &format!(
+                        "<path transform=\"translate({} {}) scale({})\" d=\"{}\" fill=\"{}\"/>\n",
+                        rect.x,
+                        rect.y,
+                        pixel_size,
+                        path,
+                        render::to_hex(code.options.foreground)
+                    );
+                }
+            }
+            svg += "</svg>\n";
+            pages.push(svg);
+        }
+        Ok(pages)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::sheet::*;
+    use crate::{ErrorCorrection, Options};
+
+    fn create_labels(count: usize) -> Vec<Label> {
+        (0..count)
+            .map(|i| Label {
+                code: QrCode::new(
+                    &format!("TAG-{}", i),
+                    ErrorCorrection::Medium,
+                    &Options::default(),
+                )
+                .unwrap(),
+                caption: Some(format!("TAG-{}", i)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let sheet = LabelSheet::new(PageSize::Letter, 10, 3)
+            .margins(36.0, 13.5, 36.0, 13.5)
+            .gutters(9.0, 0.0);
+        let cells = sheet.get_cells().unwrap();
+        assert_eq!(cells.len(), 30);
+        assert_eq!(
+            cells[0],
+            Rect {
+                x: 13.5,
+                y: 36.0,
+                width: 189.0,
+                height: 72.0
+            }
+        );
+        assert_eq!((cells[4].x, cells[4].y), (211.5, 108.0));
+        assert_eq!(cells[29].x + cells[29].width, 612.0 - 13.5);
+        assert_eq!(cells[29].y + cells[29].height, 792.0 - 36.0);
+
+        assert_eq!(
+            LabelSheet::new(PageSize::A4, 0, 3).get_cells(),
+            Err(SheetError::EmptyGrid)
+        );
+        let sheet = LabelSheet::new(PageSize::A4, 2, 2).margins(300.0, 0.0, 300.0, 0.0);
+        assert_eq!(
+            sheet.gutters(0.0, 300.0).get_cells(),
+            Err(SheetError::NoRoom)
+        );
+    }
+
+    #[test]
+    fn test_label_layout() {
+        let sheet = LabelSheet::new(PageSize::A4, 1, 1).caption_size(7.0);
+        let cell = Rect {
+            x: 10.0,
+            y: 20.0,
+            width: 100.0,
+            height: 50.0,
+        };
+        let labels = create_labels(1);
+        let layout = sheet.get_layout(&labels[0], cell);
+
+        // The code shrinks to leave room for the caption below it
+        assert_eq!(
+            layout.code,
+            Rect {
+                x: 39.5,
+                y: 20.0,
+                width: 41.0,
+                height: 41.0
+            }
+        );
+        let (text, rect, pixel_size) = layout.caption.unwrap();
+        assert_eq!((&text[..], pixel_size), ("TAG-0", 1.0));
+        assert_eq!((rect.x, rect.y, rect.width), (45.5, 63.0, 29.0));
+
+        // Captions that are too wide get cut short
+        let label = Label {
+            caption: Some("A very long caption for a small label".to_string()),
+            ..labels.into_iter().next().unwrap()
+        };
+        let (text, rect, _) = sheet.get_layout(&label, cell).caption.unwrap();
+        assert_eq!(text, "A very long c...");
+        assert!(rect.width <= cell.width);
+
+        // Captions without any height are left out
+        let sheet = sheet.caption_size(0.0);
+        assert!(sheet.get_layout(&label, cell).caption.is_none());
+    }
+
+    #[test]
+    fn test_pages() {
+        let sheet = LabelSheet::new(PageSize::A4, 2, 2);
+        let labels = create_labels(5);
+
+        let svgs = sheet.to_svg(&labels).unwrap();
+        assert_eq!(svgs.len(), 2);
+        assert!(svgs[0].contains("width=\"595.28pt\" height=\"841.89pt\""));
+        assert_eq!(svgs[0].matches("<g transform").count(), 4);
+        assert_eq!(svgs[1].matches("<g transform").count(), 1);
+
+        let pdf = String::from_utf8(sheet.to_pdf(&labels).unwrap()).unwrap();
+        assert!(pdf.contains("/Kids [3 0 R 5 0 R] /Count 2"));
+        assert_eq!(pdf.matches("/MediaBox [0 0 595.28 841.89]").count(), 2);
+    }
+}
@@ -138,7 +138,7 @@ pub const CHARACTER_CAPACITIES: [[[u16; 3]; 4]; 40] = [
         [1425, 864, 593],
     ],
     [
-        [3514, 2132, 1465],
+        [3517, 2132, 1465],
         [2701, 1637, 1125],
         [1933, 1172, 805],
         [1501, 910, 625],
@@ -474,133 +474,195 @@ pub const ECC_DATA: [[[u32; 5]; 4]; 40] = [
     ],
 ];
 
-// Format info bistreams. Index by error correction level, then by mask pattern index.
-pub const FORMAT_INFO_BITS: [[[u8; 15]; 8]; 4] = [
-    [
-        [1, 1, 1, 0, 1, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0],
-        [1, 1, 1, 0, 0, 1, 0, 1, 1, 1, 1, 0, 0, 1, 1],
-        [1, 1, 1, 1, 1, 0, 1, 1, 0, 1, 0, 1, 0, 1, 0],
-        [1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1],
-        [1, 1, 0, 0, 1, 1, 0, 0, 0, 1, 0, 1, 1, 1, 1],
-        [1, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 0, 0, 0],
-        [1, 1, 0, 1, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 1],
-        [1, 1, 0, 1, 0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 0],
-    ],
-    [
-        [1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 0],
-        [1, 0, 1, 0, 0, 0, 1, 0, 0, 1, 0, 0, 1, 0, 1],
-        [1, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1, 0, 0],
-        [1, 0, 1, 1, 0, 1, 1, 0, 1, 0, 0, 1, 0, 1, 1],
-        [1, 0, 0, 0, 1, 0, 1, 1, 1, 1, 1, 1, 0, 0, 1],
-        [1, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 1, 0],
-        [1, 0, 0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 1, 1, 1],
-        [1, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0],
-    ],
-    [
-        [0, 1, 1, 0, 1, 0, 1, 0, 1, 0, 1, 1, 1, 1, 1],
-        [0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0, 0, 0],
-        [0, 1, 1, 1, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 1],
-        [0, 1, 1, 1, 0, 1, 0, 0, 0, 0, 0, 0, 1, 1, 0],
-        [0, 1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 0, 1, 0, 0],
-        [0, 1, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1],
-        [0, 1, 0, 1, 1, 1, 0, 1, 1, 0, 1, 1, 0, 1, 0],
-        [0, 1, 0, 1, 0, 1, 1, 1, 1, 1, 0, 1, 1, 0, 1],
-    ],
-    [
-        [0, 0, 1, 0, 1, 1, 0, 1, 0, 0, 0, 1, 0, 0, 1],
-        [0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 0],
-        [0, 0, 1, 1, 1, 0, 0, 1, 1, 1, 0, 0, 1, 1, 1],
-        [0, 0, 1, 1, 0, 0, 1, 1, 1, 0, 1, 0, 0, 0, 0],
-        [0, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0],
-        [0, 0, 0, 0, 0, 1, 0, 0, 1, 0, 1, 0, 1, 0, 1],
-        [0, 0, 0, 1, 1, 0, 1, 0, 0, 0, 0, 1, 1, 0, 0],
-        [0, 0, 0, 1, 0, 0, 0, 0, 0, 1, 1, 1, 0, 1, 1],
-    ],
-];
+/// Get the center positions of qr code alignment patterns along each axis.
+/// Only the first `count` of the returned positions are used.
+pub const fn get_alignment_pattern_locations(version: usize) -> ([usize; 7], usize) {
+    let mut positions = [0; 7];
+    if version < 2 {
+        return (positions, 0);
+    }
 
-/// Get the center positions of qr code alignment patterns
-pub fn get_alignment_pattern_locations(version: usize) -> Vec<usize> {
-    match version {
-        2 => Vec::from([6, 18]),
-        3 => Vec::from([6, 22]),
-        4 => Vec::from([6, 26]),
-        5 => Vec::from([6, 30]),
-        6 => Vec::from([6, 34]),
-        7 => Vec::from([6, 22, 38]),
-        8 => Vec::from([6, 24, 42]),
-        9 => Vec::from([6, 26, 46]),
-        10 => Vec::from([6, 28, 50]),
-        11 => Vec::from([6, 30, 54]),
-        12 => Vec::from([6, 32, 58]),
-        13 => Vec::from([6, 34, 62]),
-        14 => Vec::from([6, 26, 46, 66]),
-        15 => Vec::from([6, 26, 48, 70]),
-        16 => Vec::from([6, 26, 50, 74]),
-        17 => Vec::from([6, 30, 54, 78]),
-        18 => Vec::from([6, 30, 56, 82]),
-        19 => Vec::from([6, 30, 58, 86]),
-        20 => Vec::from([6, 34, 62, 90]),
-        21 => Vec::from([6, 28, 50, 72, 94]),
-        22 => Vec::from([6, 26, 50, 74, 98]),
-        23 => Vec::from([6, 30, 54, 78, 102]),
-        24 => Vec::from([6, 28, 54, 80, 106]),
-        25 => Vec::from([6, 32, 58, 84, 110]),
-        26 => Vec::from([6, 30, 58, 86, 114]),
-        27 => Vec::from([6, 34, 62, 90, 118]),
-        28 => Vec::from([6, 26, 50, 74, 98, 122]),
-        29 => Vec::from([6, 30, 54, 78, 102, 126]),
-        30 => Vec::from([6, 26, 52, 78, 104, 130]),
-        31 => Vec::from([6, 30, 56, 82, 108, 134]),
-        32 => Vec::from([6, 34, 60, 86, 112, 138]),
-        33 => Vec::from([6, 30, 58, 86, 114, 142]),
-        34 => Vec::from([6, 34, 62, 90, 118, 146]),
-        35 => Vec::from([6, 30, 54, 78, 102, 126, 150]),
-        36 => Vec::from([6, 24, 50, 76, 102, 128, 154]),
-        37 => Vec::from([6, 28, 54, 80, 106, 132, 158]),
-        38 => Vec::from([6, 32, 58, 84, 110, 136, 162]),
-        39 => Vec::from([6, 26, 54, 82, 110, 138, 166]),
-        40 => Vec::from([6, 30, 58, 86, 114, 142, 170]),
-        _ => Vec::new(),
+    // The first pattern is always in line with the timing patterns and the
+    // last is always 7 modules from the far edge. The ones in between are
+    // evenly spaced by an even step, rounded up, except in version 32.
+    let count = version / 7 + 2;
+    let last = version * 4 + 10;
+    let step = if version == 32 {
+        26
+    } else {
+        (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2
+    };
+
+    positions[0] = 6;
+    let mut i = 1;
+    while i < count {
+        positions[i] = last - step * (count - 1 - i);
+        i += 1;
     }
+    (positions, count)
 }
 
-/// Get version bit strings for qr code versions 7 and higher
-pub fn get_version_bitstring(version: usize) -> [u8; 18] {
-    match version {
-        7 => [0, 0, 0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0, 0],
-        8 => [0, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0],
-        9 => [0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1],
-        10 => [0, 0, 1, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1],
-        11 => [0, 0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 1, 0],
-        12 => [0, 0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0],
-        13 => [0, 0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1],
-        14 => [0, 0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 1],
-        15 => [0, 0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0],
-        16 => [0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0, 0],
-        17 => [0, 1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1],
-        18 => [0, 1, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 1],
-        19 => [0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0],
-        20 => [0, 1, 0, 1, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 0, 1, 1, 0],
-        21 => [0, 1, 0, 1, 0, 1, 0, 1, 1, 0, 1, 0, 0, 0, 0, 0, 1, 1],
-        22 => [0, 1, 0, 1, 1, 0, 1, 0, 0, 0, 1, 1, 0, 0, 1, 0, 0, 1],
-        23 => [0, 1, 0, 1, 1, 1, 0, 1, 1, 1, 1, 1, 1, 0, 1, 1, 0, 0],
-        24 => [0, 1, 1, 0, 0, 0, 1, 1, 1, 0, 1, 1, 0, 0, 0, 1, 0, 0],
-        25 => [0, 1, 1, 0, 0, 1, 0, 0, 0, 1, 1, 1, 1, 0, 0, 0, 0, 1],
-        26 => [0, 1, 1, 0, 1, 0, 1, 1, 1, 1, 1, 0, 1, 0, 1, 0, 1, 1],
-        27 => [0, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 1, 0],
-        28 => [0, 1, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 0, 1, 1, 0, 1, 0],
-        29 => [0, 1, 1, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 1, 1, 1, 1, 1],
-        30 => [0, 1, 1, 1, 1, 0, 1, 1, 0, 1, 0, 1, 1, 1, 0, 1, 0, 1],
-        31 => [0, 1, 1, 1, 1, 1, 0, 0, 1, 0, 0, 1, 0, 1, 0, 0, 0, 0],
-        32 => [1, 0, 0, 0, 0, 0, 1, 0, 0, 1, 1, 1, 0, 1, 0, 1, 0, 1],
-        33 => [1, 0, 0, 0, 0, 1, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0, 0, 0],
-        34 => [1, 0, 0, 0, 1, 0, 1, 0, 0, 0, 1, 0, 1, 1, 1, 0, 1, 0],
-        35 => [1, 0, 0, 0, 1, 1, 0, 1, 1, 1, 1, 0, 0, 1, 1, 1, 1, 1],
-        36 => [1, 0, 0, 1, 0, 0, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 1, 1],
-        37 => [1, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 1, 0, 1, 1, 1, 0],
-        38 => [1, 0, 0, 1, 1, 0, 1, 0, 1, 0, 0, 1, 1, 0, 0, 1, 0, 0],
-        39 => [1, 0, 0, 1, 1, 1, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0, 0, 1],
-        40 => [1, 0, 1, 0, 0, 0, 1, 1, 0, 0, 0, 1, 1, 0, 1, 0, 0, 1],
-        _ => [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+#[cfg(test)]
+mod test {
+    use crate::drawer::QR;
+    use crate::encoder::{get_count_bits_size, EncodingMode, ErrorCorrection};
+    use crate::tables::*;
+
+    const LEVELS: [ErrorCorrection; 4] = [
+        ErrorCorrection::Low,
+        ErrorCorrection::Medium,
+        ErrorCorrection::Quartile,
+        ErrorCorrection::High,
+    ];
+
+    #[test]
+    fn test_alignment_pattern_locations() {
+        // The locations listed in the specification for versions 2 to 40
+        let expected: [&[usize]; 39] = [
+            &[6, 18],
+            &[6, 22],
+            &[6, 26],
+            &[6, 30],
+            &[6, 34],
+            &[6, 22, 38],
+            &[6, 24, 42],
+            &[6, 26, 46],
+            &[6, 28, 50],
+            &[6, 30, 54],
+            &[6, 32, 58],
+            &[6, 34, 62],
+            &[6, 26, 46, 66],
+            &[6, 26, 48, 70],
+            &[6, 26, 50, 74],
+            &[6, 30, 54, 78],
+            &[6, 30, 56, 82],
+            &[6, 30, 58, 86],
+            &[6, 34, 62, 90],
+            &[6, 28, 50, 72, 94],
+            &[6, 26, 50, 74, 98],
+            &[6, 30, 54, 78, 102],
+            &[6, 28, 54, 80, 106],
+            &[6, 32, 58, 84, 110],
+            &[6, 30, 58, 86, 114],
+            &[6, 34, 62, 90, 118],
+            &[6, 26, 50, 74, 98, 122],
+            &[6, 30, 54, 78, 102, 126],
+            &[6, 26, 52, 78, 104, 130],
+            &[6, 30, 56, 82, 108, 134],
+            &[6, 34, 60, 86, 112, 138],
+            &[6, 30, 58, 86, 114, 142],
+            &[6, 34, 62, 90, 118, 146],
+            &[6, 30, 54, 78, 102, 126, 150],
+            &[6, 24, 50, 76, 102, 128, 154],
+            &[6, 28, 54, 80, 106, 132, 158],
+            &[6, 32, 58, 84, 110, 136, 162],
+            &[6, 26, 54, 82, 110, 138, 166],
+            &[6, 30, 58, 86, 114, 142, 170],
+        ];
+
+        assert_eq!(get_alignment_pattern_locations(1).1, 0);
+        for (version, expected) in (2..=40).zip(expected) {
+            let (positions, count) = get_alignment_pattern_locations(version);
+            assert_eq!(&positions[..count], expected);
+        }
+    }
+
+    // Number of modules left for data once the function patterns and
+    // format and version information are in place
+    fn get_data_module_count(version: usize) -> usize {
+        let size = version * 4 + 17;
+        let mut modules = size * size;
+        modules -= 3 * 64 + 2 * (size - 16) + 1 + 2 * 15;
+        let (_, count) = get_alignment_pattern_locations(version);
+        if count > 0 {
+            // Alignment patterns on the timing patterns overlap them by 5 modules
+            modules -= 25 * (count * count - 3) - 10 * (count - 2);
+        }
+        if version >= 7 {
+            modules -= 2 * 18;
+        }
+        modules
+    }
+
+    #[test]
+    fn test_codeword_counts() {
+        for version in 1..=40 {
+            let modules = get_data_module_count(version);
+            // The leftover remainder bits are always 0, 3, 4 or 7
+            assert!([0, 3, 4, 7].contains(&(modules % 8)));
+
+            for level in LEVELS {
+                let template = QR::template(version, level);
+                assert_eq!(template.get_data_positions().len(), modules);
+
+                let [ecc_count, g1_blocks, g1_length, g2_blocks, g2_length] =
+                    ECC_DATA[version - 1][level as usize].map(|n| n as usize);
+                let total =
+                    g1_blocks * (g1_length + ecc_count) + g2_blocks * (g2_length + ecc_count);
+                assert_eq!(total, modules / 8, "version {version} {level:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_block_structure() {
+        for version in 1..=40 {
+            let mut ecc_counts = Vec::new();
+            for level in LEVELS {
+                let [ecc_count, g1_blocks, g1_length, g2_blocks, g2_length] =
+                    ECC_DATA[version - 1][level as usize];
+                assert!(g1_blocks > 0 && g1_length > 0);
+                // Blocks in the second group hold one more data codeword
+                if g2_blocks > 0 {
+                    assert_eq!(g2_length, g1_length + 1);
+                } else {
+                    assert_eq!(g2_length, 0);
+                }
+                // Every block must be able to correct at least one codeword
+                assert!((2..=30).contains(&ecc_count));
+                ecc_counts.push(ecc_count * (g1_blocks + g2_blocks));
+            }
+            // Higher levels have more error correction codewords
+            assert!(ecc_counts.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    // Number of bits needed to encode a number of characters in a mode
+    fn get_data_bits(mode: EncodingMode, chars: u32) -> u32 {
+        match mode {
+            EncodingMode::Numeric => chars / 3 * 10 + [0, 4, 7][chars as usize % 3],
+            EncodingMode::Alphanumeric => chars / 2 * 11 + chars % 2 * 6,
+            EncodingMode::Byte => chars * 8,
+        }
+    }
+
+    #[test]
+    fn test_character_capacities() {
+        let modes = [
+            EncodingMode::Numeric,
+            EncodingMode::Alphanumeric,
+            EncodingMode::Byte,
+        ];
+        for version in 1..=40 {
+            for level in LEVELS {
+                let info = ECC_DATA[version - 1][level as usize];
+                let available = (info[1] * info[2] + info[3] * info[4]) * 8;
+                for mode in modes {
+                    // The mode indicator, character count and data must fit,
+                    // and one more character must not
+                    let capacity = CHARACTER_CAPACITIES[version - 1][level as usize][mode as usize];
+                    let header = 4 + get_count_bits_size(version, mode);
+                    let fits = |chars| header + get_data_bits(mode, chars) <= available;
+                    assert!(
+                        fits(capacity as u32),
+                        "version {version} {level:?} {mode:?}"
+                    );
+                    assert!(
+                        !fits(capacity as u32 + 1),
+                        "version {version} {level:?} {mode:?}"
+                    );
+                }
+            }
+        }
     }
 }
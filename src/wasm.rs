@@ -0,0 +1,199 @@
+use crate::{Alignment, Caption, ErrorCorrection, Options, QrCode};
+use js_sys::{Object, Reflect, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+// Read a field of the options object, where a missing field is None
+fn get_field(options: &JsValue, name: &str) -> Result<Option<JsValue>, JsError> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(None);
+    }
+    let value = Reflect::get(options, &JsValue::from_str(name))
+        .map_err(|_| JsError::new("options must be an object"))?;
+    Ok(Some(value).filter(|value| !value.is_undefined() && !value.is_null()))
+}
+
+fn get_string(options: &JsValue, name: &str) -> Result<Option<String>, JsError> {
+    get_field(options, name)?
+        .map(|value| {
+            value
+                .as_string()
+                .ok_or_else(|| JsError::new(&format!("{} must be a string", name)))
+        })
+        .transpose()
+}
+
+fn get_number(options: &JsValue, name: &str) -> Result<Option<f64>, JsError> {
+    get_field(options, name)?
+        .map(|value| {
+            value
+                .as_f64()
+                .ok_or_else(|| JsError::new(&format!("{} must be a number", name)))
+        })
+        .transpose()
+}
+
+fn get_bool(options: &JsValue, name: &str) -> Result<bool, JsError> {
+    match get_field(options, name)? {
+        Some(value) => value
+            .as_bool()
+            .ok_or_else(|| JsError::new(&format!("{} must be a boolean", name))),
+        None => Ok(false),
+    }
+}
+
+fn to_js_error(message: String) -> JsError {
+    JsError::new(&message)
+}
+
+fn parse_level(level: Option<&str>) -> Result<ErrorCorrection, String> {
+    match level {
+        None => Ok(ErrorCorrection::Medium),
+        Some(level) => {
+            crate::parse_level(level).ok_or_else(|| "ec must be one of L, M, Q or H".to_string())
+        }
+    }
+}
+
+fn parse_alignment(alignment: Option<&str>) -> Result<Alignment, String> {
+    match alignment {
+        None | Some("center") => Ok(Alignment::Center),
+        Some("left") => Ok(Alignment::Left),
+        Some("right") => Ok(Alignment::Right),
+        Some(_) => Err("captionAlign must be left, center or right".to_string()),
+    }
+}
+
+// Numbers from javascript are floats, so fractions have to be turned away
+fn to_integer(name: &str, number: f64, min: u32, max: u32) -> Result<u32, String> {
+    if number.fract() == 0.0 && number >= min as f64 && number <= max as f64 {
+        Ok(number as u32)
+    } else {
+        Err(format!(
+            "{} must be a whole number from {} to {}",
+            name, min, max
+        ))
+    }
+}
+
+fn to_caption_size(size: f64) -> Result<f64, String> {
+    if size > 0.0 && size <= 100.0 {
+        Ok(size)
+    } else {
+        Err("captionSize must be a number above 0 and up to 100".to_string())
+    }
+}
+
+fn parse_color(name: &str, color: &str) -> Result<[u8; 3], String> {
+    crate::parse_color(color).ok_or_else(|| format!("{} must be a hex color like #1a2b3c", name))
+}
+
+// Turn the options object into the library's options, with the same defaults
+fn get_options(options: &JsValue) -> Result<(ErrorCorrection, Options), JsError> {
+    let level = parse_level(get_string(options, "ec")?.as_deref()).map_err(to_js_error)?;
+
+    let defaults = Options::default();
+    let mut caption = None;
+    if let Some(text) = get_string(options, "caption")? {
+        let alignment = parse_alignment(get_string(options, "captionAlign")?.as_deref())
+            .map_err(to_js_error)?;
+        let size = get_number(options, "captionSize")?
+            .map(to_caption_size)
+            .transpose()
+            .map_err(to_js_error)?;
+        caption = Some(Caption {
+            alignment,
+            size: size.unwrap_or(2.0),
+            ..Caption::new(&text)
+        });
+    }
+
+    let integer = |name: &str, min: u32, max: u32| -> Result<Option<u32>, JsError> {
+        get_number(options, name)?
+            .map(|number| to_integer(name, number, min, max))
+            .transpose()
+            .map_err(to_js_error)
+    };
+    let color = |name: &str| -> Result<Option<[u8; 3]>, JsError> {
+        get_string(options, name)?
+            .map(|color| parse_color(name, &color))
+            .transpose()
+            .map_err(to_js_error)
+    };
+    let options = Options {
+        inverted: get_bool(options, "inverted")?,
+        mirrored: get_bool(options, "mirrored")?,
+        version: integer("version", 1, 40)?.map(|version| version as usize),
+        mask: integer("mask", 0, 7)?.map(|mask| mask as usize),
+        scale: integer("scale", 1, 100)?.unwrap_or(defaults.scale),
+        quiet_zone: integer("quietZone", 0, 100)?.unwrap_or(defaults.quiet_zone),
+        foreground: color("foreground")?.unwrap_or(defaults.foreground),
+        background: color("background")?.unwrap_or(defaults.background),
+        caption,
+    };
+    Ok((level, options))
+}
+
+fn create(text: &str, options: &JsValue) -> Result<QrCode, JsError> {
+    let (level, options) = get_options(options)?;
+    QrCode::new(text, level, &options).map_err(|error| JsError::new(&error.to_string()))
+}
+
+/// Encode the text and get `{size, modules}`, where modules is a row major
+/// Uint8Array of the symbol without its quiet zone and 1 is a dark module
+#[wasm_bindgen]
+pub fn encode(text: &str, options: JsValue) -> Result<Object, JsError> {
+    let qr = create(text, &options)?;
+    let size = qr.size();
+    let modules: Vec<u8> = (0..size * size)
+        .map(|i| qr.is_dark(i % size, i / size) as u8)
+        .collect();
+
+    let encoded = Object::new();
+    let set = |name: &str, value: JsValue| Reflect::set(&encoded, &JsValue::from_str(name), &value);
+    set("size", JsValue::from(size as u32))
+        .and_then(|_| set("modules", Uint8Array::from(&modules[..]).into()))
+        .map_err(|_| JsError::new("unable to build the result"))?;
+    Ok(encoded)
+}
+
+/// Encode the text and draw it as an svg image
+#[wasm_bindgen(js_name = toSvg)]
+pub fn to_svg(text: &str, options: JsValue) -> Result<String, JsError> {
+    Ok(create(text, &options)?.to_svg())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::wasm::*;
+
+    #[test]
+    fn test_option_parsing() {
+        assert_eq!(parse_level(None), Ok(ErrorCorrection::Medium));
+        assert_eq!(parse_level(Some("H")), Ok(ErrorCorrection::High));
+        assert_eq!(parse_level(Some("h")), Ok(ErrorCorrection::High));
+        assert!(parse_level(Some("X")).is_err());
+        assert!(matches!(
+            parse_alignment(Some("right")),
+            Ok(Alignment::Right)
+        ));
+        assert!(parse_alignment(Some("middle")).is_err());
+
+        assert_eq!(to_integer("version", 40.0, 1, 40), Ok(40));
+        assert_eq!(
+            to_integer("version", 2.5, 1, 40),
+            Err("version must be a whole number from 1 to 40".to_string())
+        );
+        assert!(to_integer("version", 0.0, 1, 40).is_err());
+        assert!(to_integer("quietZone", -1.0, 0, 100).is_err());
+        assert!(to_integer("scale", f64::NAN, 1, 100).is_err());
+        assert!(to_integer("scale", f64::INFINITY, 1, 100).is_err());
+
+        assert_eq!(to_caption_size(0.5), Ok(0.5));
+        assert!(to_caption_size(0.0).is_err());
+        assert!(to_caption_size(100.5).is_err());
+
+        assert_eq!(parse_color("foreground", "#1a2B3c"), Ok([0x1a, 0x2b, 0x3c]));
+        assert_eq!(parse_color("foreground", "ffffff"), Ok([255; 3]));
+        assert!(parse_color("background", "#fff").is_err());
+    }
+}
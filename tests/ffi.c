@@ -0,0 +1,84 @@
+/* Checks that include/qrgen.h matches the library built with the ffi feature.
+ *
+ *   cargo build --lib --features ffi
+ *   cc -Wall -Werror -Iinclude tests/ffi.c target/debug/libqrgen.a -lm -lpthread -ldl -o target/ffi-test
+ *   target/ffi-test
+ */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "qrgen.h"
+
+static int failures = 0;
+
+#define CHECK(condition)                                                  \
+  do {                                                                    \
+    if (!(condition)) {                                                   \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+      failures++;                                                         \
+    }                                                                     \
+  } while (0)
+
+static QrStatus create(const char *text, const QrOptions *options, QrCode **code) {
+  return qr_create((const uint8_t *)text, strlen(text), options, code);
+}
+
+int main(void) {
+  QrOptions options = qr_default_options();
+  CHECK(options.ec == QR_EC_MEDIUM);
+  CHECK(options.version == 0);
+  CHECK(options.mask == -1);
+  CHECK(!options.inverted && !options.mirrored);
+  CHECK(options.scale == 10);
+  CHECK(options.quiet_zone == 4);
+  CHECK(options.foreground[0] == 0 && options.foreground[2] == 0);
+  CHECK(options.background[0] == 255 && options.background[2] == 255);
+
+  /* Every field is read from where the header puts it */
+  options.ec = QR_EC_QUARTILE;
+  options.version = 2;
+  options.mask = 3;
+  options.scale = 2;
+  options.quiet_zone = 1;
+  options.foreground[0] = 0x12;
+  options.foreground[1] = 0x34;
+  options.foreground[2] = 0x56;
+  QrCode *code = NULL;
+  CHECK(create("HELLO WORLD", &options, &code) == QR_OK);
+  CHECK(qr_size(code) == 25);
+
+  uint8_t modules[25 * 25];
+  CHECK(qr_get_modules(code, modules, sizeof modules) == QR_OK);
+  CHECK(modules[0] == 1 && modules[7] == 0);
+
+  size_t length = 0;
+  CHECK(qr_render(code, QR_FORMAT_SVG, NULL, 0, &length) == QR_BUFFER_TOO_SMALL);
+  char svg[8192];
+  CHECK(length < sizeof svg);
+  CHECK(qr_render(code, QR_FORMAT_SVG, (uint8_t *)svg, sizeof svg, &length) == QR_OK);
+  svg[length] = '\0';
+  CHECK(strstr(svg, "width=\"54\"") != NULL);
+  CHECK(strstr(svg, "#123456") != NULL);
+  qr_free(code);
+
+  options = qr_default_options();
+  options.version = 1;
+  CHECK(create("This text is far too long for a version 1 symbol", &options, &code) ==
+        QR_DATA_TOO_LONG);
+  CHECK(code == NULL);
+  options.version = 41;
+  CHECK(create("hello", &options, &code) == QR_INVALID_VERSION);
+  options.version = 0;
+  options.mask = 8;
+  CHECK(create("hello", &options, &code) == QR_INVALID_MASK);
+  CHECK(create("hello", NULL, NULL) == QR_NULL_POINTER);
+  CHECK(qr_status_message(QR_INTERNAL_ERROR) != NULL);
+
+  if (failures > 0) {
+    fprintf(stderr, "%d checks failed\n", failures);
+    return 1;
+  }
+  printf("all checks passed\n");
+  return 0;
+}